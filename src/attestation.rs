@@ -0,0 +1,180 @@
+//! TEE attestation.
+//!
+//! A playback device proves it runs on certified hardware by presenting its
+//! device certificate together with a challenge, chosen by whoever asks
+//! (typically a creator), signed with the device key.  The registry checks
+//! the certificate back to a registered, non-revoked manufacturer (see
+//! `devices`) and the challenge signature, and on success issues an
+//! [`AttestationToken`] signed with the registry signing key.  Tokens expire
+//! after `registry.attestation_ttl`, so a creator can accept one without
+//! asking the registry again, but not for long.  Nothing is stored.
+
+use std::fmt;
+use std::time::Duration;
+
+use rusqlite::Connection;
+use secp256k1::SecretKey;
+
+use crate::devices::{self, ChainError};
+use crate::signature::{sign_attestation_token, verify_attestation};
+use crate::types::{Attestation, AttestationToken, Manufacturer};
+
+/// Why an attestation was not accepted.
+#[derive(Debug)]
+pub enum AttestationError {
+    MissingChallenge,
+    InvalidChallengeSignature,
+    ExpiryOutOfRange,
+    Chain(ChainError),
+}
+
+impl AttestationError {
+    /// Machine-readable code, used as the `reason` in `AttestationVerdict`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AttestationError::MissingChallenge => "challenge_required",
+            AttestationError::InvalidChallengeSignature => "invalid_challenge_signature",
+            AttestationError::ExpiryOutOfRange => "internal",
+            AttestationError::Chain(e) => e.code(),
+        }
+    }
+}
+
+impl fmt::Display for AttestationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttestationError::MissingChallenge => {
+                write!(f, "challenge and challenge_signature are required")
+            }
+            AttestationError::InvalidChallengeSignature => {
+                write!(
+                    f,
+                    "Invalid challenge_signature: ECDSA verification failed against device_pk"
+                )
+            }
+            AttestationError::ExpiryOutOfRange => {
+                write!(
+                    f,
+                    "attestation_ttl puts expires_at past the latest representable date"
+                )
+            }
+            AttestationError::Chain(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for AttestationError {}
+
+impl From<ChainError> for AttestationError {
+    fn from(e: ChainError) -> Self {
+        AttestationError::Chain(e)
+    }
+}
+
+/// Check `att` and return the manufacturer that certified the device.
+pub fn verify(conn: &Connection, att: &Attestation) -> Result<Manufacturer, AttestationError> {
+    if att.challenge.is_empty() || att.challenge_signature.is_empty() {
+        return Err(AttestationError::MissingChallenge);
+    }
+    let manufacturer = devices::verify_chain(conn, &att.device)?;
+    if !verify_attestation(att) {
+        return Err(AttestationError::InvalidChallengeSignature);
+    }
+    Ok(manufacturer)
+}
+
+/// A token for a verified attestation, valid for `ttl` from now.
+/// `Config::validate` bounds the TTL, so the error only guards against
+/// settings that skipped validation.
+pub fn issue(
+    signing_key: &SecretKey,
+    att: &Attestation,
+    manufacturer: &Manufacturer,
+    ttl: Duration,
+) -> Result<AttestationToken, AttestationError> {
+    let now = chrono::Utc::now();
+    let expires = chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .ok_or(AttestationError::ExpiryOutOfRange)?;
+    let mut token = AttestationToken {
+        issuer: String::new(),
+        device_pk: att.device.device_pk.clone(),
+        manufacturer_id: manufacturer.id.clone(),
+        challenge: att.challenge.clone(),
+        issued_at: now.to_rfc3339(),
+        expires_at: expires.to_rfc3339(),
+        signature: String::new(),
+    };
+    sign_attestation_token(signing_key, &mut token);
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, init_db};
+    use crate::signature::{
+        pubkey_hex, sign_attestation, sign_device, sign_manufacturer, verify_attestation_token,
+    };
+    use crate::types::Device;
+
+    #[test]
+    fn attested_device_gets_a_signed_token() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let acme = SecretKey::from_slice(&[5; 32]).unwrap();
+        let device_key = SecretKey::from_slice(&[6; 32]).unwrap();
+        let registry = SecretKey::from_slice(&[8; 32]).unwrap();
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(&acme, &mut mfr);
+        db::insert_manufacturer(&conn, &mfr).unwrap();
+
+        let mut device = Device {
+            device_pk: pubkey_hex(&device_key),
+            manufacturer_pk: String::new(),
+            model: "Player 1".into(),
+            issued_at: "2026-02-01T00:00:00Z".into(),
+            manufacturer_signature: String::new(),
+        };
+        sign_device(&acme, &mut device);
+        let mut att = Attestation {
+            device,
+            challenge: "nonce-1".into(),
+            challenge_signature: String::new(),
+        };
+        sign_attestation(&device_key, &mut att);
+
+        let manufacturer = verify(&conn, &att).unwrap();
+        let token = issue(&registry, &att, &manufacturer, Duration::from_secs(300)).unwrap();
+        assert_eq!(
+            (token.issuer.clone(), token.manufacturer_id.clone()),
+            (pubkey_hex(&registry), mfr.pk_hex)
+        );
+        assert!(verify_attestation_token(&token));
+        assert!(matches!(
+            issue(&registry, &att, &manufacturer, Duration::MAX),
+            Err(AttestationError::ExpiryOutOfRange)
+        ));
+
+        // A replayed answer does not cover a new challenge
+        let replayed = Attestation {
+            challenge: "nonce-2".into(),
+            ..att.clone()
+        };
+        assert!(matches!(
+            verify(&conn, &replayed),
+            Err(AttestationError::InvalidChallengeSignature)
+        ));
+
+        // Signed by someone other than the device
+        sign_attestation(&acme, &mut att);
+        assert_eq!(
+            verify(&conn, &att).unwrap_err().code(),
+            "invalid_challenge_signature"
+        );
+    }
+}
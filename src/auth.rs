@@ -0,0 +1,176 @@
+//! Operator authorization.
+//!
+//! Admin routes (see [`RouteGroup::Admin`]) and manufacturer registration
+//! require `Authorization: Bearer <token>` matching `server.admin_token`
+//! (`--admin-token`); anything else gets `401 unauthorized`.  Without a
+//! token configured they fail closed with `503 admin_disabled`, and `serve`
+//! warns about it.
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+
+use crate::cors::RouteGroup;
+use crate::error::ApiError;
+use crate::types::AppState;
+
+/// Whether `method` and `path` need the admin token.
+pub fn requires_admin(method: &Method, path: &str) -> bool {
+    RouteGroup::of(method, path) == RouteGroup::Admin
+        || (method == Method::POST && path.ends_with("/manufacturers"))
+}
+
+/// Compare digests so the time taken does not depend on how much of the
+/// token matched.
+fn token_matches(presented: &str, expected: &str) -> bool {
+    let (a, b) = (Sha256::digest(presented), Sha256::digest(expected));
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+/// Middleware enforcing the admin token.
+pub async fn require_admin(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !requires_admin(req.method(), req.uri().path()) {
+        return next.run(req).await;
+    }
+    let Some(expected) = state.config.read().unwrap().server.admin_token.clone() else {
+        return ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "admin_disabled",
+            "No admin token configured; set server.admin_token or --admin-token",
+        )
+        .into_response();
+    };
+    let presented = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if presented.is_some_and(|t| token_matches(t.trim(), &expected)) {
+        return next.run(req).await;
+    }
+    let mut res = ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "unauthorized",
+        "Admin token required: send Authorization: Bearer <token>",
+    )
+    .into_response();
+    res.headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::init_db;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use rusqlite::Connection;
+    use tower::ServiceExt;
+
+    fn app(token: Option<&str>) -> Router {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let mut config = Config::default();
+        config.server.admin_token = token.map(String::from);
+        let state = AppState::new(conn, config);
+        Router::new()
+            .route(
+                "/api/v2/manufacturers",
+                get(|| async { "list" }).post(|| async { "registered" }),
+            )
+            .route("/api/v2/admin/export", get(|| async { "export" }))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                require_admin,
+            ))
+            .with_state(state)
+    }
+
+    async fn status(app: &Router, method: Method, path: &str, auth: Option<&str>) -> StatusCode {
+        let mut req = Request::builder().method(method).uri(path);
+        if let Some(auth) = auth {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+        app.clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn classifies_admin_routes() {
+        assert!(requires_admin(&Method::POST, "/api/v2/manufacturers"));
+        assert!(requires_admin(&Method::DELETE, "/api/listings"));
+        assert!(requires_admin(&Method::GET, "/api/v2/admin/export"));
+        assert!(!requires_admin(&Method::GET, "/api/v2/manufacturers"));
+        assert!(!requires_admin(&Method::POST, "/api/v2/listings"));
+    }
+
+    #[tokio::test]
+    async fn token_guards_admin_routes() {
+        let guarded = app(Some("s3cret"));
+        let post = Method::POST;
+        assert_eq!(
+            status(&guarded, post.clone(), "/api/v2/manufacturers", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(
+                &guarded,
+                post.clone(),
+                "/api/v2/manufacturers",
+                Some("Bearer wrong")
+            )
+            .await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(
+                &guarded,
+                post,
+                "/api/v2/manufacturers",
+                Some("Bearer s3cret")
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&guarded, Method::GET, "/api/v2/admin/export", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&guarded, Method::GET, "/api/v2/manufacturers", None).await,
+            StatusCode::OK
+        );
+
+        // No token configured: closed
+        let unset = app(None);
+        assert_eq!(
+            status(
+                &unset,
+                Method::POST,
+                "/api/v2/manufacturers",
+                Some("Bearer s3cret")
+            )
+            .await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(&unset, Method::GET, "/api/v2/admin/export", None).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(&unset, Method::GET, "/api/v2/manufacturers", None).await,
+            StatusCode::OK
+        );
+    }
+}
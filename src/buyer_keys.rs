@@ -0,0 +1,219 @@
+//! The buyer PRE key directory.
+//!
+//! To re-key a capsule for a buyer, a creator needs the buyer's proxy
+//! re-encryption public key.  Buyers publish it with
+//! `POST /buyer-keys`, signed by their Lightning node key, so a creator
+//! who knows who paid (the node pubkey on the payment) can look the key up
+//! and trust it came from that node.  Publishing a key with a later
+//! `published_at` supersedes the current one; old keys stay in the node's
+//! history so capsules re-keyed for them can still be matched up.
+//! `published_at` may not be ahead of the registry's clock by more than
+//! [`MAX_CLOCK_SKEW`], so a far-future key cannot block every later one.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db;
+use crate::pre::{self, CapsuleError, PreField};
+use crate::proxies::MAX_CLOCK_SKEW;
+use crate::signature::{is_compressed_pubkey, verify_buyer_key};
+use crate::types::BuyerKey;
+use crate::versioning::parse_cutoff;
+
+/// Why a buyer key was refused.
+#[derive(Debug)]
+pub enum BuyerKeyError {
+    InvalidNodePubkey,
+    UnknownScheme(String),
+    InvalidKey(CapsuleError),
+    InvalidTimestamp,
+    /// Not newer than the node's current key
+    Stale,
+    InvalidSignature,
+    Db(rusqlite::Error),
+}
+
+impl BuyerKeyError {
+    /// Machine-readable API error code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BuyerKeyError::InvalidNodePubkey => "invalid_pubkey",
+            BuyerKeyError::UnknownScheme(_) => "unknown_scheme",
+            BuyerKeyError::InvalidKey(_) => "invalid_pre_key",
+            BuyerKeyError::InvalidTimestamp => "invalid_timestamp",
+            BuyerKeyError::Stale => "stale_key",
+            BuyerKeyError::InvalidSignature => "invalid_signature",
+            BuyerKeyError::Db(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for BuyerKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuyerKeyError::InvalidNodePubkey => {
+                write!(f, "node_pubkey is not a compressed secp256k1 public key")
+            }
+            BuyerKeyError::UnknownScheme(s) => {
+                let known: Vec<&str> = pre::SCHEMES.iter().map(|s| s.name()).collect();
+                write!(
+                    f,
+                    "Unknown scheme '{}': expected one of {}",
+                    s,
+                    known.join(", ")
+                )
+            }
+            BuyerKeyError::InvalidKey(e) => e.fmt(f),
+            BuyerKeyError::InvalidTimestamp => {
+                write!(
+                    f,
+                    "published_at must be an RFC 3339 timestamp, not in the future"
+                )
+            }
+            BuyerKeyError::Stale => {
+                write!(f, "published_at must be later than the current key's")
+            }
+            BuyerKeyError::InvalidSignature => {
+                write!(
+                    f,
+                    "Invalid signature: ECDSA verification failed against node_pubkey"
+                )
+            }
+            BuyerKeyError::Db(e) => write!(f, "database: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BuyerKeyError {}
+
+impl From<rusqlite::Error> for BuyerKeyError {
+    fn from(e: rusqlite::Error) -> Self {
+        BuyerKeyError::Db(e)
+    }
+}
+
+fn published(key: &BuyerKey) -> Result<DateTime<Utc>, BuyerKeyError> {
+    parse_cutoff(&key.published_at).map_err(|_| BuyerKeyError::InvalidTimestamp)
+}
+
+/// Make `key` the current key of its node as of `now`, superseding the
+/// previous one.  Returns the key as stored.
+pub fn publish(
+    conn: &Connection,
+    key: &BuyerKey,
+    now: DateTime<Utc>,
+) -> Result<BuyerKey, BuyerKeyError> {
+    check(conn, key, now)?;
+    Ok(store(conn, key)?)
+}
+
+/// Every check [`publish`] makes, signature last, without storing anything.
+pub fn check(conn: &Connection, key: &BuyerKey, now: DateTime<Utc>) -> Result<(), BuyerKeyError> {
+    if !is_compressed_pubkey(&key.node_pubkey) {
+        return Err(BuyerKeyError::InvalidNodePubkey);
+    }
+    let scheme =
+        pre::scheme(&key.scheme).ok_or_else(|| BuyerKeyError::UnknownScheme(key.scheme.clone()))?;
+    pre::check_hex(scheme, PreField::PkBuyer, &key.pre_pk_hex)
+        .map_err(BuyerKeyError::InvalidKey)?;
+    let published_at = published(key)?;
+    if published_at > now + MAX_CLOCK_SKEW {
+        return Err(BuyerKeyError::InvalidTimestamp);
+    }
+    if let Some(current) = db::current_buyer_key(conn, &key.node_pubkey).optional()? {
+        if published(&current).is_ok_and(|at| at >= published_at) {
+            return Err(BuyerKeyError::Stale);
+        }
+    }
+    if !verify_buyer_key(key) {
+        return Err(BuyerKeyError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Store `key`, which passed [`check`], as its node's current key.
+pub fn store(conn: &Connection, key: &BuyerKey) -> rusqlite::Result<BuyerKey> {
+    let stored = BuyerKey {
+        superseded_at: String::new(),
+        ..key.clone()
+    };
+    db::supersede_buyer_key(conn, &key.node_pubkey, &key.published_at)?;
+    db::insert_buyer_key(conn, &stored)?;
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::pre::{g1, DEFAULT_PRE_SCHEME};
+    use crate::signature::sign_buyer_key;
+    use secp256k1::SecretKey;
+
+    #[test]
+    fn newer_keys_supersede_older_ones() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let node = SecretKey::from_slice(&[3; 32]).unwrap();
+        let now = parse_cutoff("2026-07-01T00:00:00Z").unwrap();
+        let mut first = BuyerKey {
+            node_pubkey: String::new(),
+            scheme: DEFAULT_PRE_SCHEME.into(),
+            pre_pk_hex: g1(11),
+            published_at: "2026-05-01T00:00:00Z".into(),
+            superseded_at: String::new(),
+            signature: String::new(),
+        };
+        sign_buyer_key(&node, &mut first);
+        publish(&conn, &first, now).unwrap();
+
+        let mut second = BuyerKey {
+            pre_pk_hex: g1(12),
+            published_at: "2026-06-01T00:00:00Z".into(),
+            ..first.clone()
+        };
+        let mut tampered = second.clone();
+        sign_buyer_key(&node, &mut tampered);
+        tampered.pre_pk_hex = g1(13);
+        assert!(matches!(
+            publish(&conn, &tampered, now),
+            Err(BuyerKeyError::InvalidSignature)
+        ));
+        sign_buyer_key(&node, &mut second);
+        publish(&conn, &second, now).unwrap();
+        // Replaying the first key cannot roll the directory back
+        assert!(matches!(
+            publish(&conn, &first, now),
+            Err(BuyerKeyError::Stale)
+        ));
+
+        let current = db::current_buyer_key(&conn, &first.node_pubkey).unwrap();
+        assert_eq!(current.pre_pk_hex, second.pre_pk_hex);
+        let history = db::buyer_key_history(&conn, &first.node_pubkey).unwrap();
+        let superseded: Vec<&str> = history.iter().map(|k| k.superseded_at.as_str()).collect();
+        assert_eq!(superseded, ["", "2026-06-01T00:00:00Z"]);
+
+        let bad_key = BuyerKey {
+            pre_pk_hex: "02".repeat(33),
+            published_at: "2026-07-01T00:00:00Z".into(),
+            ..second
+        };
+        assert_eq!(
+            publish(&conn, &bad_key, now).unwrap_err().code(),
+            "invalid_pre_key"
+        );
+
+        // A key from the far future would make every later rotation stale
+        let mut future = BuyerKey {
+            published_at: "9999-01-01T00:00:00Z".into(),
+            ..first
+        };
+        sign_buyer_key(&node, &mut future);
+        assert!(matches!(
+            publish(&conn, &future, now),
+            Err(BuyerKeyError::InvalidTimestamp)
+        ));
+    }
+}
@@ -0,0 +1,438 @@
+//! Typed async client for the Conduit Registry API.
+//!
+//! Talks to the `/api/v2` endpoints and reuses the server's own types
+//! (`ContentListing`, `SeederAnnouncement`, `Manufacturer`, ...), so the
+//! creator, seeder and buyer nodes no longer hand-roll JSON.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), conduit_registry::client::ClientError> {
+//! use conduit_registry::client::RegistryClient;
+//! use conduit_registry::types::SearchParams;
+//!
+//! let registry = RegistryClient::new("http://157.230.238.79:3003");
+//! let params = SearchParams { q: Some("lecture".into()), ..Default::default() };
+//! for listing in registry.search(&params, None).await?.items {
+//!     println!("{} {}", listing.content_hash, listing.file_name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Transport failures and `429`/`502`/`503`/`504` responses are retried with
+//! exponential backoff (honouring `Retry-After`), per [`RetryPolicy`].
+//! Every other error status comes back as [`ClientError::Api`] carrying the
+//! server's machine-readable code.
+
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::{header, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::error::code_for_status;
+use crate::info::RegistryInfo;
+use crate::types::{
+    Attestation, AttestationVerdict, BuyerKey, ContentListing, Device, DeviceResponse,
+    DeviceVerification, DiscoverResponse, ErrorEnvelope, Items, KeysAtParams, Manufacturer,
+    ManufacturerKey, Page, Paged, ProxyAnnouncement, ProxyParams, RevocationList, SearchParams,
+    SeederAnnouncement,
+};
+use crate::versioning::V2_PREFIX;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request could not be sent or the response not read.
+    Http(reqwest::Error),
+    /// The registry answered with an error status.
+    Api {
+        status: StatusCode,
+        /// Machine-readable code, e.g. `not_found`, `invalid_signature`
+        code: String,
+        message: String,
+        /// From `Retry-After`, on `rate_limited`
+        retry_after: Option<Duration>,
+    },
+    /// A success response whose body did not match the expected type.
+    Decode(String),
+}
+
+impl ClientError {
+    /// The API error code, if the registry returned one.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ClientError::Api { status, .. } if *status == StatusCode::NOT_FOUND)
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "registry request failed: {}", e),
+            ClientError::Api {
+                status,
+                code,
+                message,
+                ..
+            } => {
+                write!(
+                    f,
+                    "registry returned {} ({}): {}",
+                    status.as_u16(),
+                    code,
+                    message
+                )
+            }
+            ClientError::Decode(e) => write!(f, "unexpected registry response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+/// How transient failures are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each subsequent one
+    pub initial_backoff: Duration,
+    /// Upper bound on any single delay, including `Retry-After`
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    res.headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+fn page_query(page: Option<Page>) -> Vec<(&'static str, u32)> {
+    page.map(|p| vec![("limit", p.limit), ("offset", p.offset)])
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistryClient {
+    http: reqwest::Client,
+    base: String,
+    retry: RetryPolicy,
+}
+
+impl RegistryClient {
+    /// `base_url` is the registry root, e.g. `http://localhost:3003`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        RegistryClient {
+            http: reqwest::Client::new(),
+            base: base_url.into().trim_end_matches('/').to_string(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Use a preconfigured `reqwest::Client` (timeouts, proxies, ...).
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.base, V2_PREFIX, path)
+    }
+
+    /// Send a request, retrying transient failures, and return the
+    /// successful response.  `build` is called once per attempt.
+    async fn send(
+        &self,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<reqwest::Response, ClientError> {
+        let mut attempt = 0;
+        loop {
+            let retries_left = attempt < self.retry.max_retries;
+            let res = match build().send().await {
+                Ok(res) => res,
+                Err(e) if retries_left && (e.is_connect() || e.is_timeout()) => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let status = res.status();
+            if status.is_success() {
+                return Ok(res);
+            }
+            let wait = retry_after(&res);
+            if retries_left && is_retryable(status) {
+                let delay = wait.unwrap_or_else(|| self.retry.backoff(attempt));
+                tokio::time::sleep(delay.min(self.retry.max_backoff)).await;
+                attempt += 1;
+                continue;
+            }
+            return Err(api_error(res, wait).await);
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &(impl serde::Serialize + ?Sized),
+    ) -> Result<T, ClientError> {
+        let url = self.url(path);
+        let res = self.send(|| self.http.get(&url).query(query)).await?;
+        decode(res).await
+    }
+
+    async fn post_json(&self, path: &str, body: &impl serde::Serialize) -> Result<(), ClientError> {
+        let url = self.url(path);
+        self.send(|| self.http.post(&url).json(body)).await?;
+        Ok(())
+    }
+
+    async fn post_for<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl serde::Serialize,
+    ) -> Result<T, ClientError> {
+        let url = self.url(path);
+        let res = self.send(|| self.http.post(&url).json(body)).await?;
+        decode(res).await
+    }
+
+    /// The registry's information document, for capability checks.
+    pub async fn info(&self) -> Result<RegistryInfo, ClientError> {
+        self.get_json("/info", &()).await
+    }
+
+    /// Publish a signed content listing.
+    pub async fn publish(&self, listing: &ContentListing) -> Result<(), ClientError> {
+        self.post_json("/listings", listing).await
+    }
+
+    pub async fn listing(&self, content_hash: &str) -> Result<ContentListing, ClientError> {
+        self.get_json(&format!("/listings/{}", content_hash), &())
+            .await
+    }
+
+    /// One page of search results; `None` uses the server's default page.
+    pub async fn search(
+        &self,
+        params: &SearchParams,
+        page: Option<Page>,
+    ) -> Result<Paged<ContentListing>, ClientError> {
+        let url = self.url("/search");
+        let query = page_query(page);
+        let res = self
+            .send(|| self.http.get(&url).query(params).query(&query))
+            .await?;
+        decode(res).await
+    }
+
+    /// The listing for `content_hash` and everyone seeding it.
+    pub async fn discover(&self, content_hash: &str) -> Result<DiscoverResponse, ClientError> {
+        self.get_json(&format!("/discover/{}", content_hash), &())
+            .await
+    }
+
+    /// Announce that this node seeds `announcement.encrypted_hash`.
+    pub async fn announce(&self, announcement: &SeederAnnouncement) -> Result<(), ClientError> {
+        self.post_json("/seeders", announcement).await
+    }
+
+    pub async fn manufacturer(&self, pk_hex: &str) -> Result<Manufacturer, ClientError> {
+        self.get_json(&format!("/manufacturers/{}", pk_hex), &())
+            .await
+    }
+
+    pub async fn manufacturers(
+        &self,
+        page: Option<Page>,
+    ) -> Result<Paged<Manufacturer>, ClientError> {
+        self.get_json("/manufacturers", &page_query(page)).await
+    }
+
+    /// The keys manufacturer `id` may sign with at `at` (RFC 3339); `None`
+    /// means now.
+    pub async fn manufacturer_keys(
+        &self,
+        id: &str,
+        at: Option<&str>,
+    ) -> Result<Vec<ManufacturerKey>, ClientError> {
+        let query = KeysAtParams {
+            at: at.map(str::to_string),
+        };
+        let keys: Items<ManufacturerKey> = self
+            .get_json(&format!("/manufacturers/{}/keys", id), &query)
+            .await?;
+        Ok(keys.items)
+    }
+
+    /// Add a key signed (with `signature::sign_rotation`) by a current key
+    /// of the manufacturer; returns the key as stored.
+    pub async fn rotate_key(&self, key: &ManufacturerKey) -> Result<ManufacturerKey, ClientError> {
+        self.post_for(&format!("/manufacturers/{}/keys", key.manufacturer_id), key)
+            .await
+    }
+
+    /// The signed manufacturer revocation list.  Check it with
+    /// `signature::verify_revocation_list` and compare `issuer` with the
+    /// operator key you trust.
+    pub async fn revocations(&self) -> Result<RevocationList, ClientError> {
+        self.get_json("/manufacturers/revocations", &()).await
+    }
+
+    /// Register a manufacturer-signed device certificate.
+    pub async fn register_device(&self, device: &Device) -> Result<(), ClientError> {
+        self.post_json("/devices", device).await
+    }
+
+    /// Check a device attestation; a valid verdict carries a signed token
+    /// (see `signature::verify_attestation_token`).
+    pub async fn attest(
+        &self,
+        attestation: &Attestation,
+    ) -> Result<AttestationVerdict, ClientError> {
+        self.post_for("/attestations/verify", attestation).await
+    }
+
+    /// A registered device and its issuing manufacturer.
+    pub async fn device(&self, device_pk: &str) -> Result<DeviceResponse, ClientError> {
+        self.get_json(&format!("/devices/{}", device_pk), &()).await
+    }
+
+    /// Check a device certificate against the registered manufacturers.
+    pub async fn verify_device(&self, device: &Device) -> Result<DeviceVerification, ClientError> {
+        self.post_for("/devices/verify", device).await
+    }
+
+    /// Publish a buyer PRE key signed (with `signature::sign_buyer_key`) by
+    /// the buyer's node key; returns the key as stored.
+    pub async fn publish_buyer_key(&self, key: &BuyerKey) -> Result<BuyerKey, ClientError> {
+        self.post_for("/buyer-keys", key).await
+    }
+
+    /// The current PRE key of the buyer with node key `node_pubkey`.
+    /// Check it with `signature::verify_buyer_key` before re-keying for it.
+    pub async fn buyer_key(&self, node_pubkey: &str) -> Result<BuyerKey, ClientError> {
+        self.get_json(&format!("/buyer-keys/{}", node_pubkey), &())
+            .await
+    }
+
+    /// Every PRE key the buyer has published, newest first.
+    pub async fn buyer_key_history(&self, node_pubkey: &str) -> Result<Vec<BuyerKey>, ClientError> {
+        let keys: Items<BuyerKey> = self
+            .get_json(&format!("/buyer-keys/{}/history", node_pubkey), &())
+            .await?;
+        Ok(keys.items)
+    }
+
+    /// Announce a re-encryption proxy, signed with `signature::sign_proxy`.
+    /// Announce again before `ttl_secs` runs out to stay listed.
+    pub async fn announce_proxy(
+        &self,
+        announcement: &ProxyAnnouncement,
+    ) -> Result<(), ClientError> {
+        self.post_json("/proxies", announcement).await
+    }
+
+    /// Live proxies, cheapest first, optionally only those supporting
+    /// `scheme`.
+    pub async fn proxies(
+        &self,
+        scheme: Option<&str>,
+        page: Option<Page>,
+    ) -> Result<Paged<ProxyAnnouncement>, ClientError> {
+        let url = self.url("/proxies");
+        let params = ProxyParams {
+            scheme: scheme.map(str::to_string),
+        };
+        let query = page_query(page);
+        let res = self
+            .send(|| self.http.get(&url).query(&params).query(&query))
+            .await?;
+        decode(res).await
+    }
+}
+
+async fn decode<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, ClientError> {
+    let bytes = res.bytes().await?;
+    serde_json::from_slice(&bytes).map_err(|e| ClientError::Decode(e.to_string()))
+}
+
+async fn api_error(res: reqwest::Response, retry_after: Option<Duration>) -> ClientError {
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    let (code, message) = match serde_json::from_str::<ErrorEnvelope>(&body) {
+        Ok(env) => (env.error.code, env.error.message),
+        Err(_) => (code_for_status(status).to_string(), body.trim().to_string()),
+    };
+    ClientError::Api {
+        status,
+        code,
+        message,
+        retry_after,
+    }
+}
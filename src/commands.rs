@@ -0,0 +1,547 @@
+//! Offline subcommands of the `conduit-registry` binary.
+//!
+//! Creator and manufacturer tooling (`sign-listing`, `verify-listing`,
+//! `sign-manufacturer`, `sign-rotation`, `sign-device`) works on JSON files; operator tooling
+//! (`list`, `show`, `search`, `purge`, `export`, `import`) opens the SQLite
+//! database directly, so it also works while the server is down.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use rusqlite::{Connection, OpenFlags};
+use secp256k1::SecretKey;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use conduit_registry::config::DEFAULT_DB_PATH;
+use conduit_registry::db;
+use conduit_registry::export::{self, OnInvalid};
+use conduit_registry::limits::DEFAULT_PAGE_SIZE;
+use conduit_registry::pre::DEFAULT_PRE_SCHEME;
+use conduit_registry::proxies;
+use conduit_registry::signature::{
+    sign_buyer_key, sign_device, sign_listing, sign_manufacturer, sign_rotation, verify_listing,
+};
+use conduit_registry::snapshot;
+use conduit_registry::types::{
+    BuyerKey, ContentListing, Device, DiscoverResponse, Manufacturer, ManufacturerKey, Page,
+    SearchParams,
+};
+
+#[derive(Args)]
+pub struct DbArgs {
+    /// Path to the SQLite database file
+    #[arg(long, env = "CONDUIT_REGISTRY_DB_PATH", default_value = DEFAULT_DB_PATH)]
+    pub db_path: String,
+}
+
+impl DbArgs {
+    /// Open an existing database and bring its schema up to date.  Unlike
+    /// `serve`, never creates a new file: a mistyped path is an error.
+    fn open(&self) -> Result<Connection, String> {
+        let conn = Connection::open_with_flags(
+            &self.db_path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("cannot open {}: {}", self.db_path, e))?;
+        db::init_db(&conn);
+        Ok(conn)
+    }
+
+    /// Open the database, creating it if needed.
+    fn open_or_create(&self) -> Result<Connection, String> {
+        let conn = Connection::open(&self.db_path)
+            .map_err(|e| format!("cannot open {}: {}", self.db_path, e))?;
+        db::init_db(&conn);
+        Ok(conn)
+    }
+}
+
+fn read_input(path: &Path) -> Result<String, String> {
+    let mut buf = String::new();
+    if path == Path::new("-") {
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .map_err(|e| format!("reading stdin: {}", e))?;
+    } else {
+        buf = std::fs::read_to_string(path)
+            .map_err(|e| format!("reading {}: {}", path.display(), e))?;
+    }
+    Ok(buf)
+}
+
+fn read_listing(path: &Path) -> Result<ContentListing, String> {
+    serde_json::from_str(&read_input(path)?).map_err(|e| format!("invalid listing JSON: {}", e))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn parse_secret_key(hex_key: &str) -> Result<SecretKey, String> {
+    let bytes = hex::decode(hex_key.trim()).map_err(|e| format!("secret key: {}", e))?;
+    SecretKey::from_slice(&bytes).map_err(|e| format!("secret key: {}", e))
+}
+
+/// Read a `what` document from `file`, let `sign` sign it in place, then
+/// write it to `output` (reporting the summary `sign` returns) or stdout.
+fn sign_file<T: Serialize + DeserializeOwned>(
+    file: &Path,
+    secret_key: &str,
+    output: Option<&Path>,
+    what: &str,
+    sign: impl FnOnce(&SecretKey, &mut T) -> String,
+) -> Result<(), String> {
+    let secret_key = parse_secret_key(secret_key)?;
+    let mut value: T = serde_json::from_str(&read_input(file)?)
+        .map_err(|e| format!("invalid {} JSON: {}", what, e))?;
+    let summary = sign(&secret_key, &mut value);
+
+    let json = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, json + "\n")
+                .map_err(|e| format!("writing {}: {}", path.display(), e))?;
+            eprintln!("{}", summary);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Creator tooling
+// ---------------------------------------------------------------------------
+
+#[derive(Args)]
+pub struct SignListingArgs {
+    /// Listing JSON file, or `-` for stdin
+    pub file: PathBuf,
+
+    /// Creator secret key (64 hex chars); sets `creator_pubkey` accordingly
+    #[arg(long, env = "CONDUIT_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: String,
+
+    /// Write the signed listing here instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+pub fn sign_listing_cmd(args: SignListingArgs) -> Result<(), String> {
+    sign_file(
+        &args.file,
+        &args.secret_key,
+        args.output.as_deref(),
+        "listing",
+        |sk, listing: &mut ContentListing| {
+            sign_listing(sk, listing);
+            format!(
+                "Signed {} as {}",
+                listing.content_hash, listing.creator_pubkey
+            )
+        },
+    )
+}
+
+#[derive(Args)]
+pub struct VerifyListingArgs {
+    /// Listing JSON file, or `-` for stdin
+    pub file: PathBuf,
+}
+
+pub fn verify_listing_cmd(args: VerifyListingArgs) -> Result<(), String> {
+    let listing = read_listing(&args.file)?;
+    if listing.creator_signature.is_empty() {
+        return Err("listing has no creator_signature".into());
+    }
+    if !verify_listing(&listing) {
+        return Err(format!(
+            "signature INVALID for {} (creator {})",
+            listing.content_hash, listing.creator_pubkey
+        ));
+    }
+    println!(
+        "OK: {} signed by {}",
+        listing.content_hash, listing.creator_pubkey
+    );
+    Ok(())
+}
+
+#[derive(Args)]
+pub struct SignManufacturerArgs {
+    /// Manufacturer JSON file, or `-` for stdin
+    pub file: PathBuf,
+
+    /// Manufacturer secret key (64 hex chars); sets `pk_hex` accordingly
+    #[arg(long, env = "CONDUIT_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: String,
+
+    /// Write the signed manufacturer here instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// Sign a manufacturer registration, stamping `registered_at` if unset
+/// since the signature covers it.
+pub fn sign_manufacturer_cmd(args: SignManufacturerArgs) -> Result<(), String> {
+    sign_file(
+        &args.file,
+        &args.secret_key,
+        args.output.as_deref(),
+        "manufacturer",
+        |sk, mfr: &mut Manufacturer| {
+            if mfr.registered_at.is_empty() {
+                mfr.registered_at = chrono::Utc::now().to_rfc3339();
+            }
+            sign_manufacturer(sk, mfr);
+            format!("Signed {} as {}", mfr.name, mfr.pk_hex)
+        },
+    )
+}
+
+#[derive(Args)]
+pub struct SignRotationArgs {
+    /// Manufacturer key JSON file, or `-` for stdin
+    pub file: PathBuf,
+
+    /// Secret key (64 hex chars) of a current manufacturer key; sets `signed_by`
+    #[arg(long, env = "CONDUIT_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: String,
+
+    /// Write the signed key here instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// Authorize a new manufacturer key with a current one, stamping
+/// `not_before` if unset since the registry refuses windows that open in
+/// the past.
+pub fn sign_rotation_cmd(args: SignRotationArgs) -> Result<(), String> {
+    sign_file(
+        &args.file,
+        &args.secret_key,
+        args.output.as_deref(),
+        "manufacturer key",
+        |sk, key: &mut ManufacturerKey| {
+            if key.not_before.is_empty() {
+                key.not_before = chrono::Utc::now().to_rfc3339();
+            }
+            sign_rotation(sk, key);
+            format!("Authorized {} with {}", key.pk_hex, key.signed_by)
+        },
+    )
+}
+
+#[derive(Args)]
+pub struct SignBuyerKeyArgs {
+    /// Buyer key JSON file, or `-` for stdin
+    pub file: PathBuf,
+
+    /// Node secret key (64 hex chars); sets `node_pubkey` accordingly
+    #[arg(long, env = "CONDUIT_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: String,
+
+    /// Write the signed key here instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// Sign a buyer PRE key with the node key, stamping `published_at` if
+/// unset since the signature covers it.
+pub fn sign_buyer_key_cmd(args: SignBuyerKeyArgs) -> Result<(), String> {
+    sign_file(
+        &args.file,
+        &args.secret_key,
+        args.output.as_deref(),
+        "buyer key",
+        |sk, key: &mut BuyerKey| {
+            if key.published_at.is_empty() {
+                key.published_at = chrono::Utc::now().to_rfc3339();
+            }
+            sign_buyer_key(sk, key);
+            format!("Signed {} key for {}", key.scheme, key.node_pubkey)
+        },
+    )
+}
+
+#[derive(Args)]
+pub struct SignDeviceArgs {
+    /// Device JSON file, or `-` for stdin
+    pub file: PathBuf,
+
+    /// Manufacturer secret key (64 hex chars); sets `manufacturer_pk` accordingly
+    #[arg(long, env = "CONDUIT_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: String,
+
+    /// Write the certificate here instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// Certify a device key with the manufacturer key, stamping `issued_at` if
+/// unset since the signature covers it.
+pub fn sign_device_cmd(args: SignDeviceArgs) -> Result<(), String> {
+    sign_file(
+        &args.file,
+        &args.secret_key,
+        args.output.as_deref(),
+        "device",
+        |sk, device: &mut Device| {
+            if device.issued_at.is_empty() {
+                device.issued_at = chrono::Utc::now().to_rfc3339();
+            }
+            sign_device(sk, device);
+            format!(
+                "Certified {} under {}",
+                device.device_pk, device.manufacturer_pk
+            )
+        },
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Operator tooling
+// ---------------------------------------------------------------------------
+
+#[derive(Args)]
+pub struct ListArgs {
+    #[command(flatten)]
+    pub db: DbArgs,
+
+    /// Maximum number of listings to print
+    #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+    pub limit: u32,
+
+    /// Number of listings to skip
+    #[arg(long, default_value_t = 0)]
+    pub offset: u32,
+
+    /// Print JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Substring match on file_name
+    pub q: Option<String>,
+
+    /// File extension, e.g. `mp4`
+    #[arg(long = "type")]
+    pub content_type: Option<String>,
+
+    /// Maximum price in sats
+    #[arg(long)]
+    pub max_price: Option<u64>,
+
+    /// Exact playback policy, e.g. `tee_required`
+    #[arg(long)]
+    pub policy: Option<String>,
+
+    #[command(flatten)]
+    pub list: ListArgs,
+}
+
+#[derive(Args)]
+pub struct ShowArgs {
+    #[command(flatten)]
+    pub db: DbArgs,
+
+    /// Plaintext content hash
+    pub content_hash: String,
+}
+
+#[derive(Args)]
+pub struct PurgeArgs {
+    #[command(flatten)]
+    pub db: DbArgs,
+
+    /// Listing to delete, together with its seeder announcements
+    #[arg(long)]
+    pub content_hash: String,
+}
+
+fn print_listings(items: &[ContentListing], json: bool) -> Result<(), String> {
+    if json {
+        return print_json(&items);
+    }
+    for l in items {
+        println!(
+            "{:<64}  {:>10} sats  {:<20}  {}",
+            l.content_hash, l.price_sats, l.registered_at, l.file_name
+        );
+    }
+    eprintln!("{} listing(s)", items.len());
+    Ok(())
+}
+
+fn search(args: &ListArgs, params: &SearchParams) -> Result<(), String> {
+    let conn = args.db.open()?;
+    let page = Page {
+        limit: args.limit,
+        offset: args.offset,
+    };
+    let items = db::search_listings(&conn, params, Some(page)).map_err(|e| e.to_string())?;
+    print_listings(&items, args.json)
+}
+
+pub fn list_cmd(args: ListArgs) -> Result<(), String> {
+    search(&args, &SearchParams::default())
+}
+
+pub fn search_cmd(args: SearchArgs) -> Result<(), String> {
+    let params = SearchParams {
+        q: args.q,
+        content_type: args.content_type,
+        max_price: args.max_price,
+        policy: args.policy,
+    };
+    search(&args.list, &params)
+}
+
+pub fn show_cmd(args: ShowArgs) -> Result<(), String> {
+    let conn = args.db.open()?;
+    let listing = db::get_listing(&conn, &args.content_hash)
+        .map_err(|_| format!("no visible listing {}", args.content_hash))?;
+    let seeders = db::seeders_for(&conn, &listing.encrypted_hash).map_err(|e| e.to_string())?;
+    let manufacturers =
+        db::manufacturers_by_id(&conn, &listing.playback_params.allowed_manufacturers)
+            .map_err(|e| e.to_string())?;
+    let proxies =
+        proxies::candidates(&conn, &listing, DEFAULT_PRE_SCHEME).map_err(|e| e.to_string())?;
+    print_json(&DiscoverResponse {
+        listing,
+        seeders,
+        manufacturers,
+        proxies,
+    })
+}
+
+pub fn purge_cmd(args: PurgeArgs) -> Result<(), String> {
+    let mut conn = args.db.open()?;
+    match db::purge_listing(&mut conn, &args.content_hash).map_err(|e| e.to_string())? {
+        (0, _) => Err(format!("no listing {}", args.content_hash)),
+        (_, seeders) => {
+            println!(
+                "Purged listing {} and {} seeder announcement(s)",
+                args.content_hash, seeders
+            );
+            Ok(())
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Backup and migration
+// ---------------------------------------------------------------------------
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub db: DbArgs,
+
+    /// NDJSON output file; stdout if omitted
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+pub fn export_cmd(args: ExportArgs) -> Result<(), String> {
+    let conn = args.db.open()?;
+    let counts = match &args.output {
+        Some(path) => {
+            let file =
+                File::create(path).map_err(|e| format!("creating {}: {}", path.display(), e))?;
+            export::export(&conn, BufWriter::new(file))
+        }
+        None => export::export(&conn, io::stdout().lock()),
+    }
+    .map_err(|e| format!("export failed: {}", e))?;
+    eprintln!("Exported {}", counts);
+    Ok(())
+}
+
+#[derive(Args)]
+#[group(multiple = false)]
+pub struct OnInvalidArgs {
+    /// Leave out records with invalid signatures or fields, and report them
+    #[arg(long)]
+    pub skip_invalid: bool,
+
+    /// Abort without importing anything at the first invalid record (default)
+    #[arg(long)]
+    pub fail_fast: bool,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// NDJSON file from `export`, or `-` for stdin
+    pub file: PathBuf,
+
+    #[command(flatten)]
+    pub on_invalid: OnInvalidArgs,
+
+    /// Public key (hex) of the registry signing key that signed the export's
+    /// revocations; revoked manufacturers are invalid without it
+    #[arg(long)]
+    pub revocation_issuer: Option<String>,
+
+    /// Database to import into; created if it does not exist
+    #[command(flatten)]
+    pub db: DbArgs,
+}
+
+pub fn import_cmd(args: ImportArgs) -> Result<(), String> {
+    let on_invalid = if args.on_invalid.skip_invalid {
+        OnInvalid::Skip
+    } else {
+        OnInvalid::Fail
+    };
+    let issuer = args.revocation_issuer.as_deref();
+    let mut conn = args.db.open_or_create()?;
+    let summary = if args.file == Path::new("-") {
+        export::import(&mut conn, io::stdin().lock(), on_invalid, issuer)
+    } else {
+        let file = File::open(&args.file)
+            .map_err(|e| format!("reading {}: {}", args.file.display(), e))?;
+        export::import(&mut conn, BufReader::new(file), on_invalid, issuer)
+    }
+    .map_err(|e| format!("import aborted, nothing imported: {}", e))?;
+
+    println!("Imported {}", summary.imported);
+    if !summary.skipped.is_empty() {
+        println!("Skipped {} invalid record(s):", summary.skipped.len());
+        for r in &summary.skipped {
+            println!("  line {}: {}", r.line, r.reason);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Snapshot file written by --snapshot-dir or POST /admin/snapshot
+    pub snapshot: PathBuf,
+
+    /// Database to replace; the current file is kept as <db-path>.pre-restore-<timestamp>
+    #[command(flatten)]
+    pub db: DbArgs,
+}
+
+pub fn restore_cmd(args: RestoreArgs) -> Result<(), String> {
+    let restored = snapshot::restore(&args.snapshot, Path::new(&args.db.db_path))
+        .map_err(|e| format!("restore failed: {}", e))?;
+    println!(
+        "Restored {} ({} listings) to {}",
+        args.snapshot.display(),
+        restored.listings,
+        args.db.db_path
+    );
+    if let Some(previous) = restored.previous {
+        println!("Previous database kept at {}", previous.display());
+    }
+    Ok(())
+}
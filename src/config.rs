@@ -0,0 +1,596 @@
+//! Server configuration.
+//!
+//! Settings come from three layers, later ones winning: a TOML file given
+//! with `--config`, `CONDUIT_REGISTRY_*` environment variables, and
+//! command-line flags.  The binary resolves the last two through clap and
+//! applies them on top of [`Config::load`].  A file may set any subset:
+//!
+//! ```toml
+//! [registry]
+//! name = "Conduit Registry (EU)"
+//! operator_pubkey = "02..."
+//! signing_key = "..."
+//! attestation_ttl = "5m"
+//! pre_scheme = "afgh06-bls12-381"
+//!
+//! [server]
+//! port = 3003
+//! db_path = "/var/lib/conduit/registry.sqlite"
+//! legacy_api_cutoff = "2027-01-01"
+//! shutdown_timeout = "30s"
+//!
+//! [rate_limits]
+//! read = "600,120"
+//! verify = "20,10"
+//!
+//! [snapshots]
+//! dir = "/var/lib/conduit/snapshots"
+//! interval = "6h"
+//! keep = 14
+//!
+//! [cors]
+//! write_origins = ["https://studio.conduit.example"]
+//! max_age = "1h"
+//!
+//! [tls]
+//! cert = "/etc/conduit/fullchain.pem"
+//! key = "/etc/conduit/privkey.pem"
+//! http_redirect_port = 80
+//! ```
+//!
+//! On SIGHUP the server re-reads the file; only the settings listed in
+//! [`Config::reload`] take effect without a restart.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use secp256k1::SecretKey;
+use serde::{Deserialize, Serialize};
+
+use crate::limits::{DEFAULT_MAX_BODY_BYTES, DEFAULT_MAX_IMPORT_BYTES};
+use crate::pre::{self, DEFAULT_PRE_SCHEME};
+use crate::ratelimit::RateLimits;
+use crate::signature::pubkey_hex;
+use crate::snapshot::SnapshotConfig;
+
+pub const DEFAULT_REGISTRY_NAME: &str = "Conduit Registry";
+pub const DEFAULT_PORT: u16 = 3003;
+pub const DEFAULT_DB_PATH: &str = "/tmp/conduit-registry.db";
+pub const DEFAULT_SNAPSHOT_KEEP: usize = 7;
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_CORS_MAX_AGE: Duration = Duration::from_secs(3600);
+pub const DEFAULT_ATTESTATION_TTL: Duration = Duration::from_secs(300);
+/// Upper bound on `registry.attestation_ttl`: a token is meant to be
+/// short-lived, and the bound keeps `expires_at` a representable date.
+pub const MAX_ATTESTATION_TTL: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub registry: RegistrySettings,
+    pub server: ServerConfig,
+    pub rate_limits: RateLimits,
+    pub snapshots: SnapshotSettings,
+    pub cors: CorsSettings,
+    pub tls: TlsSettings,
+}
+
+/// How the registry describes itself in `GET /api/info`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistrySettings {
+    pub name: String,
+    pub description: String,
+    /// Operator's node public key (33-byte compressed, hex)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator_pubkey: Option<String>,
+    /// Operator contact, e.g. an email address or URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    /// Secret key (hex) the registry signs revocations and attestation
+    /// tokens with; its public key is published as `operator_pubkey`.
+    /// Never printed by `config check`.
+    #[serde(skip_serializing)]
+    pub signing_key: Option<String>,
+    /// How long an attestation token is valid
+    #[serde(with = "duration")]
+    pub attestation_ttl: Duration,
+    /// Proxy re-encryption scheme listing capsules are checked against;
+    /// one of `pre::SCHEMES`
+    pub pre_scheme: String,
+}
+
+impl Default for RegistrySettings {
+    fn default() -> Self {
+        RegistrySettings {
+            name: DEFAULT_REGISTRY_NAME.into(),
+            description: String::new(),
+            operator_pubkey: None,
+            contact: None,
+            signing_key: None,
+            attestation_ttl: DEFAULT_ATTESTATION_TTL,
+            pre_scheme: DEFAULT_PRE_SCHEME.into(),
+        }
+    }
+}
+
+impl RegistrySettings {
+    /// The parsed signing key, if set and valid (see `Config::validate`).
+    pub fn signing_key(&self) -> Option<SecretKey> {
+        let bytes = hex::decode(self.signing_key.as_ref()?.trim()).ok()?;
+        SecretKey::from_slice(&bytes).ok()
+    }
+
+    /// The configured PRE scheme, or the default if it is unknown (see
+    /// `Config::validate`).
+    pub fn pre_scheme(&self) -> &'static dyn pre::PreScheme {
+        pre::scheme(&self.pre_scheme)
+            .unwrap_or_else(|| pre::scheme(DEFAULT_PRE_SCHEME).expect("default scheme"))
+    }
+
+    /// `operator_pubkey`, or else the public key of `signing_key`.
+    pub fn operator_pubkey(&self) -> Option<String> {
+        self.operator_pubkey
+            .clone()
+            .or_else(|| self.signing_key().map(|sk| pubkey_hex(&sk)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// HTTP port to listen on
+    pub port: u16,
+    /// Path to the SQLite database file; created if missing
+    pub db_path: PathBuf,
+    /// Maximum request body size in bytes
+    pub max_body_bytes: usize,
+    /// Maximum body size for NDJSON imports
+    pub max_import_bytes: usize,
+    /// When the unprefixed `/api/...` aliases of v1 stop being served
+    #[serde(with = "cutoff", skip_serializing_if = "Option::is_none")]
+    pub legacy_api_cutoff: Option<DateTime<Utc>>,
+    /// Report not ready on `/readyz` so load balancers drain this instance
+    pub maintenance: bool,
+    /// How long to wait for in-flight requests on SIGTERM/SIGINT
+    #[serde(with = "duration")]
+    pub shutdown_timeout: Duration,
+    /// Bearer token for admin routes and manufacturer registration; unset
+    /// disables them.  Never printed by `config check`.
+    #[serde(skip_serializing)]
+    pub admin_token: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            port: DEFAULT_PORT,
+            db_path: PathBuf::from(DEFAULT_DB_PATH),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_import_bytes: DEFAULT_MAX_IMPORT_BYTES,
+            legacy_api_cutoff: None,
+            maintenance: false,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            admin_token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotSettings {
+    /// Snapshot directory; unset disables snapshots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Time between periodic snapshots, e.g. `"6h"`; unset for on-demand only
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub interval: Option<Duration>,
+    /// Number of snapshots retained
+    pub keep: usize,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        SnapshotSettings {
+            dir: None,
+            interval: None,
+            keep: DEFAULT_SNAPSHOT_KEEP,
+        }
+    }
+}
+
+impl SnapshotSettings {
+    /// The snapshot configuration, if a directory is set.
+    pub fn enabled(&self) -> Option<SnapshotConfig> {
+        self.dir.clone().map(|dir| SnapshotConfig {
+            dir,
+            interval: self.interval,
+            keep: self.keep,
+        })
+    }
+}
+
+/// Cross-origin access; see `cors.rs` for the route groups.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    /// Origins allowed to call write routes, e.g. `"https://studio.example"`;
+    /// `"*"` allows any.  Reads are open to all origins, admin routes to none.
+    pub write_origins: Vec<String>,
+    /// How long browsers may cache a preflight response
+    #[serde(with = "duration")]
+    pub max_age: Duration,
+    /// Let browsers send cookies and HTTP auth on cross-origin writes
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        CorsSettings {
+            write_origins: Vec::new(),
+            max_age: DEFAULT_CORS_MAX_AGE,
+            allow_credentials: false,
+        }
+    }
+}
+
+/// HTTPS; serving plain HTTP unless both `cert` and `key` are set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    /// PEM certificate chain, leaf first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert: Option<PathBuf>,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    /// Also listen for plain HTTP here and redirect it to HTTPS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_redirect_port: Option<u16>,
+}
+
+impl TlsSettings {
+    /// Certificate and key paths, if TLS is on.
+    pub fn enabled(&self) -> Option<(PathBuf, PathBuf)> {
+        self.cert.clone().zip(self.key.clone())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "reading {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Invalid(why) => write!(f, "invalid configuration: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Defaults, overlaid with the TOML file at `path` if given.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.into(), e))?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse(path.into(), e))
+    }
+
+    /// Check settings that parse but cannot work together.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |why: &str| Err(ConfigError::Invalid(why.into()));
+        if let Some(pk) = &self.registry.operator_pubkey {
+            let parsed = hex::decode(pk)
+                .ok()
+                .and_then(|b| secp256k1::PublicKey::from_slice(&b).ok());
+            if parsed.is_none() {
+                return invalid(
+                    "registry.operator_pubkey must be a hex-encoded secp256k1 public key",
+                );
+            }
+        }
+        if self.registry.signing_key.is_some() {
+            let Some(sk) = self.registry.signing_key() else {
+                return invalid("registry.signing_key must be a hex-encoded secp256k1 secret key");
+            };
+            if self
+                .registry
+                .operator_pubkey
+                .as_ref()
+                .is_some_and(|pk| *pk != pubkey_hex(&sk))
+            {
+                return invalid("registry.operator_pubkey does not match registry.signing_key");
+            }
+        }
+        if self.registry.attestation_ttl.is_zero()
+            || self.registry.attestation_ttl > MAX_ATTESTATION_TTL
+        {
+            return invalid("registry.attestation_ttl must be between 1s and 24h");
+        }
+        if pre::scheme(&self.registry.pre_scheme).is_none() {
+            let known: Vec<&str> = pre::SCHEMES.iter().map(|s| s.name()).collect();
+            return Err(ConfigError::Invalid(format!(
+                "registry.pre_scheme: unknown scheme '{}', expected one of {}",
+                self.registry.pre_scheme,
+                known.join(", ")
+            )));
+        }
+        if self
+            .server
+            .admin_token
+            .as_ref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            return invalid("server.admin_token must not be empty");
+        }
+        if self.server.max_body_bytes == 0 || self.server.max_import_bytes == 0 {
+            return invalid("body size limits must be positive");
+        }
+        if self.snapshots.interval.is_some() && self.snapshots.dir.is_none() {
+            return invalid("snapshots.interval requires snapshots.dir");
+        }
+        if self.snapshots.keep == 0 {
+            return invalid("snapshots.keep must be at least 1");
+        }
+        for origin in &self.cors.write_origins {
+            // scheme://host[:port], nothing else: browsers send exactly that
+            let host = origin
+                .strip_prefix("https://")
+                .or_else(|| origin.strip_prefix("http://"));
+            let valid = origin == "*"
+                || host.is_some_and(|h| {
+                    !h.is_empty() && !h.contains(|c: char| c == '/' || c.is_whitespace())
+                });
+            if !valid {
+                return Err(ConfigError::Invalid(format!(
+                    "cors.write_origins: '{}' is not an origin like https://example.com",
+                    origin
+                )));
+            }
+        }
+        if self.cors.allow_credentials && self.cors.write_origins.iter().any(|o| o == "*") {
+            return invalid(
+                "cors.allow_credentials cannot be combined with write_origins = [\"*\"]",
+            );
+        }
+        if self.tls.cert.is_some() != self.tls.key.is_some() {
+            return invalid("tls.cert and tls.key must be set together");
+        }
+        if let Some(port) = self.tls.http_redirect_port {
+            if self.tls.cert.is_none() {
+                return invalid("tls.http_redirect_port requires tls.cert and tls.key");
+            }
+            if port == self.server.port {
+                return invalid("tls.http_redirect_port must differ from server.port");
+            }
+        }
+        Ok(())
+    }
+
+    /// The effective configuration as TOML.
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("config serializes to TOML")
+    }
+
+    /// Take the reloadable settings (the registry description, rate limits,
+    /// the legacy API cutoff, maintenance mode, the shutdown timeout and the
+    /// admin token) from `new`.  Returns the names of other settings that
+    /// differ and only apply after a restart.  The signing key is one of
+    /// them: stored revocations are re-signed under a new key at startup
+    /// (see `revocation::resign`), so it cannot change while serving.
+    pub fn reload(&mut self, new: Config) -> Vec<&'static str> {
+        let mut pending = Vec::new();
+        let (old, next) = (&self.server, &new.server);
+        if old.port != next.port {
+            pending.push("server.port");
+        }
+        if old.db_path != next.db_path {
+            pending.push("server.db_path");
+        }
+        if old.max_body_bytes != next.max_body_bytes {
+            pending.push("server.max_body_bytes");
+        }
+        if old.max_import_bytes != next.max_import_bytes {
+            pending.push("server.max_import_bytes");
+        }
+        if self.snapshots != new.snapshots {
+            pending.push("snapshots");
+        }
+        if self.cors != new.cors {
+            pending.push("cors");
+        }
+        // Certificate files are reloaded separately; see `tls::CertStore`
+        if self.tls != new.tls {
+            pending.push("tls");
+        }
+
+        let (signing_key, operator_pubkey) =
+            if self.registry.signing_key != new.registry.signing_key {
+                pending.push("registry.signing_key");
+                (
+                    self.registry.signing_key.take(),
+                    self.registry.operator_pubkey.take(),
+                )
+            } else {
+                (new.registry.signing_key, new.registry.operator_pubkey)
+            };
+        self.registry = RegistrySettings {
+            signing_key,
+            operator_pubkey,
+            ..new.registry
+        };
+        self.rate_limits = new.rate_limits;
+        self.server.legacy_api_cutoff = new.server.legacy_api_cutoff;
+        self.server.maintenance = new.server.maintenance;
+        self.server.shutdown_timeout = new.server.shutdown_timeout;
+        self.server.admin_token = new.server.admin_token;
+        pending
+    }
+}
+
+/// `legacy_api_cutoff` as `YYYY-MM-DD` or RFC 3339.
+mod cutoff {
+    use super::*;
+    use crate::versioning::parse_cutoff;
+
+    pub fn serialize<S: serde::Serializer>(
+        v: &Option<DateTime<Utc>>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        match v {
+            Some(dt) => s.serialize_str(&dt.to_rfc3339()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        let s = String::deserialize(d)?;
+        parse_cutoff(&s).map(Some).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Durations as `"30s"`, `"30m"`, `"6h"`, `"1d"`.
+mod duration {
+    use super::*;
+    use crate::snapshot::{format_interval, parse_interval};
+
+    pub fn serialize<S: serde::Serializer>(v: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&format_interval(*v))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(d)?;
+        parse_interval(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+mod opt_duration {
+    use super::*;
+
+    pub fn serialize<S: serde::Serializer>(v: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match v {
+            Some(d) => duration::serialize(d, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        duration::deserialize(d).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    #[test]
+    fn partial_file_keeps_defaults() {
+        let cfg = parse(
+            r#"
+            [server]
+            port = 8080
+            legacy_api_cutoff = "2027-01-01"
+
+            [rate_limits]
+            read = "100,10"
+
+            [snapshots]
+            dir = "/var/snaps"
+            interval = "6h"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.server.port, 8080);
+        assert_eq!(cfg.server.db_path, PathBuf::from(DEFAULT_DB_PATH));
+        assert_eq!(cfg.rate_limits.read.to_string(), "100,10");
+        assert_eq!(cfg.rate_limits.write, RateLimits::default().write);
+        assert_eq!(cfg.snapshots.interval, Some(Duration::from_secs(6 * 3600)));
+        assert_eq!(cfg.snapshots.keep, DEFAULT_SNAPSHOT_KEEP);
+        cfg.validate().unwrap();
+
+        // The printed form parses back to the same configuration
+        assert_eq!(parse(&cfg.to_toml()).unwrap(), cfg);
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_settings() {
+        assert!(parse("[server]\nprot = 1").is_err());
+        assert!(parse("[rate_limits]\nread = \"fast\"").is_err());
+        assert!(parse("[snapshots]\ninterval = \"6w\"").is_err());
+
+        let orphan = parse("[snapshots]\ninterval = \"1d\"").unwrap();
+        assert!(orphan.validate().is_err());
+        let wildcard = parse("[cors]\nwrite_origins = [\"*\"]\nallow_credentials = true").unwrap();
+        assert!(wildcard.validate().is_err());
+        let with_path = parse("[cors]\nwrite_origins = [\"https://studio.example/app\"]").unwrap();
+        assert!(with_path.validate().is_err());
+        let half_tls = parse("[tls]\ncert = \"cert.pem\"").unwrap();
+        assert!(half_tls.validate().is_err());
+        let redirect =
+            parse("[tls]\ncert = \"c\"\nkey = \"k\"\nhttp_redirect_port = 3003").unwrap();
+        assert!(
+            redirect.validate().is_err(),
+            "redirect port collides with server.port"
+        );
+        let operator = parse("[registry]\noperator_pubkey = \"02abcd\"").unwrap();
+        assert!(operator.validate().is_err());
+        let signing = parse("[registry]\nsigning_key = \"00\"").unwrap();
+        assert!(signing.validate().is_err());
+        let forever = parse("[registry]\nattestation_ttl = \"30d\"").unwrap();
+        assert!(forever.validate().is_err());
+        let scheme = parse("[registry]\npre_scheme = \"rsa\"").unwrap();
+        assert!(scheme.validate().is_err());
+        let mismatch = format!(
+            "[registry]\nsigning_key = \"{}\"\noperator_pubkey = \"{}\"",
+            "01".repeat(32),
+            pubkey_hex(&SecretKey::from_slice(&[2; 32]).unwrap())
+        );
+        assert!(parse(&mismatch).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn reload_applies_only_safe_settings() {
+        let mut current = Config::default();
+        let mut next =
+            parse("[server]\nport = 9000\nmaintenance = true\nlegacy_api_cutoff = \"2027-01-01\"")
+                .unwrap();
+        next.registry.name = "Renamed".into();
+        next.rate_limits.read = "1".parse().unwrap();
+
+        assert_eq!(current.reload(next.clone()), ["server.port"]);
+        assert_eq!(current.server.port, DEFAULT_PORT);
+        assert_eq!(
+            current.server.legacy_api_cutoff,
+            next.server.legacy_api_cutoff
+        );
+        assert!(current.server.maintenance);
+        assert_eq!(current.rate_limits, next.rate_limits);
+        assert_eq!(current.registry.name, "Renamed");
+
+        // A new signing key waits for the restart that re-signs revocations
+        let mut rekeyed = current.clone();
+        rekeyed.registry.signing_key = Some("01".repeat(32));
+        assert_eq!(current.reload(rekeyed), ["registry.signing_key"]);
+        assert_eq!(current.registry.signing_key, None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Cross-origin policy per route group.
+//!
+//! | Group | Routes | Cross-origin |
+//! |-------|--------|--------------|
+//! | read  | `GET`/`HEAD` outside `/admin` | any origin, no credentials |
+//! | write | `POST`/`PUT`/`PATCH` outside `/admin` | `cors.write_origins` only |
+//! | admin | `/admin/...` and every `DELETE` | never |
+//!
+//! Preflights are classified by their `Access-Control-Request-Method`.
+//! Browsers enforce the policy from the response headers, but a form post
+//! needs no preflight, so write and admin requests whose `Origin` is not
+//! allowed are also refused here with `403 cross_origin_forbidden`.
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::config::CorsSettings;
+use crate::error::ApiError;
+
+/// Which cross-origin rules apply to a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    Read,
+    Write,
+    Admin,
+}
+
+impl RouteGroup {
+    pub fn of(method: &Method, path: &str) -> Self {
+        if path.contains("/admin/") || path.ends_with("/admin") || method == Method::DELETE {
+            return RouteGroup::Admin;
+        }
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => RouteGroup::Read,
+            _ => RouteGroup::Write,
+        }
+    }
+
+    fn of_request(req: &Request) -> Self {
+        // A preflight asks about the method the real request will use
+        let method = match req.method() {
+            &Method::OPTIONS => req
+                .headers()
+                .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+                .and_then(|m| Method::from_bytes(m.as_bytes()).ok())
+                .unwrap_or(Method::OPTIONS),
+            m => m.clone(),
+        };
+        RouteGroup::of(&method, req.uri().path())
+    }
+}
+
+/// The CORS layers for the read and write groups; admin gets none.
+#[derive(Clone)]
+pub struct CorsPolicy {
+    read: CorsLayer,
+    write: CorsLayer,
+    write_origins: Arc<Vec<HeaderValue>>,
+    any_write_origin: bool,
+}
+
+impl CorsPolicy {
+    /// Build from validated settings (see `Config::validate`).
+    pub fn new(settings: &CorsSettings) -> Self {
+        let read = CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods([Method::GET, Method::HEAD])
+            .allow_headers(Any)
+            .expose_headers([header::RETRY_AFTER, header::LINK])
+            .max_age(settings.max_age);
+
+        let any_write_origin = settings.write_origins.iter().any(|o| o == "*");
+        let write_origins: Vec<HeaderValue> = settings
+            .write_origins
+            .iter()
+            .filter(|o| *o != "*")
+            .filter_map(|o| HeaderValue::from_str(o).ok())
+            .collect();
+        let allow_origin = if any_write_origin {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(write_origins.clone())
+        };
+        let write = CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::POST, Method::PUT, Method::PATCH])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+            .expose_headers([header::RETRY_AFTER, header::LOCATION])
+            .allow_credentials(settings.allow_credentials)
+            .max_age(settings.max_age);
+
+        CorsPolicy {
+            read,
+            write,
+            write_origins: Arc::new(write_origins),
+            any_write_origin,
+        }
+    }
+
+    /// Whether a request from `headers` may reach a `group` route.
+    fn allows(&self, group: RouteGroup, headers: &HeaderMap) -> bool {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            // Not a browser cross-origin request
+            return true;
+        };
+        if same_origin(origin, headers) {
+            return true;
+        }
+        match group {
+            RouteGroup::Read => true,
+            RouteGroup::Write => self.any_write_origin || self.write_origins.contains(origin),
+            RouteGroup::Admin => false,
+        }
+    }
+}
+
+/// Whether `origin` names the host the request was sent to.
+fn same_origin(origin: &HeaderValue, headers: &HeaderMap) -> bool {
+    let (Ok(origin), Some(host)) = (origin.to_str(), headers.get(header::HOST)) else {
+        return false;
+    };
+    let authority = origin.split_once("://").map_or("", |(_, rest)| rest);
+    host.to_str()
+        .is_ok_and(|h| h.eq_ignore_ascii_case(authority))
+}
+
+/// Middleware applying the per-group CORS policy.
+pub async fn cors(State(policy): State<CorsPolicy>, req: Request, next: Next) -> Response {
+    let group = RouteGroup::of_request(&req);
+    let preflight = req.method() == Method::OPTIONS;
+    if !preflight && !policy.allows(group, req.headers()) {
+        return ApiError::new(
+            StatusCode::FORBIDDEN,
+            "cross_origin_forbidden",
+            match group {
+                RouteGroup::Admin => "Admin routes cannot be called cross-origin",
+                _ => "Origin is not allowed to write to this registry",
+            },
+        )
+        .into_response();
+    }
+    let layer = match group {
+        RouteGroup::Read => &policy.read,
+        RouteGroup::Write => &policy.write,
+        // No CORS headers: browsers refuse the preflight and the response
+        RouteGroup::Admin => return next.run(req).await,
+    };
+    match layer.layer(next).oneshot(req).await {
+        Ok(res) => res,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use std::time::Duration;
+
+    fn app(write_origins: &[&str]) -> Router {
+        let settings = CorsSettings {
+            write_origins: write_origins.iter().map(|o| o.to_string()).collect(),
+            max_age: Duration::from_secs(600),
+            allow_credentials: !write_origins.contains(&"*"),
+        };
+        Router::new()
+            .route(
+                "/api/v2/listings",
+                get(|| async { "list" })
+                    .post(|| async { "created" })
+                    .delete(|| async { "gone" }),
+            )
+            .route(
+                "/api/v2/admin/import",
+                axum::routing::post(|| async { "imported" }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                CorsPolicy::new(&settings),
+                cors,
+            ))
+    }
+
+    async fn send(app: &Router, method: Method, path: &str, headers: &[(&str, &str)]) -> Response {
+        let mut req = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, "registry.test");
+        for (k, v) in headers {
+            req = req.header(*k, *v);
+        }
+        app.clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn allow_origin(res: &Response) -> Option<&str> {
+        res.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|v| v.to_str().unwrap())
+    }
+
+    #[test]
+    fn classifies_routes() {
+        assert_eq!(
+            RouteGroup::of(&Method::GET, "/api/v2/listings"),
+            RouteGroup::Read
+        );
+        assert_eq!(
+            RouteGroup::of(&Method::POST, "/api/v2/seeders"),
+            RouteGroup::Write
+        );
+        assert_eq!(
+            RouteGroup::of(&Method::DELETE, "/api/listings"),
+            RouteGroup::Admin
+        );
+        assert_eq!(
+            RouteGroup::of(&Method::GET, "/api/v2/admin/export"),
+            RouteGroup::Admin
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_are_open_to_any_origin() {
+        let app = app(&[]);
+        let res = send(
+            &app,
+            Method::GET,
+            "/api/v2/listings",
+            &[("origin", "https://anywhere.example")],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(allow_origin(&res), Some("*"));
+    }
+
+    #[tokio::test]
+    async fn writes_only_from_configured_origins() {
+        let app = app(&["https://studio.example"]);
+        let preflight = [
+            ("origin", "https://studio.example"),
+            ("access-control-request-method", "POST"),
+        ];
+        let res = send(&app, Method::OPTIONS, "/api/v2/listings", &preflight).await;
+        assert_eq!(allow_origin(&res), Some("https://studio.example"));
+        assert_eq!(res.headers()[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+
+        let res = send(
+            &app,
+            Method::POST,
+            "/api/v2/listings",
+            &[("origin", "https://evil.example")],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send(
+            &app,
+            Method::POST,
+            "/api/v2/listings",
+            &[("origin", "https://studio.example")],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        // Same-origin and non-browser requests are unaffected
+        let res = send(
+            &app,
+            Method::POST,
+            "/api/v2/listings",
+            &[("origin", "http://registry.test")],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send(&app, Method::POST, "/api/v2/listings", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn admin_is_never_cross_origin() {
+        let app = app(&["*"]);
+        let preflight = [
+            ("origin", "https://studio.example"),
+            ("access-control-request-method", "DELETE"),
+        ];
+        let res = send(&app, Method::OPTIONS, "/api/v2/listings", &preflight).await;
+        assert_eq!(allow_origin(&res), None);
+
+        let origin = [("origin", "https://studio.example")];
+        let res = send(&app, Method::POST, "/api/v2/admin/import", &origin).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send(&app, Method::DELETE, "/api/v2/listings", &origin).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send(&app, Method::DELETE, "/api/v2/listings", &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
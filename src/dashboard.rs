@@ -58,8 +58,8 @@ pub const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
 <script>
 async function load() {
   const [listRes, seederRes] = await Promise.all([
-    fetch('/api/listings').then(r => r.json()),
-    fetch('/api/seeders?all=1').then(r => r.json()).catch(() => ({items:[]}))
+    fetch('/api/v1/listings').then(r => r.json()),
+    fetch('/api/v1/seeders?all=1').then(r => r.json()).catch(() => ({items:[]}))
   ]);
   const listings = listRes.items || [];
   const seeders = seederRes.items || [];
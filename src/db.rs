@@ -1,8 +1,11 @@
 //! Database initialization and helpers for the Conduit Registry.
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 
-use crate::types::ContentListing;
+use crate::types::{
+    BlockEntry, BlockKind, BuyerKey, ContentListing, Device, Manufacturer, ManufacturerKey, Page,
+    PlaybackParams, ProxyAnnouncement, Revocation, SearchParams, SeederAnnouncement,
+};
 
 pub fn init_db(conn: &Connection) {
     conn.execute_batch(
@@ -75,6 +78,11 @@ pub fn init_db(conn: &Connection) {
         "ALTER TABLE listings ADD COLUMN creator_signature TEXT NOT NULL DEFAULT ''",
         [],
     );
+    // Migration: add playback_params column (JSON, empty for none)
+    let _ = conn.execute(
+        "ALTER TABLE listings ADD COLUMN playback_params TEXT NOT NULL DEFAULT ''",
+        [],
+    );
     // TEE device manufacturers table
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS manufacturers (
@@ -82,9 +90,143 @@ pub fn init_db(conn: &Connection) {
             name TEXT NOT NULL,
             description TEXT NOT NULL DEFAULT '',
             website TEXT NOT NULL DEFAULT '',
-            registered_at TEXT NOT NULL
+            registered_at TEXT NOT NULL,
+            signature TEXT NOT NULL DEFAULT '',
+            revoked_at TEXT NOT NULL DEFAULT '',
+            revocation_reason TEXT NOT NULL DEFAULT '',
+            revocation_signature TEXT NOT NULL DEFAULT ''
         );"
     ).expect("Failed to create manufacturers table");
+    // Migration: add manufacturer proof-of-possession signature
+    let _ = conn.execute(
+        "ALTER TABLE manufacturers ADD COLUMN signature TEXT NOT NULL DEFAULT ''",
+        [],
+    );
+    // Migration: revocation replaces deletion
+    for col in ["revoked_at", "revocation_reason", "revocation_signature"] {
+        let _ = conn.execute(
+            &format!(
+                "ALTER TABLE manufacturers ADD COLUMN {} TEXT NOT NULL DEFAULT ''",
+                col
+            ),
+            [],
+        );
+    }
+    // Manufacturer keys and their validity windows (key rotation)
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS manufacturer_keys (
+            pk_hex TEXT PRIMARY KEY,
+            manufacturer_id TEXT NOT NULL,
+            not_before TEXT NOT NULL,
+            not_after TEXT NOT NULL DEFAULT '',
+            retired_at TEXT NOT NULL DEFAULT '',
+            signed_by TEXT NOT NULL DEFAULT '',
+            signature TEXT NOT NULL DEFAULT ''
+        );
+        CREATE INDEX IF NOT EXISTS idx_manufacturer_keys_id ON manufacturer_keys(manufacturer_id);",
+    )
+    .expect("Failed to create manufacturer_keys table");
+    // Migration: manufacturers registered before rotation get their
+    // registration key
+    let _ = conn.execute(
+        "INSERT OR IGNORE INTO manufacturer_keys (pk_hex, manufacturer_id, not_before)
+         SELECT pk_hex, pk_hex, registered_at FROM manufacturers",
+        [],
+    );
+    // Buyer PRE keys, current and superseded
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS buyer_keys (
+            node_pubkey TEXT NOT NULL,
+            published_at TEXT NOT NULL,
+            scheme TEXT NOT NULL,
+            pre_pk_hex TEXT NOT NULL,
+            superseded_at TEXT NOT NULL DEFAULT '',
+            signature TEXT NOT NULL,
+            PRIMARY KEY (node_pubkey, published_at)
+        );",
+    )
+    .expect("Failed to create buyer_keys table");
+    // Re-encryption proxy announcements; `schemes` is comma-separated
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS proxies (
+            proxy_pubkey TEXT PRIMARY KEY,
+            proxy_address TEXT NOT NULL,
+            proxy_ln_address TEXT NOT NULL DEFAULT '',
+            proxy_alias TEXT NOT NULL DEFAULT '',
+            fee_sats INTEGER NOT NULL,
+            schemes TEXT NOT NULL,
+            ttl_secs INTEGER NOT NULL,
+            announced_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            signature TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_proxies_expires ON proxies(expires_at);",
+    )
+    .expect("Failed to create proxies table");
+    // Manufacturer-certified TEE device keys
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS devices (
+            device_pk TEXT PRIMARY KEY,
+            manufacturer_pk TEXT NOT NULL,
+            model TEXT NOT NULL DEFAULT '',
+            issued_at TEXT NOT NULL,
+            manufacturer_signature TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_devices_manufacturer ON devices(manufacturer_pk);",
+    )
+    .expect("Failed to create devices table");
+    // Moderation blocklist
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS blocklist (
+            kind TEXT NOT NULL,
+            value TEXT NOT NULL,
+            reason TEXT NOT NULL DEFAULT '',
+            blocked_at TEXT NOT NULL,
+            PRIMARY KEY (kind, value)
+        );",
+    )
+    .expect("Failed to create blocklist table");
+}
+
+/// `(table, columns)` for every table in `conn`.
+fn schema(conn: &Connection) -> rusqlite::Result<Vec<(String, Vec<String>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let tables: Vec<String> = stmt
+        .query_map([], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    tables
+        .into_iter()
+        .map(|table| {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+            let cols = stmt
+                .query_map([&table], |r| r.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok((table, cols))
+        })
+        .collect()
+}
+
+/// The first table or column that `init_db` would create but `conn` lacks,
+/// e.g. `"table listings lacks column creator_alias"`; `None` if the
+/// schema is current.
+pub fn schema_gap(conn: &Connection) -> rusqlite::Result<Option<String>> {
+    let expected = {
+        let fresh = Connection::open_in_memory()?;
+        init_db(&fresh);
+        schema(&fresh)?
+    };
+    let actual = schema(conn)?;
+    for (table, cols) in &expected {
+        let Some((_, have)) = actual.iter().find(|(t, _)| t == table) else {
+            return Ok(Some(format!("missing table {}", table)));
+        };
+        if let Some(col) = cols.iter().find(|c| !have.contains(c)) {
+            return Ok(Some(format!("table {} lacks column {}", table, col)));
+        }
+    }
+    Ok(None)
 }
 
 pub fn listing_from_row(row: &rusqlite::Row) -> rusqlite::Result<ContentListing> {
@@ -107,6 +249,20 @@ pub fn listing_from_row(row: &rusqlite::Row) -> rusqlite::Result<ContentListing>
         pre_c2_hex: row.get(15)?,
         pre_pk_creator_hex: row.get(16)?,
         playback_policy: row.get(17)?,
+        playback_params: {
+            let json: String = row.get(19)?;
+            if json.is_empty() {
+                PlaybackParams::default()
+            } else {
+                serde_json::from_str(&json).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        19,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })?
+            }
+        },
         creator_signature: row.get(18)?,
     })
 }
@@ -115,4 +271,792 @@ pub const LISTING_COLS: &str =
     "content_hash, encrypted_hash, file_name, size_bytes, price_sats,
      chunk_size, chunk_count, plaintext_root, encrypted_root,
      creator_pubkey, creator_address, creator_ln_address, creator_alias, registered_at,
-     pre_c1_hex, pre_c2_hex, pre_pk_creator_hex, playback_policy, creator_signature";
+     pre_c1_hex, pre_c2_hex, pre_pk_creator_hex, playback_policy, creator_signature,
+     playback_params";
+
+/// SQL predicate that hides listings matching any blocklist entry.
+pub const LISTING_NOT_BLOCKED: &str =
+    "lower(content_hash) NOT IN (SELECT lower(value) FROM blocklist WHERE kind = 'content_hash')
+     AND lower(encrypted_hash) NOT IN (SELECT lower(value) FROM blocklist WHERE kind = 'encrypted_hash')
+     AND lower(creator_pubkey) NOT IN (SELECT lower(value) FROM blocklist WHERE kind = 'creator_pubkey')";
+
+/// SQL predicate that hides seeder announcements matching any blocklist entry.
+pub const SEEDER_NOT_BLOCKED: &str =
+    "lower(encrypted_hash) NOT IN (SELECT lower(value) FROM blocklist WHERE kind = 'encrypted_hash')
+     AND lower(seeder_pubkey) NOT IN (SELECT lower(value) FROM blocklist WHERE kind = 'seeder_pubkey')";
+
+/// Whether `value` matches a `kind` entry.  Values are hex, so case does
+/// not matter.
+pub fn is_blocked(conn: &Connection, kind: BlockKind, value: &str) -> bool {
+    conn.query_row(
+        "SELECT 1 FROM blocklist WHERE kind = ?1 AND lower(value) = lower(?2)",
+        rusqlite::params![kind.as_str(), value],
+        |_| Ok(()),
+    )
+    .is_ok()
+}
+
+pub const SEEDER_COLS: &str =
+    "encrypted_hash, seeder_pubkey, seeder_address, seeder_ln_address, seeder_alias,
+     transport_price, chunk_count, announced_at";
+
+pub fn seeder_from_row(row: &rusqlite::Row) -> rusqlite::Result<SeederAnnouncement> {
+    Ok(SeederAnnouncement {
+        encrypted_hash: row.get(0)?,
+        seeder_pubkey: row.get(1)?,
+        seeder_address: row.get(2)?,
+        seeder_ln_address: row.get(3)?,
+        seeder_alias: row.get(4)?,
+        transport_price: row.get(5)?,
+        chunk_count: row.get(6)?,
+        announced_at: row.get(7)?,
+    })
+}
+
+pub const MANUFACTURER_COLS: &str = "pk_hex, name, description, website, registered_at, signature,
+     revoked_at, revocation_reason, revocation_signature";
+
+pub fn manufacturer_from_row(row: &rusqlite::Row) -> rusqlite::Result<Manufacturer> {
+    let pk_hex: String = row.get(0)?;
+    let revoked_at: String = row.get(6)?;
+    let revocation = if revoked_at.is_empty() {
+        None
+    } else {
+        Some(Revocation {
+            pk_hex: pk_hex.clone(),
+            revoked_at,
+            reason: row.get(7)?,
+            signature: row.get(8)?,
+        })
+    };
+    Ok(Manufacturer {
+        id: pk_hex.clone(),
+        pk_hex,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        website: row.get(3)?,
+        registered_at: row.get(4)?,
+        signature: row.get(5)?,
+        revocation,
+    })
+}
+
+pub const KEY_COLS: &str =
+    "pk_hex, manufacturer_id, not_before, not_after, retired_at, signed_by, signature";
+
+pub fn key_from_row(row: &rusqlite::Row) -> rusqlite::Result<ManufacturerKey> {
+    Ok(ManufacturerKey {
+        pk_hex: row.get(0)?,
+        manufacturer_id: row.get(1)?,
+        not_before: row.get(2)?,
+        not_after: row.get(3)?,
+        retired_at: row.get(4)?,
+        signed_by: row.get(5)?,
+        signature: row.get(6)?,
+    })
+}
+
+pub const BUYER_KEY_COLS: &str =
+    "node_pubkey, scheme, pre_pk_hex, published_at, superseded_at, signature";
+
+pub fn buyer_key_from_row(row: &rusqlite::Row) -> rusqlite::Result<BuyerKey> {
+    Ok(BuyerKey {
+        node_pubkey: row.get(0)?,
+        scheme: row.get(1)?,
+        pre_pk_hex: row.get(2)?,
+        published_at: row.get(3)?,
+        superseded_at: row.get(4)?,
+        signature: row.get(5)?,
+    })
+}
+
+pub const PROXY_COLS: &str =
+    "proxy_pubkey, proxy_address, proxy_ln_address, proxy_alias, fee_sats, schemes, ttl_secs,
+     announced_at, signature";
+
+pub fn proxy_from_row(row: &rusqlite::Row) -> rusqlite::Result<ProxyAnnouncement> {
+    let schemes: String = row.get(5)?;
+    Ok(ProxyAnnouncement {
+        proxy_pubkey: row.get(0)?,
+        proxy_address: row.get(1)?,
+        proxy_ln_address: row.get(2)?,
+        proxy_alias: row.get(3)?,
+        fee_sats: row.get(4)?,
+        schemes: schemes
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect(),
+        ttl_secs: row.get(6)?,
+        announced_at: row.get(7)?,
+        signature: row.get(8)?,
+    })
+}
+
+pub const DEVICE_COLS: &str =
+    "device_pk, manufacturer_pk, model, issued_at, manufacturer_signature";
+
+pub fn device_from_row(row: &rusqlite::Row) -> rusqlite::Result<Device> {
+    Ok(Device {
+        device_pk: row.get(0)?,
+        manufacturer_pk: row.get(1)?,
+        model: row.get(2)?,
+        issued_at: row.get(3)?,
+        manufacturer_signature: row.get(4)?,
+    })
+}
+
+fn page_clause(page: Option<Page>) -> String {
+    match page {
+        Some(p) => format!(" LIMIT {} OFFSET {}", p.limit, p.offset),
+        None => String::new(),
+    }
+}
+
+/// Visible listings matching `params`, newest first.
+pub fn search_listings(
+    conn: &Connection,
+    params: &SearchParams,
+    page: Option<Page>,
+) -> rusqlite::Result<Vec<ContentListing>> {
+    // Build dynamic query
+    let mut sql = format!(
+        "SELECT {} FROM listings WHERE {}",
+        LISTING_COLS, LISTING_NOT_BLOCKED
+    );
+    let mut bind_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(ref q) = params.q {
+        bind_values.push(Box::new(format!("%{}%", q)));
+        sql.push_str(&format!(" AND file_name LIKE ?{}", bind_values.len()));
+    }
+
+    if let Some(ref content_type) = params.content_type {
+        bind_values.push(Box::new(format!("%.{}", content_type)));
+        sql.push_str(&format!(" AND file_name LIKE ?{}", bind_values.len()));
+    }
+
+    if let Some(max_price) = params.max_price {
+        bind_values.push(Box::new(max_price as i64));
+        sql.push_str(&format!(" AND price_sats <= ?{}", bind_values.len()));
+    }
+
+    if let Some(ref policy) = params.policy {
+        bind_values.push(Box::new(policy.clone()));
+        sql.push_str(&format!(" AND playback_policy = ?{}", bind_values.len()));
+    }
+
+    sql.push_str(" ORDER BY registered_at DESC");
+    sql.push_str(&page_clause(page));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+        bind_values.iter().map(|b| b.as_ref()).collect();
+
+    let items = stmt
+        .query_map(params_ref.as_slice(), listing_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// A single visible listing.
+pub fn get_listing(conn: &Connection, content_hash: &str) -> rusqlite::Result<ContentListing> {
+    let sql = format!(
+        "SELECT {} FROM listings WHERE content_hash = ?1 AND {}",
+        LISTING_COLS, LISTING_NOT_BLOCKED
+    );
+    conn.query_row(&sql, rusqlite::params![content_hash], listing_from_row)
+}
+
+/// Visible seeder announcements for one encrypted hash.
+pub fn seeders_for(
+    conn: &Connection,
+    encrypted_hash: &str,
+) -> rusqlite::Result<Vec<SeederAnnouncement>> {
+    let sql = format!(
+        "SELECT {} FROM seeders WHERE encrypted_hash = ?1 AND {}",
+        SEEDER_COLS, SEEDER_NOT_BLOCKED
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map(rusqlite::params![encrypted_hash], seeder_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// Visible seeder announcements, newest first.
+pub fn list_seeders(
+    conn: &Connection,
+    page: Option<Page>,
+) -> rusqlite::Result<Vec<SeederAnnouncement>> {
+    let sql = format!(
+        "SELECT {} FROM seeders WHERE {} ORDER BY announced_at DESC{}",
+        SEEDER_COLS,
+        SEEDER_NOT_BLOCKED,
+        page_clause(page)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map([], seeder_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// All manufacturers, newest first.
+pub fn list_manufacturers(
+    conn: &Connection,
+    page: Option<Page>,
+) -> rusqlite::Result<Vec<Manufacturer>> {
+    let sql = format!(
+        "SELECT {} FROM manufacturers ORDER BY registered_at DESC{}",
+        MANUFACTURER_COLS,
+        page_clause(page)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map([], manufacturer_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+pub fn get_manufacturer(conn: &Connection, pk_hex: &str) -> rusqlite::Result<Manufacturer> {
+    let sql = format!(
+        "SELECT {} FROM manufacturers WHERE pk_hex = ?1",
+        MANUFACTURER_COLS
+    );
+    conn.query_row(&sql, rusqlite::params![pk_hex], manufacturer_from_row)
+}
+
+/// The manufacturers with the given ids, in that order; unknown ids are
+/// left out.
+pub fn manufacturers_by_id(
+    conn: &Connection,
+    ids: &[String],
+) -> rusqlite::Result<Vec<Manufacturer>> {
+    let mut found = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(m) = get_manufacturer(conn, id).optional()? {
+            found.push(m);
+        }
+    }
+    Ok(found)
+}
+
+pub fn get_key(conn: &Connection, pk_hex: &str) -> rusqlite::Result<ManufacturerKey> {
+    let sql = format!(
+        "SELECT {} FROM manufacturer_keys WHERE pk_hex = ?1",
+        KEY_COLS
+    );
+    conn.query_row(&sql, rusqlite::params![pk_hex], key_from_row)
+}
+
+/// Every key of one manufacturer, oldest first.
+pub fn manufacturer_keys(
+    conn: &Connection,
+    manufacturer_id: &str,
+) -> rusqlite::Result<Vec<ManufacturerKey>> {
+    let sql = format!(
+        "SELECT {} FROM manufacturer_keys WHERE manufacturer_id = ?1 ORDER BY rowid",
+        KEY_COLS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map(rusqlite::params![manufacturer_id], key_from_row)?
+        .collect();
+    items
+}
+
+/// Every manufacturer key, in insertion order (for export).
+pub fn all_keys(conn: &Connection) -> rusqlite::Result<Vec<ManufacturerKey>> {
+    let sql = format!("SELECT {} FROM manufacturer_keys ORDER BY rowid", KEY_COLS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map([], key_from_row)?.collect();
+    items
+}
+
+/// The manufacturer that owns key `pk_hex`, whether it is the
+/// registration key or a rotated one.
+pub fn manufacturer_for_key(conn: &Connection, pk_hex: &str) -> rusqlite::Result<Manufacturer> {
+    let sql = format!(
+        "SELECT {} FROM manufacturers
+         WHERE pk_hex = (SELECT manufacturer_id FROM manufacturer_keys WHERE pk_hex = ?1)",
+        MANUFACTURER_COLS
+    );
+    conn.query_row(&sql, rusqlite::params![pk_hex], manufacturer_from_row)
+}
+
+/// The current PRE key of buyer `node_pubkey`.
+pub fn current_buyer_key(conn: &Connection, node_pubkey: &str) -> rusqlite::Result<BuyerKey> {
+    let sql = format!(
+        "SELECT {} FROM buyer_keys WHERE node_pubkey = ?1 AND superseded_at = ''",
+        BUYER_KEY_COLS
+    );
+    conn.query_row(&sql, rusqlite::params![node_pubkey], buyer_key_from_row)
+}
+
+/// Every PRE key buyer `node_pubkey` has published, newest first.
+pub fn buyer_key_history(conn: &Connection, node_pubkey: &str) -> rusqlite::Result<Vec<BuyerKey>> {
+    let sql = format!(
+        "SELECT {} FROM buyer_keys WHERE node_pubkey = ?1 ORDER BY rowid DESC",
+        BUYER_KEY_COLS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map(rusqlite::params![node_pubkey], buyer_key_from_row)?
+        .collect();
+    items
+}
+
+/// Every buyer key, in insertion order (for export).
+pub fn all_buyer_keys(conn: &Connection) -> rusqlite::Result<Vec<BuyerKey>> {
+    let sql = format!("SELECT {} FROM buyer_keys ORDER BY rowid", BUYER_KEY_COLS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map([], buyer_key_from_row)?.collect();
+    items
+}
+
+pub fn get_proxy(conn: &Connection, proxy_pubkey: &str) -> rusqlite::Result<ProxyAnnouncement> {
+    let sql = format!("SELECT {} FROM proxies WHERE proxy_pubkey = ?1", PROXY_COLS);
+    conn.query_row(&sql, rusqlite::params![proxy_pubkey], proxy_from_row)
+}
+
+/// Proxies whose announcement has not expired by `now` (RFC 3339, UTC,
+/// as stored in `expires_at`), optionally only those supporting `scheme`;
+/// cheapest first.
+pub fn live_proxies(
+    conn: &Connection,
+    now: &str,
+    scheme: Option<&str>,
+    page: Option<Page>,
+) -> rusqlite::Result<Vec<ProxyAnnouncement>> {
+    let sql = format!(
+        "SELECT {} FROM proxies
+         WHERE expires_at > ?1 AND (?2 IS NULL OR instr(',' || schemes || ',', ',' || ?2 || ',') > 0)
+         ORDER BY fee_sats, announced_at DESC{}",
+        PROXY_COLS,
+        page_clause(page)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map(rusqlite::params![now, scheme], proxy_from_row)?
+        .collect();
+    items
+}
+
+/// Every revocation, oldest first.
+pub fn revocations(conn: &Connection) -> rusqlite::Result<Vec<Revocation>> {
+    let mut stmt = conn.prepare(
+        "SELECT pk_hex, revoked_at, revocation_reason, revocation_signature FROM manufacturers
+         WHERE revoked_at != '' ORDER BY revoked_at, pk_hex",
+    )?;
+    let items = stmt
+        .query_map([], |row| {
+            Ok(Revocation {
+                pk_hex: row.get(0)?,
+                revoked_at: row.get(1)?,
+                reason: row.get(2)?,
+                signature: row.get(3)?,
+            })
+        })?
+        .collect();
+    items
+}
+
+/// Mark a manufacturer revoked.  Returns 0 if there is no such manufacturer
+/// or it is already revoked.
+pub fn revoke_manufacturer(conn: &Connection, rev: &Revocation) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE manufacturers SET revoked_at = ?2, revocation_reason = ?3, revocation_signature = ?4
+         WHERE pk_hex = ?1 AND revoked_at = ''",
+        rusqlite::params![rev.pk_hex, rev.revoked_at, rev.reason, rev.signature],
+    )
+}
+
+/// Replace the signature on a stored revocation, leaving what it states
+/// unchanged.
+pub fn resign_revocation(conn: &Connection, rev: &Revocation) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE manufacturers SET revocation_signature = ?3
+         WHERE pk_hex = ?1 AND revoked_at = ?2",
+        rusqlite::params![rev.pk_hex, rev.revoked_at, rev.signature],
+    )
+}
+
+pub fn get_device(conn: &Connection, device_pk: &str) -> rusqlite::Result<Device> {
+    let sql = format!("SELECT {} FROM devices WHERE device_pk = ?1", DEVICE_COLS);
+    conn.query_row(&sql, rusqlite::params![device_pk], device_from_row)
+}
+
+/// Every device, oldest first (for export).
+pub fn all_devices(conn: &Connection) -> rusqlite::Result<Vec<Device>> {
+    let sql = format!("SELECT {} FROM devices ORDER BY issued_at", DEVICE_COLS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map([], device_from_row)?.collect();
+    items
+}
+
+/// All blocklist entries, newest first.
+pub fn list_blocks(conn: &Connection, page: Option<Page>) -> rusqlite::Result<Vec<BlockEntry>> {
+    let sql = format!(
+        "SELECT kind, value, reason, blocked_at FROM blocklist ORDER BY blocked_at DESC{}",
+        page_clause(page)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map([], |row| {
+            let kind: String = row.get(0)?;
+            Ok((kind, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?
+        .filter_map(|r| r.ok())
+        .filter_map(|(kind, value, reason, blocked_at)| {
+            Some(BlockEntry {
+                kind: BlockKind::parse(&kind)?,
+                value,
+                reason,
+                blocked_at,
+            })
+        })
+        .collect();
+    Ok(items)
+}
+
+pub fn insert_listing(conn: &Connection, listing: &ContentListing) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO listings
+         (content_hash, encrypted_hash, file_name, size_bytes, price_sats,
+          chunk_size, chunk_count, plaintext_root, encrypted_root,
+          creator_pubkey, creator_address, creator_ln_address, creator_alias, registered_at,
+          pre_c1_hex, pre_c2_hex, pre_pk_creator_hex, playback_policy, creator_signature,
+          playback_params)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+        rusqlite::params![
+            listing.content_hash,
+            listing.encrypted_hash,
+            listing.file_name,
+            listing.size_bytes,
+            listing.price_sats,
+            listing.chunk_size,
+            listing.chunk_count,
+            listing.plaintext_root,
+            listing.encrypted_root,
+            listing.creator_pubkey,
+            listing.creator_address,
+            listing.creator_ln_address,
+            listing.creator_alias,
+            listing.registered_at,
+            listing.pre_c1_hex,
+            listing.pre_c2_hex,
+            listing.pre_pk_creator_hex,
+            listing.playback_policy,
+            listing.creator_signature,
+            if listing.playback_params.is_empty() {
+                String::new()
+            } else {
+                serde_json::to_string(&listing.playback_params).expect("playback params serialize")
+            },
+        ],
+    )
+}
+
+pub fn insert_seeder(
+    conn: &Connection,
+    announcement: &SeederAnnouncement,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO seeders
+         (encrypted_hash, seeder_pubkey, seeder_address, seeder_ln_address, seeder_alias,
+          transport_price, chunk_count, announced_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            announcement.encrypted_hash,
+            announcement.seeder_pubkey,
+            announcement.seeder_address,
+            announcement.seeder_ln_address,
+            announcement.seeder_alias,
+            announcement.transport_price,
+            announcement.chunk_count,
+            announcement.announced_at,
+        ],
+    )
+}
+
+/// Insert or update a manufacturer.  An existing revocation is never
+/// cleared or replaced.
+pub fn insert_manufacturer(conn: &Connection, mfr: &Manufacturer) -> rusqlite::Result<usize> {
+    let rev = mfr.revocation.as_ref();
+    conn.execute(
+        "INSERT INTO manufacturers
+         (pk_hex, name, description, website, registered_at, signature,
+          revoked_at, revocation_reason, revocation_signature)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT (pk_hex) DO UPDATE SET
+          name = excluded.name, description = excluded.description, website = excluded.website,
+          registered_at = excluded.registered_at, signature = excluded.signature,
+          revoked_at = CASE WHEN revoked_at = '' THEN excluded.revoked_at ELSE revoked_at END,
+          revocation_reason = CASE WHEN revoked_at = '' THEN excluded.revocation_reason ELSE revocation_reason END,
+          revocation_signature =
+            CASE WHEN revoked_at = '' THEN excluded.revocation_signature ELSE revocation_signature END",
+        rusqlite::params![
+            mfr.pk_hex,
+            mfr.name,
+            mfr.description,
+            mfr.website,
+            mfr.registered_at,
+            mfr.signature,
+            rev.map_or("", |r| r.revoked_at.as_str()),
+            rev.map_or("", |r| r.reason.as_str()),
+            rev.map_or("", |r| r.signature.as_str()),
+        ],
+    )?;
+    // The registration key, valid from registration on
+    conn.execute(
+        "INSERT OR IGNORE INTO manufacturer_keys (pk_hex, manufacturer_id, not_before) VALUES (?1, ?1, ?2)",
+        rusqlite::params![mfr.pk_hex, mfr.registered_at],
+    )
+}
+
+pub fn insert_key(conn: &Connection, key: &ManufacturerKey) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO manufacturer_keys
+         (pk_hex, manufacturer_id, not_before, not_after, retired_at, signed_by, signature)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            key.pk_hex,
+            key.manufacturer_id,
+            key.not_before,
+            key.not_after,
+            key.retired_at,
+            key.signed_by,
+            key.signature,
+        ],
+    )
+}
+
+/// End `pk_hex`'s window at `at` unless it already ends earlier.
+pub fn retire_key(conn: &Connection, pk_hex: &str, at: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE manufacturer_keys SET retired_at = ?2 WHERE pk_hex = ?1 AND retired_at = ''",
+        rusqlite::params![pk_hex, at],
+    )
+}
+
+pub fn insert_buyer_key(conn: &Connection, key: &BuyerKey) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO buyer_keys
+         (node_pubkey, scheme, pre_pk_hex, published_at, superseded_at, signature)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            key.node_pubkey,
+            key.scheme,
+            key.pre_pk_hex,
+            key.published_at,
+            key.superseded_at,
+            key.signature,
+        ],
+    )
+}
+
+/// Mark the current key of `node_pubkey`, if any, superseded at `at`.
+pub fn supersede_buyer_key(
+    conn: &Connection,
+    node_pubkey: &str,
+    at: &str,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE buyer_keys SET superseded_at = ?2 WHERE node_pubkey = ?1 AND superseded_at = ''",
+        rusqlite::params![node_pubkey, at],
+    )
+}
+
+/// Store `ann`, replacing the proxy's previous announcement.
+pub fn insert_proxy(
+    conn: &Connection,
+    ann: &ProxyAnnouncement,
+    expires_at: &str,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO proxies
+         (proxy_pubkey, proxy_address, proxy_ln_address, proxy_alias, fee_sats, schemes, ttl_secs,
+          announced_at, expires_at, signature)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        rusqlite::params![
+            ann.proxy_pubkey,
+            ann.proxy_address,
+            ann.proxy_ln_address,
+            ann.proxy_alias,
+            ann.fee_sats,
+            ann.schemes.join(","),
+            ann.ttl_secs,
+            ann.announced_at,
+            expires_at,
+            ann.signature,
+        ],
+    )
+}
+
+/// Drop announcements that expired by `now`.
+pub fn purge_expired_proxies(conn: &Connection, now: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM proxies WHERE expires_at <= ?1",
+        rusqlite::params![now],
+    )
+}
+
+pub fn insert_device(conn: &Connection, device: &Device) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO devices (device_pk, manufacturer_pk, model, issued_at, manufacturer_signature)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            device.device_pk,
+            device.manufacturer_pk,
+            device.model,
+            device.issued_at,
+            device.manufacturer_signature,
+        ],
+    )
+}
+
+/// Store `entry`, its value lowercased.
+pub fn insert_block(conn: &Connection, entry: &BlockEntry) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO blocklist (kind, value, reason, blocked_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![entry.kind.as_str(), entry.value.to_ascii_lowercase(), entry.reason, entry.blocked_at],
+    )
+}
+
+/// Every listing, blocked or not, oldest first (for export).
+pub fn all_listings(conn: &Connection) -> rusqlite::Result<Vec<ContentListing>> {
+    let sql = format!(
+        "SELECT {} FROM listings ORDER BY registered_at",
+        LISTING_COLS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map([], listing_from_row)?.collect();
+    items
+}
+
+/// Every seeder announcement, blocked or not, oldest first (for export).
+pub fn all_seeders(conn: &Connection) -> rusqlite::Result<Vec<SeederAnnouncement>> {
+    let sql = format!("SELECT {} FROM seeders ORDER BY announced_at", SEEDER_COLS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map([], seeder_from_row)?.collect();
+    items
+}
+
+/// Delete a listing and the seeder announcements for its encrypted hash.
+/// Returns `(listings, seeders)` removed; `(0, 0)` if there was no such listing.
+pub fn purge_listing(
+    conn: &mut Connection,
+    content_hash: &str,
+) -> rusqlite::Result<(usize, usize)> {
+    let tx = conn.transaction()?;
+    let encrypted_hash: Option<String> = tx
+        .query_row(
+            "SELECT encrypted_hash FROM listings WHERE content_hash = ?1",
+            rusqlite::params![content_hash],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(encrypted_hash) = encrypted_hash else {
+        return Ok((0, 0));
+    };
+    let seeders = tx.execute(
+        "DELETE FROM seeders WHERE encrypted_hash = ?1",
+        rusqlite::params![encrypted_hash],
+    )?;
+    let listings = tx.execute(
+        "DELETE FROM listings WHERE content_hash = ?1",
+        rusqlite::params![content_hash],
+    )?;
+    tx.commit()?;
+    Ok((listings, seeders))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_listing(conn: &Connection, content_hash: &str, creator: &str) {
+        conn.execute(
+            "INSERT INTO listings (content_hash, encrypted_hash, file_name, size_bytes, price_sats,
+             creator_pubkey, creator_address, creator_ln_address, registered_at)
+             VALUES (?1, ?2, 'f', 1, 1, ?3, '', '', '')",
+            rusqlite::params![content_hash, format!("enc-{}", content_hash), creator],
+        )
+        .unwrap();
+    }
+
+    fn visible(conn: &Connection) -> Vec<String> {
+        let sql = format!(
+            "SELECT content_hash FROM listings WHERE {} ORDER BY content_hash",
+            LISTING_NOT_BLOCKED
+        );
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let rows = stmt.query_map([], |r| r.get(0)).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn blocklist_hides_matching_listings() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        insert_listing(&conn, "a", "alice");
+        insert_listing(&conn, "b", "bob");
+        insert_listing(&conn, "c", "bob");
+        assert_eq!(visible(&conn), ["a", "b", "c"]);
+
+        conn.execute(
+            "INSERT INTO blocklist (kind, value, blocked_at) VALUES ('creator_pubkey', 'bob', '')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(visible(&conn), ["a"]);
+        assert!(is_blocked(&conn, BlockKind::CreatorPubkey, "bob"));
+        assert!(!is_blocked(&conn, BlockKind::SeederPubkey, "bob"));
+        // Hex values match whatever their case
+        assert!(is_blocked(&conn, BlockKind::CreatorPubkey, "BOB"));
+        insert_listing(&conn, "d", "BOB");
+        assert_eq!(visible(&conn), ["a"]);
+
+        conn.execute(
+            "INSERT INTO blocklist (kind, value, blocked_at) VALUES ('encrypted_hash', 'enc-a', '')",
+            [],
+        )
+        .unwrap();
+        assert!(visible(&conn).is_empty());
+    }
+
+    #[test]
+    fn purge_removes_listing_and_its_seeders() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        insert_listing(&conn, "a", "alice");
+        insert_listing(&conn, "b", "alice");
+        for (enc, pk) in [("enc-a", "s1"), ("enc-a", "s2"), ("enc-b", "s1")] {
+            conn.execute(
+                "INSERT INTO seeders (encrypted_hash, seeder_pubkey, seeder_address,
+                 seeder_ln_address, transport_price, announced_at) VALUES (?1, ?2, '', '', 0, '')",
+                rusqlite::params![enc, pk],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(purge_listing(&mut conn, "a").unwrap(), (1, 2));
+        assert_eq!(purge_listing(&mut conn, "a").unwrap(), (0, 0));
+        assert_eq!(visible(&conn), ["b"]);
+        assert_eq!(seeders_for(&conn, "enc-b").unwrap().len(), 1);
+
+        // Blocked seeders drop out of the public list but not the export
+        conn.execute(
+            "INSERT INTO blocklist (kind, value, blocked_at) VALUES ('seeder_pubkey', 'S1', '')",
+            [],
+        )
+        .unwrap();
+        assert!(list_seeders(&conn, None).unwrap().is_empty());
+        assert_eq!(all_seeders(&conn).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,212 @@
+//! TEE device certificates.
+//!
+//! A manufacturer certifies a device key by signing
+//! `conduit:device:v1:{device_pk}:{manufacturer_pk}:{model}:{issued_at}`
+//! with one of its manufacturer keys.  The chain is short: a device
+//! certificate is valid when that signature verifies, `manufacturer_pk` is
+//! a key of a registered manufacturer (its registration key, or one added
+//! by rotation, see `keys`), `issued_at` falls inside that key's validity
+//! window, and the manufacturer has not been revoked.  Revocation
+//! invalidates every certificate of the manufacturer, whenever it was
+//! issued and under whichever key.
+
+use std::fmt;
+
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db;
+use crate::keys::valid_at;
+use crate::signature::{is_compressed_pubkey, verify_device};
+use crate::types::{Device, Manufacturer};
+use crate::versioning::parse_cutoff;
+
+/// Why a device certificate does not chain to a registered manufacturer.
+#[derive(Debug)]
+pub enum ChainError {
+    InvalidDevicePk,
+    MissingSignature,
+    UnknownManufacturer,
+    RevokedManufacturer,
+    OutsideKeyWindow,
+    InvalidSignature,
+    Db(rusqlite::Error),
+}
+
+impl ChainError {
+    /// Machine-readable code, used both as the API error code and as the
+    /// `reason` in `DeviceVerification`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChainError::InvalidDevicePk => "invalid_pubkey",
+            ChainError::MissingSignature => "signature_required",
+            ChainError::UnknownManufacturer => "unknown_manufacturer",
+            ChainError::RevokedManufacturer => "manufacturer_revoked",
+            ChainError::OutsideKeyWindow => "outside_key_window",
+            ChainError::InvalidSignature => "invalid_signature",
+            ChainError::Db(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::InvalidDevicePk => {
+                write!(
+                    f,
+                    "device_pk must be a 33-byte compressed secp256k1 key (hex)"
+                )
+            }
+            ChainError::MissingSignature => write!(f, "manufacturer_signature is required"),
+            ChainError::UnknownManufacturer => {
+                write!(f, "manufacturer_pk is not a registered manufacturer")
+            }
+            ChainError::RevokedManufacturer => write!(f, "manufacturer_pk has been revoked"),
+            ChainError::OutsideKeyWindow => {
+                write!(
+                    f,
+                    "issued_at is outside the validity window of manufacturer_pk"
+                )
+            }
+            ChainError::InvalidSignature => {
+                write!(f, "Invalid manufacturer_signature: ECDSA verification failed against manufacturer_pk")
+            }
+            ChainError::Db(e) => write!(f, "database: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+impl From<rusqlite::Error> for ChainError {
+    fn from(e: rusqlite::Error) -> Self {
+        ChainError::Db(e)
+    }
+}
+
+/// Check `device` back to a registered manufacturer and return it.
+pub fn verify_chain(conn: &Connection, device: &Device) -> Result<Manufacturer, ChainError> {
+    if !is_compressed_pubkey(&device.device_pk) {
+        return Err(ChainError::InvalidDevicePk);
+    }
+    if device.manufacturer_signature.is_empty() {
+        return Err(ChainError::MissingSignature);
+    }
+    let key = db::get_key(conn, &device.manufacturer_pk)
+        .optional()?
+        .ok_or(ChainError::UnknownManufacturer)?;
+    let manufacturer = db::get_manufacturer(conn, &key.manufacturer_id)
+        .optional()?
+        .ok_or(ChainError::UnknownManufacturer)?;
+    if manufacturer.revocation.is_some() {
+        return Err(ChainError::RevokedManufacturer);
+    }
+    let issued_at = parse_cutoff(&device.issued_at).map_err(|_| ChainError::OutsideKeyWindow)?;
+    if !valid_at(&key, issued_at) {
+        return Err(ChainError::OutsideKeyWindow);
+    }
+    if !verify_device(device) {
+        return Err(ChainError::InvalidSignature);
+    }
+    Ok(manufacturer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::revocation::revoke;
+    use crate::signature::{pubkey_hex, sign_device, sign_manufacturer};
+    use secp256k1::SecretKey;
+
+    fn register(conn: &Connection, sk: &SecretKey) -> Manufacturer {
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(sk, &mut mfr);
+        mfr.id = mfr.pk_hex.clone();
+        db::insert_manufacturer(conn, &mfr).unwrap();
+        mfr
+    }
+
+    fn device(sk: &SecretKey) -> Device {
+        let mut device = Device {
+            device_pk: pubkey_hex(&SecretKey::from_slice(&[8; 32]).unwrap()),
+            manufacturer_pk: String::new(),
+            model: "Player 1".into(),
+            issued_at: "2026-02-01T00:00:00Z".into(),
+            manufacturer_signature: String::new(),
+        };
+        sign_device(sk, &mut device);
+        device
+    }
+
+    #[test]
+    fn chain_verifies_to_registered_manufacturer() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let acme = SecretKey::from_slice(&[5; 32]).unwrap();
+        let mfr = register(&conn, &acme);
+
+        let cert = device(&acme);
+        assert_eq!(verify_chain(&conn, &cert).unwrap(), mfr);
+
+        // Signed by a key nobody registered
+        let rogue = device(&SecretKey::from_slice(&[6; 32]).unwrap());
+        assert!(matches!(
+            verify_chain(&conn, &rogue),
+            Err(ChainError::UnknownManufacturer)
+        ));
+
+        // Tampered certificate
+        let mut tampered = cert.clone();
+        tampered.device_pk = pubkey_hex(&SecretKey::from_slice(&[9; 32]).unwrap());
+        assert!(matches!(
+            verify_chain(&conn, &tampered),
+            Err(ChainError::InvalidSignature)
+        ));
+
+        // Malformed keys are refused before any lookup, multi-byte text included
+        for device_pk in ["05".repeat(33), format!("a{}", "é".repeat(20))] {
+            let malformed = Device {
+                device_pk,
+                ..cert.clone()
+            };
+            assert!(matches!(
+                verify_chain(&conn, &malformed),
+                Err(ChainError::InvalidDevicePk)
+            ));
+        }
+
+        let unsigned = Device {
+            manufacturer_signature: String::new(),
+            ..cert.clone()
+        };
+        assert!(matches!(
+            verify_chain(&conn, &unsigned),
+            Err(ChainError::MissingSignature)
+        ));
+
+        // Issued before the key's window opened
+        let mut early = cert.clone();
+        early.issued_at = "2025-06-01T00:00:00Z".into();
+        sign_device(&acme, &mut early);
+        assert!(matches!(
+            verify_chain(&conn, &early),
+            Err(ChainError::OutsideKeyWindow)
+        ));
+
+        revoke(
+            &conn,
+            &SecretKey::from_slice(&[7; 32]).unwrap(),
+            &mfr.pk_hex,
+            "",
+        )
+        .unwrap();
+        assert!(matches!(
+            verify_chain(&conn, &cert),
+            Err(ChainError::RevokedManufacturer)
+        ));
+    }
+}
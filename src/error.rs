@@ -0,0 +1,85 @@
+//! API error responses for the Conduit Registry.
+//!
+//! Handlers return `ApiError` for every failure.  The body is the v1 shape,
+//! `{"error": "<message>"}`; the machine-readable code travels in a response
+//! extension so the v2 envelope layer (see `versioning.rs`) can turn it into
+//! `{"error": {"code": ..., "message": ...}}` without the handlers knowing
+//! which API version served the request.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+/// Machine-readable error code attached to error responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode(pub &'static str);
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn blocked(kind: &str) -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            "blocked",
+            format!("{} is blocked", kind),
+        )
+    }
+
+    pub fn internal(err: impl std::fmt::Display) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+            err.to_string(),
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut res = (
+            self.status,
+            Json(serde_json::json!({"error": self.message})),
+        )
+            .into_response();
+        res.extensions_mut().insert(ErrorCode(self.code));
+        res
+    }
+}
+
+/// Fallback code for error responses that did not come from an `ApiError`,
+/// e.g. extractor rejections and the body-limit layer.
+pub fn code_for_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::GONE => "gone",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "invalid_body",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        s if s.is_server_error() => "internal",
+        _ => "error",
+    }
+}
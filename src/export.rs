@@ -0,0 +1,555 @@
+//! NDJSON export and import of registry data.
+//!
+//! One JSON object per line, tagged by `type`:
+//!
+//! ```text
+//! {"type":"manufacturer","pk_hex":"...","name":"...",...}
+//! {"type":"manufacturer_key","manufacturer_id":"...","pk_hex":"...",...}
+//! {"type":"device","device_pk":"...","manufacturer_pk":"...",...}
+//! {"type":"buyer_key","node_pubkey":"...","pre_pk_hex":"...",...}
+//! {"type":"listing","content_hash":"...",...,"creator_signature":"..."}
+//! {"type":"seeder","encrypted_hash":"...","seeder_pubkey":"...",...}
+//! {"type":"block","kind":"content_hash","value":"...",...}
+//! ```
+//!
+//! Blocklist entries are the registry's tombstones: they travel with the
+//! data so moderated content stays hidden and refused on the destination.
+//! Export includes blocked listings and seeders for the same reason.
+//!
+//! Import re-verifies every listing's creator signature, manufacturer's
+//! proof-of-possession signature and revocation, key rotation, device
+//! certificate and buyer key signature, applies the field
+//! length caps, then upserts in a single transaction.  Records that fail
+//! either check are skipped or abort the whole import, per `OnInvalid`.
+//! Revocations must be signed by the key given as `revocation_issuer`, and
+//! an upsert never lifts a revocation already in the database.  A rotated
+//! key must be signed by a key its manufacturer held at the time, either
+//! already in the database or earlier in the input.
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::db;
+use crate::keys;
+use crate::signature::{
+    verify_buyer_key, verify_device, verify_listing, verify_manufacturer, verify_revocation,
+    verify_rotation,
+};
+use crate::types::{
+    BlockEntry, BuyerKey, ContentListing, Device, Manufacturer, ManufacturerKey, SeederAnnouncement,
+};
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// One line of an export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Manufacturer(Manufacturer),
+    ManufacturerKey(ManufacturerKey),
+    Device(Device),
+    BuyerKey(BuyerKey),
+    Listing(Box<ContentListing>),
+    Seeder(SeederAnnouncement),
+    Block(BlockEntry),
+}
+
+/// Records written or imported, by type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RecordCounts {
+    pub manufacturers: usize,
+    pub manufacturer_keys: usize,
+    pub devices: usize,
+    pub buyer_keys: usize,
+    pub listings: usize,
+    pub seeders: usize,
+    pub blocks: usize,
+}
+
+impl RecordCounts {
+    fn add(&mut self, record: &Record) {
+        match record {
+            Record::Manufacturer(_) => self.manufacturers += 1,
+            Record::ManufacturerKey(_) => self.manufacturer_keys += 1,
+            Record::Device(_) => self.devices += 1,
+            Record::BuyerKey(_) => self.buyer_keys += 1,
+            Record::Listing(_) => self.listings += 1,
+            Record::Seeder(_) => self.seeders += 1,
+            Record::Block(_) => self.blocks += 1,
+        }
+    }
+}
+
+impl fmt::Display for RecordCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} listings, {} seeders, {} manufacturers, {} manufacturer keys, {} devices, {} buyer keys, {} blocklist entries",
+            self.listings,
+            self.seeders,
+            self.manufacturers,
+            self.manufacturer_keys,
+            self.devices,
+            self.buyer_keys,
+            self.blocks
+        )
+    }
+}
+
+/// Write every record in the database to `out`, one per line.
+pub fn export(conn: &Connection, out: impl Write) -> io::Result<RecordCounts> {
+    let records = records(conn).map_err(io::Error::other)?;
+    write(records, out)
+}
+
+/// Every record in the database, in export order: whatever a record refers
+/// to comes before it.
+pub fn records(conn: &Connection) -> rusqlite::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    records.extend(
+        db::list_manufacturers(conn, None)?
+            .into_iter()
+            .map(Record::Manufacturer),
+    );
+    records.extend(db::all_keys(conn)?.into_iter().map(Record::ManufacturerKey));
+    records.extend(db::all_devices(conn)?.into_iter().map(Record::Device));
+    records.extend(db::all_buyer_keys(conn)?.into_iter().map(Record::BuyerKey));
+    records.extend(
+        db::all_listings(conn)?
+            .into_iter()
+            .map(|l| Record::Listing(Box::new(l))),
+    );
+    records.extend(db::all_seeders(conn)?.into_iter().map(Record::Seeder));
+    records.extend(db::list_blocks(conn, None)?.into_iter().map(Record::Block));
+    Ok(records)
+}
+
+/// Write `records` to `out`, one per line.
+pub fn write(records: Vec<Record>, mut out: impl Write) -> io::Result<RecordCounts> {
+    let mut counts = RecordCounts::default();
+    for record in records {
+        serde_json::to_writer(&mut out, &record)?;
+        out.write_all(b"\n")?;
+        counts.add(&record);
+    }
+    out.flush()?;
+    Ok(counts)
+}
+
+/// What to do with a record that fails validation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnInvalid {
+    /// Leave it out and keep going
+    Skip,
+    /// Abort and roll back the whole import
+    #[default]
+    Fail,
+}
+
+/// A record that was not imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Rejected {
+    /// 1-based line number in the input
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ImportSummary {
+    pub imported: RecordCounts,
+    pub skipped: Vec<Rejected>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Db(rusqlite::Error),
+    /// A record failed validation under `OnInvalid::Fail`; nothing was imported.
+    Invalid(Rejected),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "reading input: {}", e),
+            ImportError::Db(e) => write!(f, "database: {}", e),
+            ImportError::Invalid(r) => write!(f, "line {}: {}", r.line, r.reason),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for ImportError {
+    fn from(e: rusqlite::Error) -> Self {
+        ImportError::Db(e)
+    }
+}
+
+/// Parse and validate one line against `conn`, the rows imported so far
+/// included.
+fn check(conn: &Connection, line: &str, revocation_issuer: Option<&str>) -> Result<Record, String> {
+    let record: Record =
+        serde_json::from_str(line).map_err(|e| format!("invalid record: {}", e))?;
+    match &record {
+        Record::Listing(l) => {
+            if l.creator_signature.is_empty() {
+                return Err(format!(
+                    "listing {} has no creator_signature",
+                    l.content_hash
+                ));
+            }
+            if !verify_listing(l) {
+                return Err(format!(
+                    "listing {} has an invalid creator_signature",
+                    l.content_hash
+                ));
+            }
+        }
+        Record::Manufacturer(m) => {
+            if m.signature.is_empty() {
+                return Err(format!("manufacturer {} has no signature", m.pk_hex));
+            }
+            if !verify_manufacturer(m) {
+                return Err(format!(
+                    "manufacturer {} has an invalid signature",
+                    m.pk_hex
+                ));
+            }
+            if let Some(rev) = &m.revocation {
+                let Some(issuer) = revocation_issuer else {
+                    return Err(format!(
+                        "manufacturer {} is revoked, but no revocation issuer is set",
+                        m.pk_hex
+                    ));
+                };
+                if rev.pk_hex != m.pk_hex || !verify_revocation(rev, issuer) {
+                    return Err(format!(
+                        "manufacturer {} has an invalid revocation signature",
+                        m.pk_hex
+                    ));
+                }
+            }
+        }
+        // The registration key is vouched for by the manufacturer record;
+        // rotated keys carry their own statement
+        Record::ManufacturerKey(k) if k.signed_by.is_empty() => {
+            if k.pk_hex != k.manufacturer_id {
+                return Err(format!(
+                    "manufacturer key {} has no rotation signature",
+                    k.pk_hex
+                ));
+            }
+        }
+        Record::ManufacturerKey(k) => {
+            if !verify_rotation(k) {
+                return Err(format!(
+                    "manufacturer key {} has an invalid rotation signature",
+                    k.pk_hex
+                ));
+            }
+            let current =
+                keys::signer_was_current(conn, k).map_err(|e| format!("database: {}", e))?;
+            if !current {
+                return Err(format!(
+                    "manufacturer key {} is not signed by a key of {} current at not_before",
+                    k.pk_hex, k.manufacturer_id
+                ));
+            }
+        }
+        Record::Device(d) => {
+            if d.manufacturer_signature.is_empty() {
+                return Err(format!(
+                    "device {} has no manufacturer_signature",
+                    d.device_pk
+                ));
+            }
+            if !verify_device(d) {
+                return Err(format!(
+                    "device {} has an invalid manufacturer_signature",
+                    d.device_pk
+                ));
+            }
+        }
+        Record::BuyerKey(k) => {
+            if !verify_buyer_key(k) {
+                return Err(format!(
+                    "buyer key {} has an invalid signature",
+                    k.node_pubkey
+                ));
+            }
+        }
+        Record::Seeder(_) | Record::Block(_) => {}
+    }
+    Ok(record)
+}
+
+/// Upsert the records in `input` into the database.  Existing rows with the
+/// same key are replaced; nothing is deleted.  `revocation_issuer` is the
+/// public key (hex) manufacturer revocations must be signed with; without
+/// it, revoked manufacturers are invalid.
+pub fn import(
+    conn: &mut Connection,
+    input: impl BufRead,
+    on_invalid: OnInvalid,
+    revocation_issuer: Option<&str>,
+) -> Result<ImportSummary, ImportError> {
+    let tx = conn.transaction()?;
+    let mut summary = ImportSummary::default();
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match check(&tx, &line, revocation_issuer) {
+            Ok(r) => r,
+            Err(reason) => {
+                let rejected = Rejected {
+                    line: i + 1,
+                    reason,
+                };
+                match on_invalid {
+                    OnInvalid::Skip => {
+                        summary.skipped.push(rejected);
+                        continue;
+                    }
+                    // Dropping `tx` rolls back
+                    OnInvalid::Fail => return Err(ImportError::Invalid(rejected)),
+                }
+            }
+        };
+        match &record {
+            Record::Manufacturer(m) => db::insert_manufacturer(&tx, m)?,
+            Record::ManufacturerKey(k) => db::insert_key(&tx, k)?,
+            Record::Device(d) => db::insert_device(&tx, d)?,
+            Record::BuyerKey(k) => db::insert_buyer_key(&tx, k)?,
+            Record::Listing(l) => db::insert_listing(&tx, l)?,
+            Record::Seeder(s) => db::insert_seeder(&tx, s)?,
+            Record::Block(b) => db::insert_block(&tx, b)?,
+        };
+        summary.imported.add(&record);
+    }
+
+    tx.commit()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys;
+    use crate::signature::{
+        pubkey_hex, sign_listing, sign_manufacturer, sign_revocation, sign_rotation,
+    };
+    use crate::types::{BlockKind, Revocation};
+    use secp256k1::SecretKey;
+
+    fn listing(content_hash: &str) -> ContentListing {
+        let mut l: ContentListing = serde_json::from_value(serde_json::json!({
+            "content_hash": content_hash, "encrypted_hash": format!("enc-{}", content_hash),
+            "file_name": "f.mp4", "size_bytes": 1, "price_sats": 10, "chunk_size": 1,
+            "chunk_count": 1, "plaintext_root": "p", "encrypted_root": "r",
+            "creator_pubkey": "", "creator_address": "", "creator_ln_address": "",
+            "creator_alias": "", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_listing(&SecretKey::from_slice(&[3; 32]).unwrap(), &mut l);
+        l
+    }
+
+    fn fresh() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        db::init_db(&conn);
+        conn
+    }
+
+    fn seeded() -> Connection {
+        let conn = fresh();
+        db::insert_listing(&conn, &listing("a")).unwrap();
+        db::insert_listing(&conn, &listing("b")).unwrap();
+        db::insert_block(
+            &conn,
+            &BlockEntry {
+                kind: BlockKind::ContentHash,
+                value: "b".into(),
+                reason: "dmca".into(),
+                blocked_at: "2026-02-01T00:00:00Z".into(),
+            },
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let mut out = Vec::new();
+        let counts = export(&seeded(), &mut out).unwrap();
+        assert_eq!((counts.listings, counts.blocks), (2, 1));
+
+        let mut dest = fresh();
+        let summary = import(&mut dest, out.as_slice(), OnInvalid::Fail, None).unwrap();
+        assert_eq!(summary.imported, counts);
+        assert!(summary.skipped.is_empty());
+
+        let mut again = Vec::new();
+        export(&dest, &mut again).unwrap();
+        assert_eq!(out, again);
+        // The tombstone still hides the blocked listing
+        assert!(db::get_listing(&dest, "b").is_err());
+    }
+
+    #[test]
+    fn invalid_records_skip_or_fail() {
+        let mut forged = listing("c");
+        forged.price_sats = 1;
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(&SecretKey::from_slice(&[4; 32]).unwrap(), &mut mfr);
+        let unsigned = Manufacturer {
+            signature: String::new(),
+            ..mfr.clone()
+        };
+        let input = [
+            serde_json::to_string(&Record::Listing(Box::new(listing("a")))).unwrap(),
+            serde_json::to_string(&Record::Listing(Box::new(forged))).unwrap(),
+            "{\"type\":\"unknown\"}".to_string(),
+            serde_json::to_string(&Record::Manufacturer(mfr)).unwrap(),
+            serde_json::to_string(&Record::Manufacturer(unsigned)).unwrap(),
+        ]
+        .join("\n");
+
+        let mut conn = fresh();
+        match import(&mut conn, input.as_bytes(), OnInvalid::Fail, None) {
+            Err(ImportError::Invalid(r)) => assert_eq!(r.line, 2),
+            other => panic!("expected failure, got {:?}", other),
+        }
+        assert!(
+            db::all_listings(&conn).unwrap().is_empty(),
+            "fail-fast rolls back"
+        );
+
+        let summary = import(&mut conn, input.as_bytes(), OnInvalid::Skip, None).unwrap();
+        assert_eq!(
+            (summary.imported.listings, summary.imported.manufacturers),
+            (1, 1)
+        );
+        let lines: Vec<usize> = summary.skipped.iter().map(|r| r.line).collect();
+        assert_eq!(lines, [2, 3, 5]);
+    }
+
+    #[test]
+    fn rotated_keys_must_be_signed_by_their_manufacturer() {
+        let old = SecretKey::from_slice(&[4; 32]).unwrap();
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(&old, &mut mfr);
+        let source = fresh();
+        db::insert_manufacturer(&source, &mfr).unwrap();
+        let mut key = ManufacturerKey {
+            manufacturer_id: mfr.pk_hex.clone(),
+            pk_hex: pubkey_hex(&SecretKey::from_slice(&[5; 32]).unwrap()),
+            not_before: "2099-01-01T00:00:00Z".into(),
+            not_after: String::new(),
+            retired_at: String::new(),
+            signed_by: String::new(),
+            signature: String::new(),
+        };
+        sign_rotation(&old, &mut key);
+        keys::rotate(&source, &mfr.pk_hex, &key).unwrap();
+        let mut out = Vec::new();
+        export(&source, &mut out).unwrap();
+        let mut dest = fresh();
+        assert_eq!(
+            import(&mut dest, out.as_slice(), OnInvalid::Fail, None)
+                .unwrap()
+                .imported
+                .manufacturer_keys,
+            2
+        );
+
+        // A key signing itself onto Acme is not Acme's key
+        let intruder = SecretKey::from_slice(&[6; 32]).unwrap();
+        let mut forged = ManufacturerKey {
+            pk_hex: pubkey_hex(&intruder),
+            ..key
+        };
+        sign_rotation(&intruder, &mut forged);
+        let line = serde_json::to_string(&Record::ManufacturerKey(forged.clone())).unwrap();
+        assert!(matches!(
+            import(&mut dest, line.as_bytes(), OnInvalid::Fail, None),
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(db::get_key(&dest, &forged.pk_hex).is_err());
+    }
+
+    #[test]
+    fn revocations_are_verified_and_never_lifted() {
+        let registry = SecretKey::from_slice(&[8; 32]).unwrap();
+        let issuer = pubkey_hex(&registry);
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(&SecretKey::from_slice(&[4; 32]).unwrap(), &mut mfr);
+        let mut rev = Revocation {
+            pk_hex: mfr.pk_hex.clone(),
+            revoked_at: "2026-03-01T00:00:00Z".into(),
+            reason: "key compromised".into(),
+            signature: String::new(),
+        };
+        sign_revocation(&registry, &mut rev);
+        let revoked = Manufacturer {
+            revocation: Some(rev.clone()),
+            ..mfr.clone()
+        };
+        let line =
+            |m: &Manufacturer| serde_json::to_string(&Record::Manufacturer(m.clone())).unwrap();
+
+        let mut conn = fresh();
+        assert!(import(&mut conn, line(&revoked).as_bytes(), OnInvalid::Fail, None).is_err());
+        let stranger = pubkey_hex(&SecretKey::from_slice(&[9; 32]).unwrap());
+        assert!(import(
+            &mut conn,
+            line(&revoked).as_bytes(),
+            OnInvalid::Fail,
+            Some(&stranger)
+        )
+        .is_err());
+        import(
+            &mut conn,
+            line(&revoked).as_bytes(),
+            OnInvalid::Fail,
+            Some(&issuer),
+        )
+        .unwrap();
+
+        // A stale export without the revocation does not lift it
+        import(
+            &mut conn,
+            line(&mfr).as_bytes(),
+            OnInvalid::Fail,
+            Some(&issuer),
+        )
+        .unwrap();
+        assert_eq!(
+            db::get_manufacturer(&conn, &mfr.pk_hex).unwrap().revocation,
+            Some(rev)
+        );
+    }
+}
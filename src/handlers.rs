@@ -1,98 +1,122 @@
 //! HTTP handler functions for the Conduit Registry API.
+//!
+//! Route paths in the `#[utoipa::path]` attributes are relative to the
+//! version prefix (`/api/v1`, `/api/v2`) they are mounted under in `lib.rs`.
+//! Handlers shared by both versions come first; the v2-only variants
+//! (paginated lists) are at the bottom.
 
+use std::io;
+
+use axum::body::{Body, Bytes};
 use axum::extract::{Path, Query, State};
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::IntoResponse;
 use axum::Json;
+use tokio::sync::mpsc;
 
-use crate::db::{listing_from_row, LISTING_COLS};
-use crate::signature::verify_lightning_signature;
+use crate::attestation::{self, AttestationError};
+use crate::buyer_keys::{self, BuyerKeyError};
+use crate::db::{self, is_blocked};
+use crate::devices::{self, ChainError};
+use crate::error::ApiError;
+use crate::export::{self, ImportError, ImportSummary, NDJSON_CONTENT_TYPE};
+use crate::info::RegistryInfo;
+use crate::keys::{self, RotationError};
+use crate::limits::DESCRIPTION_MAX;
+use crate::policy::{self, PolicyError};
+use crate::pre;
+use crate::proxies::{self, ProxyError};
+use crate::ratelimit::{RateLimited, RouteClass};
+use crate::revocation;
+use crate::signature::{abbrev, pubkey_hex, verify_listing, verify_manufacturer};
+use crate::snapshot::{self, SnapshotInfo};
 use crate::types::{
-    AppState, ContentListing, DiscoverResponse, Manufacturer, SearchParams, SeederAnnouncement,
+    AppState, Attestation, AttestationVerdict, BlockEntry, BlockKind, BuyerKey, ContentListing,
+    DeletedResponse, Device, DeviceResponse, DeviceVerification, DiscoverResponse, ErrorResponse,
+    ImportParams, Items, KeysAtParams, Manufacturer, ManufacturerKey, OkResponse, PageParams,
+    Paged, ProxyAnnouncement, ProxyParams, Revocation, RevocationList, RevokeParams, SearchParams,
+    SeederAnnouncement,
 };
 
-fn listing_canonical_message(
-    content_hash: &str,
-    encrypted_hash: &str,
-    encrypted_root: &str,
-    price_sats: u64,
-    creator_pubkey: &str,
-) -> String {
-    format!(
-        "conduit:listing:v1:{}:{}:{}:{}:{}",
-        content_hash, encrypted_hash, encrypted_root, price_sats, creator_pubkey
-    )
-}
-
 /// POST /api/listings -- creator publishes a content listing
+#[utoipa::path(
+    post,
+    path = "/listings",
+    tag = "listings",
+    request_body = ContentListing,
+    responses(
+        (status = 200, description = "Listing stored", body = OkResponse),
+        (status = 400, description = "Missing or invalid creator_signature, invalid playback policy or allowed manufacturers, or malformed PRE capsule", body = ErrorResponse),
+        (status = 403, description = "Listing matches a blocklist entry", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
 pub async fn create_listing(
     State(state): State<AppState>,
     Json(listing): Json<ContentListing>,
 ) -> impl IntoResponse {
+    {
+        let db = state.db.lock().unwrap();
+        let blocked = [
+            (BlockKind::ContentHash, &listing.content_hash),
+            (BlockKind::EncryptedHash, &listing.encrypted_hash),
+            (BlockKind::CreatorPubkey, &listing.creator_pubkey),
+        ]
+        .into_iter()
+        .find(|(kind, value)| is_blocked(&db, *kind, value));
+        if let Some((kind, _)) = blocked {
+            eprintln!(
+                "Rejected blocked listing {} ({})",
+                listing.content_hash,
+                kind.as_str()
+            );
+            return ApiError::blocked(kind.as_str()).into_response();
+        }
+    }
+
     // Verify creator signature (Layer 2)
     if listing.creator_signature.is_empty() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": "creator_signature is required"})),
-        );
+        return ApiError::bad_request("signature_required", "creator_signature is required")
+            .into_response();
+    }
+    if let Err(e) = policy::validate(&listing.playback_policy, &listing.playback_params) {
+        return ApiError::bad_request(e.code(), e.to_string()).into_response();
+    }
+    {
+        let db = state.db.lock().unwrap();
+        match policy::check_manufacturers(&db, &listing.playback_params) {
+            Ok(()) => {}
+            Err(PolicyError::Db(e)) => return ApiError::internal(e).into_response(),
+            Err(e) => return ApiError::bad_request(e.code(), e.to_string()).into_response(),
+        }
+    }
+    let pre_scheme = state.config.read().unwrap().registry.pre_scheme();
+    if let Err(e) = pre::check_listing(pre_scheme, &listing) {
+        return ApiError::bad_request(e.code(), e.to_string()).into_response();
     }
 
-    let canonical = listing_canonical_message(
-        &listing.content_hash,
-        &listing.encrypted_hash,
-        &listing.encrypted_root,
-        listing.price_sats,
-        &listing.creator_pubkey,
-    );
-
-    if !verify_lightning_signature(
-        canonical.as_bytes(),
-        &listing.creator_signature,
-        &listing.creator_pubkey,
-    ) {
+    if !verify_listing(&listing) {
         eprintln!(
             "Signature verification FAILED for listing {} (creator {})",
             listing.content_hash,
-            &listing.creator_pubkey[..16.min(listing.creator_pubkey.len())]
-        );
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "Invalid creator_signature: ECDSA verification failed against creator_pubkey"
-            })),
+            abbrev(&listing.creator_pubkey)
         );
+        return ApiError::bad_request(
+            "invalid_signature",
+            "Invalid creator_signature: ECDSA verification failed against creator_pubkey",
+        )
+        .into_response();
+    }
+    // Only a verified signature may spend the creator's bucket
+    if let Err(retry_after) = state.limiter.check(
+        RouteClass::Verify,
+        &format!("pk:{}", listing.creator_pubkey),
+    ) {
+        return RateLimited(retry_after).into_response();
     }
 
     let db = state.db.lock().unwrap();
-    let result = db.execute(
-        "INSERT OR REPLACE INTO listings
-         (content_hash, encrypted_hash, file_name, size_bytes, price_sats,
-          chunk_size, chunk_count, plaintext_root, encrypted_root,
-          creator_pubkey, creator_address, creator_ln_address, creator_alias, registered_at,
-          pre_c1_hex, pre_c2_hex, pre_pk_creator_hex, playback_policy, creator_signature)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
-        rusqlite::params![
-            listing.content_hash,
-            listing.encrypted_hash,
-            listing.file_name,
-            listing.size_bytes,
-            listing.price_sats,
-            listing.chunk_size,
-            listing.chunk_count,
-            listing.plaintext_root,
-            listing.encrypted_root,
-            listing.creator_pubkey,
-            listing.creator_address,
-            listing.creator_ln_address,
-            listing.creator_alias,
-            listing.registered_at,
-            listing.pre_c1_hex,
-            listing.pre_c2_hex,
-            listing.pre_pk_creator_hex,
-            listing.playback_policy,
-            listing.creator_signature,
-        ],
-    );
+    let result = db::insert_listing(&db, &listing);
 
     match result {
         Ok(_) => {
@@ -100,119 +124,109 @@ pub async fn create_listing(
                 "Listing stored (sig verified): {} ({})",
                 listing.file_name, listing.content_hash
             );
-            (StatusCode::OK, Json(serde_json::json!({"ok": true})))
+            (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
         }
         Err(e) => {
             eprintln!("Failed to store listing: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": e.to_string()})),
-            )
+            ApiError::internal(e).into_response()
         }
     }
 }
 
 /// GET /api/listings -- list all content listings
+#[utoipa::path(
+    get,
+    path = "/listings",
+    tag = "listings",
+    responses((status = 200, description = "All visible listings", body = Items<ContentListing>))
+)]
 pub async fn list_listings(State(state): State<AppState>) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
-    let sql = format!("SELECT {} FROM listings ORDER BY registered_at DESC", LISTING_COLS);
-    let mut stmt = db.prepare(&sql).unwrap();
-
-    let items: Vec<ContentListing> = stmt
-        .query_map([], listing_from_row)
-        .unwrap()
-        .filter_map(|r| r.ok())
-        .collect();
-
-    Json(serde_json::json!({ "items": items }))
+    match db::search_listings(&db, &SearchParams::default(), None) {
+        Ok(items) => Json(serde_json::json!({ "items": items })).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
 }
 
 /// GET /api/listings/{content_hash} -- get a specific listing
+#[utoipa::path(
+    get,
+    path = "/listings/{content_hash}",
+    tag = "listings",
+    params(("content_hash" = String, Path, description = "Plaintext content hash")),
+    responses(
+        (status = 200, description = "The listing", body = ContentListing),
+        (status = 404, description = "Listing not found", body = ErrorResponse),
+    )
+)]
 pub async fn get_listing(
     State(state): State<AppState>,
     Path(content_hash): Path<String>,
 ) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
-    let sql = format!("SELECT {} FROM listings WHERE content_hash = ?1", LISTING_COLS);
-    let result = db.query_row(&sql, rusqlite::params![content_hash], listing_from_row);
-
-    match result {
+    match db::get_listing(&db, &content_hash) {
         Ok(listing) => (StatusCode::OK, Json(serde_json::json!(listing))).into_response(),
-        Err(_) => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Listing not found"})),
-        )
-            .into_response(),
+        Err(_) => ApiError::not_found("Listing not found").into_response(),
     }
 }
 
-/// GET /api/search?q=term&type=mp4&max_price=1000 -- search listings
+/// GET /api/search?q=term&type=mp4&max_price=1000&policy=open -- search listings
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "listings",
+    params(SearchParams),
+    responses((status = 200, description = "Matching listings", body = Items<ContentListing>))
+)]
 pub async fn search_listings(
     State(state): State<AppState>,
     Query(params): Query<SearchParams>,
 ) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
-
-    // Build dynamic query
-    let mut sql = format!("SELECT {} FROM listings WHERE 1=1", LISTING_COLS);
-    let mut bind_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-    let mut param_idx = 1;
-
-    if let Some(ref q) = params.q {
-        sql.push_str(&format!(" AND file_name LIKE ?{}", param_idx));
-        bind_values.push(Box::new(format!("%{}%", q)));
-        param_idx += 1;
-    }
-
-    if let Some(ref content_type) = params.content_type {
-        sql.push_str(&format!(" AND file_name LIKE ?{}", param_idx));
-        bind_values.push(Box::new(format!("%.{}", content_type)));
-        param_idx += 1;
-    }
-
-    if let Some(max_price) = params.max_price {
-        sql.push_str(&format!(" AND price_sats <= ?{}", param_idx));
-        bind_values.push(Box::new(max_price as i64));
-        // param_idx += 1;  // last param
+    match db::search_listings(&db, &params, None) {
+        Ok(items) => Json(serde_json::json!({ "items": items })).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
     }
-
-    sql.push_str(" ORDER BY registered_at DESC");
-
-    let mut stmt = db.prepare(&sql).unwrap();
-    let params_ref: Vec<&dyn rusqlite::types::ToSql> =
-        bind_values.iter().map(|b| b.as_ref()).collect();
-
-    let items: Vec<ContentListing> = stmt
-        .query_map(params_ref.as_slice(), listing_from_row)
-        .unwrap()
-        .filter_map(|r| r.ok())
-        .collect();
-
-    Json(serde_json::json!({ "items": items }))
 }
 
 /// POST /api/seeders -- seeder announces availability
+#[utoipa::path(
+    post,
+    path = "/seeders",
+    tag = "seeders",
+    request_body = SeederAnnouncement,
+    responses(
+        (status = 200, description = "Announcement stored", body = OkResponse),
+        (status = 403, description = "Announcement matches a blocklist entry", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
 pub async fn create_seeder(
     State(state): State<AppState>,
     Json(announcement): Json<SeederAnnouncement>,
 ) -> impl IntoResponse {
+    let pk_key = format!("pk:{}", announcement.seeder_pubkey);
+    if let Err(retry_after) = state.limiter.check(RouteClass::Write, &pk_key) {
+        return RateLimited(retry_after).into_response();
+    }
+
     let db = state.db.lock().unwrap();
-    let result = db.execute(
-        "INSERT OR REPLACE INTO seeders
-         (encrypted_hash, seeder_pubkey, seeder_address, seeder_ln_address, seeder_alias,
-          transport_price, chunk_count, announced_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        rusqlite::params![
+    let blocked = [
+        (BlockKind::EncryptedHash, &announcement.encrypted_hash),
+        (BlockKind::SeederPubkey, &announcement.seeder_pubkey),
+    ]
+    .into_iter()
+    .find(|(kind, value)| is_blocked(&db, *kind, value));
+    if let Some((kind, _)) = blocked {
+        eprintln!(
+            "Rejected blocked seeder announcement for {} ({})",
             announcement.encrypted_hash,
-            announcement.seeder_pubkey,
-            announcement.seeder_address,
-            announcement.seeder_ln_address,
-            announcement.seeder_alias,
-            announcement.transport_price,
-            announcement.chunk_count,
-            announcement.announced_at,
-        ],
-    );
+            kind.as_str()
+        );
+        return ApiError::blocked(kind.as_str()).into_response();
+    }
+
+    let result = db::insert_seeder(&db, &announcement);
 
     match result {
         Ok(_) => {
@@ -220,19 +234,29 @@ pub async fn create_seeder(
                 "Seeder announced: {} for {}",
                 announcement.seeder_address, announcement.encrypted_hash
             );
-            (StatusCode::OK, Json(serde_json::json!({"ok": true})))
+            (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
         }
         Err(e) => {
             eprintln!("Failed to store seeder: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": e.to_string()})),
-            )
+            ApiError::internal(e).into_response()
         }
     }
 }
 
 /// GET /api/discover/{content_hash} -- listing + all seeders for that content
+///
+/// Listings with a PRE capsule also get up to `proxies::DISCOVER_PROXIES`
+/// live proxies for the registry's `pre_scheme`.
+#[utoipa::path(
+    get,
+    path = "/discover/{content_hash}",
+    tag = "listings",
+    params(("content_hash" = String, Path, description = "Plaintext content hash")),
+    responses(
+        (status = 200, description = "Listing, its seeders, allowed manufacturers and candidate re-encryption proxies", body = DiscoverResponse),
+        (status = 404, description = "Listing not found", body = ErrorResponse),
+    )
+)]
 pub async fn discover(
     State(state): State<AppState>,
     Path(content_hash): Path<String>,
@@ -240,51 +264,45 @@ pub async fn discover(
     let db = state.db.lock().unwrap();
 
     // Get the listing
-    let sql = format!("SELECT {} FROM listings WHERE content_hash = ?1", LISTING_COLS);
-    let listing_result = db.query_row(&sql, rusqlite::params![content_hash], listing_from_row);
-
-    let listing = match listing_result {
+    let listing = match db::get_listing(&db, &content_hash) {
         Ok(l) => l,
-        Err(_) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({"error": "Listing not found"})),
-            )
-                .into_response();
-        }
+        Err(_) => return ApiError::not_found("Listing not found").into_response(),
     };
 
     // Get all seeders for this content's encrypted_hash
-    let mut stmt = db
-        .prepare(
-            "SELECT encrypted_hash, seeder_pubkey, seeder_address, seeder_ln_address, seeder_alias,
-                    transport_price, chunk_count, announced_at
-             FROM seeders WHERE encrypted_hash = ?1",
-        )
-        .unwrap();
-
-    let seeders: Vec<SeederAnnouncement> = stmt
-        .query_map(rusqlite::params![listing.encrypted_hash], |row| {
-            Ok(SeederAnnouncement {
-                encrypted_hash: row.get(0)?,
-                seeder_pubkey: row.get(1)?,
-                seeder_address: row.get(2)?,
-                seeder_ln_address: row.get(3)?,
-                seeder_alias: row.get(4)?,
-                transport_price: row.get(5)?,
-                chunk_count: row.get(6)?,
-                announced_at: row.get(7)?,
-            })
-        })
-        .unwrap()
-        .filter_map(|r| r.ok())
-        .collect();
+    let seeders = match db::seeders_for(&db, &listing.encrypted_hash) {
+        Ok(s) => s,
+        Err(e) => return ApiError::internal(e).into_response(),
+    };
+
+    let manufacturers =
+        match db::manufacturers_by_id(&db, &listing.playback_params.allowed_manufacturers) {
+            Ok(m) => m,
+            Err(e) => return ApiError::internal(e).into_response(),
+        };
 
-    let response = DiscoverResponse { listing, seeders };
+    let pre_scheme = state.config.read().unwrap().registry.pre_scheme.clone();
+    let proxies = match proxies::candidates(&db, &listing, &pre_scheme) {
+        Ok(p) => p,
+        Err(e) => return ApiError::internal(e).into_response(),
+    };
+
+    let response = DiscoverResponse {
+        listing,
+        seeders,
+        manufacturers,
+        proxies,
+    };
     (StatusCode::OK, Json(serde_json::json!(response))).into_response()
 }
 
 /// DELETE /api/listings -- clear all listings (for test re-provisioning)
+#[utoipa::path(
+    delete,
+    path = "/listings",
+    tag = "admin",
+    responses((status = 200, description = "Listings removed", body = DeletedResponse))
+)]
 pub async fn delete_all_listings(State(state): State<AppState>) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
     let deleted = db.execute("DELETE FROM listings", []).unwrap_or(0);
@@ -293,6 +311,12 @@ pub async fn delete_all_listings(State(state): State<AppState>) -> impl IntoResp
 }
 
 /// DELETE /api/seeders -- clear all seeder announcements (for test re-provisioning)
+#[utoipa::path(
+    delete,
+    path = "/seeders",
+    tag = "admin",
+    responses((status = 200, description = "Announcements removed", body = DeletedResponse))
+)]
 pub async fn delete_all_seeders(State(state): State<AppState>) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
     let deleted = db.execute("DELETE FROM seeders", []).unwrap_or(0);
@@ -305,133 +329,1108 @@ pub async fn delete_all_seeders(State(state): State<AppState>) -> impl IntoRespo
 // ---------------------------------------------------------------------------
 
 /// POST /api/manufacturers -- register a TEE device manufacturer
+///
+/// The registrant proves it holds the manufacturer key by signing
+/// `conduit:manufacturer:v1:{pk_hex}:{name}:{registered_at}` with it.
+#[utoipa::path(
+    post,
+    path = "/manufacturers",
+    tag = "manufacturers",
+    request_body = Manufacturer,
+    responses(
+        (status = 200, description = "Manufacturer registered", body = OkResponse),
+        (status = 400, description = "Missing registered_at, or missing or invalid signature", body = ErrorResponse),
+        (status = 401, description = "Admin token required", body = ErrorResponse),
+        (status = 409, description = "The key has been revoked, or belongs to another manufacturer", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
 pub async fn create_manufacturer(
     State(state): State<AppState>,
     Json(mut mfr): Json<Manufacturer>,
 ) -> impl IntoResponse {
+    mfr.id = mfr.pk_hex.clone();
+    mfr.revocation = None;
     if mfr.registered_at.is_empty() {
-        mfr.registered_at = chrono::Utc::now().to_rfc3339();
+        return ApiError::bad_request(
+            "registered_at_required",
+            "registered_at is required: it is part of the signed message",
+        )
+        .into_response();
+    }
+    if mfr.signature.is_empty() {
+        return ApiError::bad_request("signature_required", "signature is required")
+            .into_response();
     }
+    if !verify_manufacturer(&mfr) {
+        eprintln!(
+            "Signature verification FAILED for manufacturer {} ({})",
+            mfr.name,
+            abbrev(&mfr.pk_hex)
+        );
+        return ApiError::bad_request(
+            "invalid_signature",
+            "Invalid signature: ECDSA verification failed against pk_hex",
+        )
+        .into_response();
+    }
+    // Only a verified signature may spend the key's bucket
+    if let Err(retry_after) = state
+        .limiter
+        .check(RouteClass::Verify, &format!("pk:{}", mfr.pk_hex))
+    {
+        return RateLimited(retry_after).into_response();
+    }
+
     let db = state.db.lock().unwrap();
-    let result = db.execute(
-        "INSERT OR REPLACE INTO manufacturers (pk_hex, name, description, website, registered_at)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        rusqlite::params![mfr.pk_hex, mfr.name, mfr.description, mfr.website, mfr.registered_at],
-    );
+    // Re-registering would silently lift the revocation
+    if db::get_manufacturer(&db, &mfr.pk_hex).is_ok_and(|m| m.revocation.is_some()) {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "manufacturer_revoked",
+            "This key has been revoked",
+        )
+        .into_response();
+    }
+    if db::get_key(&db, &mfr.pk_hex).is_ok_and(|k| k.manufacturer_id != mfr.pk_hex) {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "key_exists",
+            "This key belongs to another manufacturer",
+        )
+        .into_response();
+    }
+    let result = db::insert_manufacturer(&db, &mfr);
     match result {
         Ok(_) => {
-            println!("Manufacturer registered: {} ({})", mfr.name, &mfr.pk_hex[..16]);
-            (StatusCode::OK, Json(serde_json::json!({"ok": true})))
+            println!(
+                "Manufacturer registered (sig verified): {} ({})",
+                mfr.name,
+                abbrev(&mfr.pk_hex)
+            );
+            (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
         }
         Err(e) => {
             eprintln!("Failed to register manufacturer: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()})))
+            ApiError::internal(e).into_response()
         }
     }
 }
 
 /// GET /api/manufacturers -- list all registered manufacturers
+#[utoipa::path(
+    get,
+    path = "/manufacturers",
+    tag = "manufacturers",
+    responses((status = 200, description = "All manufacturers", body = Items<Manufacturer>))
+)]
 pub async fn list_manufacturers(State(state): State<AppState>) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
-    let mut stmt = db
-        .prepare("SELECT pk_hex, name, description, website, registered_at FROM manufacturers ORDER BY registered_at DESC")
-        .unwrap();
-    let items: Vec<Manufacturer> = stmt
-        .query_map([], |row| {
-            Ok(Manufacturer {
-                pk_hex: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                website: row.get(3)?,
-                registered_at: row.get(4)?,
-            })
-        })
-        .unwrap()
-        .filter_map(|r| r.ok())
-        .collect();
-    Json(serde_json::json!({ "items": items }))
+    match db::list_manufacturers(&db, None) {
+        Ok(items) => Json(serde_json::json!({ "items": items })).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
 }
 
 /// GET /api/manufacturers/{pk_hex} -- get a specific manufacturer
+#[utoipa::path(
+    get,
+    path = "/manufacturers/{pk_hex}",
+    tag = "manufacturers",
+    params(("pk_hex" = String, Path, description = "Manufacturer public key (hex)")),
+    responses(
+        (status = 200, description = "The manufacturer", body = Manufacturer),
+        (status = 404, description = "Manufacturer not found", body = ErrorResponse),
+    )
+)]
 pub async fn get_manufacturer(
     State(state): State<AppState>,
     Path(pk_hex): Path<String>,
 ) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
-    let result = db.query_row(
-        "SELECT pk_hex, name, description, website, registered_at FROM manufacturers WHERE pk_hex = ?1",
-        rusqlite::params![pk_hex],
-        |row| {
-            Ok(Manufacturer {
-                pk_hex: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                website: row.get(3)?,
-                registered_at: row.get(4)?,
-            })
-        },
-    );
-    match result {
+    match db::get_manufacturer(&db, &pk_hex) {
         Ok(mfr) => (StatusCode::OK, Json(serde_json::json!(mfr))).into_response(),
-        Err(_) => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Manufacturer not found"}))).into_response(),
+        Err(_) => ApiError::not_found("Manufacturer not found").into_response(),
     }
 }
 
-/// DELETE /api/manufacturers/{pk_hex} -- deregister a manufacturer
+/// DELETE /api/manufacturers/{pk_hex} -- revoke a manufacturer key
+///
+/// The manufacturer is kept, with a revocation statement signed by the
+/// registry signing key; see `revocation.rs`.
+#[utoipa::path(
+    delete,
+    path = "/manufacturers/{pk_hex}",
+    tag = "manufacturers",
+    params(("pk_hex" = String, Path, description = "Manufacturer public key (hex)"), RevokeParams),
+    responses(
+        (status = 200, description = "Manufacturer revoked", body = Revocation),
+        (status = 401, description = "Admin token required", body = ErrorResponse),
+        (status = 404, description = "Manufacturer not found", body = ErrorResponse),
+        (status = 409, description = "Already revoked, or no registry signing key configured", body = ErrorResponse),
+    )
+)]
 pub async fn delete_manufacturer(
     State(state): State<AppState>,
     Path(pk_hex): Path<String>,
+    Query(params): Query<RevokeParams>,
 ) -> impl IntoResponse {
+    let Some(signing_key) = state.config.read().unwrap().registry.signing_key() else {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "signing_disabled",
+            "Revocation needs a registry signing key; set registry.signing_key or --signing-key",
+        )
+        .into_response();
+    };
+    let reason = params.reason.unwrap_or_default();
+    if reason.len() > DESCRIPTION_MAX {
+        return ApiError::bad_request(
+            "invalid_body",
+            format!("reason exceeds {} bytes", DESCRIPTION_MAX),
+        )
+        .into_response();
+    }
+
+    let db = state.db.lock().unwrap();
+    match revocation::revoke(&db, &signing_key, &pk_hex, &reason) {
+        Ok(Some(rev)) => {
+            println!("Manufacturer revoked: {} ({})", abbrev(&pk_hex), rev.reason);
+            Json(rev).into_response()
+        }
+        Ok(None) => match db::get_manufacturer(&db, &pk_hex) {
+            Ok(_) => ApiError::new(
+                StatusCode::CONFLICT,
+                "manufacturer_revoked",
+                "Manufacturer is already revoked",
+            )
+            .into_response(),
+            Err(_) => ApiError::not_found("Manufacturer not found").into_response(),
+        },
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/manufacturers/revocations -- the signed revocation list
+#[utoipa::path(
+    get,
+    path = "/manufacturers/revocations",
+    tag = "manufacturers",
+    responses(
+        (status = 200, description = "Every revoked manufacturer key, signed by the registry", body = RevocationList),
+        (status = 409, description = "No registry signing key configured", body = ErrorResponse),
+    )
+)]
+pub async fn list_revocations(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(signing_key) = state.config.read().unwrap().registry.signing_key() else {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "signing_disabled",
+            "No registry signing key configured; set registry.signing_key or --signing-key",
+        )
+        .into_response();
+    };
+    let db = state.db.lock().unwrap();
+    match revocation::list(&db, &signing_key) {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// POST /api/v2/manufacturers/{id}/keys -- add a key by rotation
+///
+/// The statement must be signed by a key of the manufacturer that is valid
+/// now; that key is retired when the new one takes over.  See `keys.rs`.
+#[utoipa::path(
+    post,
+    path = "/manufacturers/{id}/keys",
+    tag = "manufacturers",
+    params(("id" = String, Path, description = "Manufacturer id (its registration key)")),
+    request_body = ManufacturerKey,
+    responses(
+        (status = 200, description = "Key added", body = ManufacturerKey),
+        (status = 400, description = "Invalid key, window or signature, or signer not a current key", body = ErrorResponse),
+        (status = 404, description = "Manufacturer not found", body = ErrorResponse),
+        (status = 409, description = "Manufacturer revoked, or key already registered", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
+pub async fn rotate_manufacturer_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(key): Json<ManufacturerKey>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    match keys::check_rotation(&db, &id, &key) {
+        Ok(()) => {}
+        Err(RotationError::Db(e)) => return ApiError::internal(e).into_response(),
+        Err(e) => {
+            let status = match e {
+                RotationError::UnknownManufacturer => StatusCode::NOT_FOUND,
+                RotationError::RevokedManufacturer | RotationError::KeyExists => {
+                    StatusCode::CONFLICT
+                }
+                _ => StatusCode::BAD_REQUEST,
+            };
+            return ApiError::new(status, e.code(), e.to_string()).into_response();
+        }
+    }
+    // Only a verified signature may spend the signer's bucket
+    if let Err(retry_after) = state
+        .limiter
+        .check(RouteClass::Verify, &format!("pk:{}", key.signed_by))
+    {
+        return RateLimited(retry_after).into_response();
+    }
+
+    match keys::store_rotation(&db, &key) {
+        Ok(stored) => {
+            println!(
+                "Manufacturer key rotated: {} -> {} ({})",
+                abbrev(&key.signed_by),
+                abbrev(&key.pk_hex),
+                abbrev(&id)
+            );
+            Json(stored).into_response()
+        }
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/manufacturers/{id}/keys -- the keys valid at a timestamp
+#[utoipa::path(
+    get,
+    path = "/manufacturers/{id}/keys",
+    tag = "manufacturers",
+    params(("id" = String, Path, description = "Manufacturer id (its registration key)"), KeysAtParams),
+    responses(
+        (status = 200, description = "Keys valid at `at`", body = Items<ManufacturerKey>),
+        (status = 400, description = "Invalid `at`", body = ErrorResponse),
+        (status = 404, description = "Manufacturer not found", body = ErrorResponse),
+    )
+)]
+pub async fn list_manufacturer_keys(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<KeysAtParams>,
+) -> impl IntoResponse {
+    let at = match params.at.as_deref().map(crate::versioning::parse_cutoff) {
+        None => chrono::Utc::now(),
+        Some(Ok(at)) => at,
+        Some(Err(e)) => return ApiError::bad_request("invalid_timestamp", e).into_response(),
+    };
+    let db = state.db.lock().unwrap();
+    if db::get_manufacturer(&db, &id).is_err() {
+        return ApiError::not_found("Manufacturer not found").into_response();
+    }
+    match keys::keys_at(&db, &id, at) {
+        Ok(items) => Json(Items { items }).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// TEE device certificates (see devices.rs)
+// ---------------------------------------------------------------------------
+
+/// POST /api/devices -- a manufacturer registers a device key it certified
+#[utoipa::path(
+    post,
+    path = "/devices",
+    tag = "devices",
+    request_body = Device,
+    responses(
+        (status = 200, description = "Device registered", body = OkResponse),
+        (status = 400, description = "Missing or invalid manufacturer_signature", body = ErrorResponse),
+        (status = 403, description = "manufacturer_pk has been revoked", body = ErrorResponse),
+        (status = 404, description = "manufacturer_pk is not a registered manufacturer", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
+pub async fn create_device(
+    State(state): State<AppState>,
+    Json(device): Json<Device>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    if let Err(e) = devices::verify_chain(&db, &device) {
+        eprintln!(
+            "Rejected device {} ({}): {}",
+            abbrev(&device.device_pk),
+            e.code(),
+            e
+        );
+        return match e {
+            ChainError::UnknownManufacturer => {
+                ApiError::new(StatusCode::NOT_FOUND, e.code(), e.to_string()).into_response()
+            }
+            ChainError::RevokedManufacturer => {
+                ApiError::new(StatusCode::FORBIDDEN, e.code(), e.to_string()).into_response()
+            }
+            ChainError::Db(e) => ApiError::internal(e).into_response(),
+            e => ApiError::bad_request(e.code(), e.to_string()).into_response(),
+        };
+    }
+    // Only a verified certificate may spend the manufacturer's bucket
+    if let Err(retry_after) = state.limiter.check(
+        RouteClass::Verify,
+        &format!("pk:{}", device.manufacturer_pk),
+    ) {
+        return RateLimited(retry_after).into_response();
+    }
+
+    match db::insert_device(&db, &device) {
+        Ok(_) => {
+            println!(
+                "Device registered (sig verified): {} by {}",
+                abbrev(&device.device_pk),
+                abbrev(&device.manufacturer_pk)
+            );
+            (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
+        }
+        Err(e) => {
+            eprintln!("Failed to register device: {}", e);
+            ApiError::internal(e).into_response()
+        }
+    }
+}
+
+/// GET /api/devices/{device_pk} -- a device and its issuing manufacturer
+#[utoipa::path(
+    get,
+    path = "/devices/{device_pk}",
+    tag = "devices",
+    params(("device_pk" = String, Path, description = "Device public key (hex)")),
+    responses(
+        (status = 200, description = "The device and its manufacturer", body = DeviceResponse),
+        (status = 404, description = "Device not found", body = ErrorResponse),
+    )
+)]
+pub async fn get_device(
+    State(state): State<AppState>,
+    Path(device_pk): Path<String>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    let Ok(device) = db::get_device(&db, &device_pk) else {
+        return ApiError::not_found("Device not found").into_response();
+    };
+    match db::manufacturer_for_key(&db, &device.manufacturer_pk) {
+        Ok(manufacturer) => Json(DeviceResponse {
+            device,
+            manufacturer,
+        })
+        .into_response(),
+        Err(_) => ApiError::not_found("Issuing manufacturer not found").into_response(),
+    }
+}
+
+/// POST /api/devices/verify -- check a device certificate chain
+///
+/// The certificate need not be registered; `registered` says whether it is.
+#[utoipa::path(
+    post,
+    path = "/devices/verify",
+    tag = "devices",
+    request_body = Device,
+    responses(
+        (status = 200, description = "Verification result; `valid` is false with a `reason` if the chain is broken", body = DeviceVerification),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
+pub async fn verify_device(
+    State(state): State<AppState>,
+    Json(device): Json<Device>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    let registered = db::get_device(&db, &device.device_pk).is_ok_and(|d| d == device);
+    let verification = match devices::verify_chain(&db, &device) {
+        Ok(manufacturer) => DeviceVerification {
+            valid: true,
+            reason: None,
+            manufacturer: Some(manufacturer),
+            registered,
+        },
+        Err(ChainError::Db(e)) => return ApiError::internal(e).into_response(),
+        Err(e) => DeviceVerification {
+            valid: false,
+            reason: Some(e.code().to_string()),
+            manufacturer: db::manufacturer_for_key(&db, &device.manufacturer_pk).ok(),
+            registered,
+        },
+    };
+    Json(verification).into_response()
+}
+
+// ---------------------------------------------------------------------------
+// TEE attestation (see attestation.rs)
+// ---------------------------------------------------------------------------
+
+/// POST /api/v2/attestations/verify -- check a device attestation
+///
+/// On success the verdict carries a short-lived token signed by the
+/// registry signing key.
+#[utoipa::path(
+    post,
+    path = "/attestations/verify",
+    tag = "devices",
+    request_body = Attestation,
+    responses(
+        (status = 200, description = "Verdict; `valid` is false with a `reason` if the attestation fails", body = AttestationVerdict),
+        (status = 409, description = "No registry signing key configured", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
+pub async fn verify_attestation(
+    State(state): State<AppState>,
+    Json(att): Json<Attestation>,
+) -> impl IntoResponse {
+    let (signing_key, ttl) = {
+        let config = state.config.read().unwrap();
+        (
+            config.registry.signing_key(),
+            config.registry.attestation_ttl,
+        )
+    };
+    let Some(signing_key) = signing_key else {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "signing_disabled",
+            "Attestation needs a registry signing key; set registry.signing_key or --signing-key",
+        )
+        .into_response();
+    };
+    let db = state.db.lock().unwrap();
+    let verdict = match attestation::verify(&db, &att) {
+        Ok(manufacturer) => {
+            // Only a verified attestation may spend the device's bucket
+            let pk_key = format!("pk:{}", att.device.device_pk);
+            if let Err(retry_after) = state.limiter.check(RouteClass::Verify, &pk_key) {
+                return RateLimited(retry_after).into_response();
+            }
+            let token = match attestation::issue(&signing_key, &att, &manufacturer, ttl) {
+                Ok(token) => token,
+                Err(e) => return ApiError::internal(e).into_response(),
+            };
+            AttestationVerdict {
+                valid: true,
+                reason: None,
+                token: Some(token),
+                manufacturer: Some(manufacturer),
+            }
+        }
+        Err(AttestationError::Chain(ChainError::Db(e))) => {
+            return ApiError::internal(e).into_response()
+        }
+        Err(e) => AttestationVerdict {
+            valid: false,
+            reason: Some(e.code().to_string()),
+            manufacturer: db::manufacturer_for_key(&db, &att.device.manufacturer_pk).ok(),
+            token: None,
+        },
+    };
+    Json(verdict).into_response()
+}
+
+// ---------------------------------------------------------------------------
+// Buyer PRE key directory (see buyer_keys.rs)
+// ---------------------------------------------------------------------------
+
+/// POST /api/v2/buyer-keys -- publish a buyer's PRE public key
+///
+/// Signed by the buyer's node key; supersedes the node's current key.
+#[utoipa::path(
+    post,
+    path = "/buyer-keys",
+    tag = "buyers",
+    request_body = BuyerKey,
+    responses(
+        (status = 200, description = "Key published", body = BuyerKey),
+        (status = 400, description = "Invalid node pubkey, scheme, key, timestamp or signature", body = ErrorResponse),
+        (status = 409, description = "Not newer than the current key", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
+pub async fn publish_buyer_key(
+    State(state): State<AppState>,
+    Json(key): Json<BuyerKey>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    match buyer_keys::check(&db, &key, chrono::Utc::now()) {
+        Ok(()) => {}
+        Err(BuyerKeyError::Db(e)) => return ApiError::internal(e).into_response(),
+        Err(e) => {
+            let status = match e {
+                BuyerKeyError::Stale => StatusCode::CONFLICT,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            return ApiError::new(status, e.code(), e.to_string()).into_response();
+        }
+    }
+    // Only a verified signature may spend the node's bucket
+    if let Err(retry_after) = state
+        .limiter
+        .check(RouteClass::Verify, &format!("pk:{}", key.node_pubkey))
+    {
+        return RateLimited(retry_after).into_response();
+    }
+
+    match buyer_keys::store(&db, &key) {
+        Ok(stored) => {
+            println!(
+                "Buyer key published: {} ({})",
+                abbrev(&key.node_pubkey),
+                key.scheme
+            );
+            Json(stored).into_response()
+        }
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/buyer-keys/{node_pubkey} -- a buyer's current PRE key
+#[utoipa::path(
+    get,
+    path = "/buyer-keys/{node_pubkey}",
+    tag = "buyers",
+    params(("node_pubkey" = String, Path, description = "Buyer's Lightning node public key (hex)")),
+    responses(
+        (status = 200, description = "The current key", body = BuyerKey),
+        (status = 404, description = "No key published", body = ErrorResponse),
+    )
+)]
+pub async fn get_buyer_key(
+    State(state): State<AppState>,
+    Path(node_pubkey): Path<String>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    match db::current_buyer_key(&db, &node_pubkey) {
+        Ok(key) => Json(key).into_response(),
+        Err(_) => ApiError::not_found("No buyer key published for this node").into_response(),
+    }
+}
+
+/// GET /api/v2/buyer-keys/{node_pubkey}/history -- every key a buyer has
+/// published, newest first
+#[utoipa::path(
+    get,
+    path = "/buyer-keys/{node_pubkey}/history",
+    tag = "buyers",
+    params(("node_pubkey" = String, Path, description = "Buyer's Lightning node public key (hex)")),
+    responses(
+        (status = 200, description = "Keys, newest first; superseded ones carry `superseded_at`", body = Items<BuyerKey>),
+        (status = 404, description = "No key published", body = ErrorResponse),
+    )
+)]
+pub async fn buyer_key_history(
+    State(state): State<AppState>,
+    Path(node_pubkey): Path<String>,
+) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    match db::buyer_key_history(&db, &node_pubkey) {
+        Ok(items) if items.is_empty() => {
+            ApiError::not_found("No buyer key published for this node").into_response()
+        }
+        Ok(items) => Json(Items { items }).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Re-encryption proxies (see proxies.rs)
+// ---------------------------------------------------------------------------
+
+/// POST /api/v2/proxies -- a re-encryption proxy announces itself
+///
+/// Signed by the proxy's node key; replaces its previous announcement.
+#[utoipa::path(
+    post,
+    path = "/proxies",
+    tag = "proxies",
+    request_body = ProxyAnnouncement,
+    responses(
+        (status = 200, description = "Announcement stored", body = OkResponse),
+        (status = 400, description = "Invalid pubkey, address, schemes, TTL, timestamp or signature", body = ErrorResponse),
+        (status = 409, description = "Older than the proxy's current announcement", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    )
+)]
+pub async fn announce_proxy(
+    State(state): State<AppState>,
+    Json(ann): Json<ProxyAnnouncement>,
+) -> impl IntoResponse {
+    let now = chrono::Utc::now();
+    let db = state.db.lock().unwrap();
+    let expires_at = match proxies::check(&db, &ann, now) {
+        Ok(at) => at,
+        Err(ProxyError::Db(e)) => return ApiError::internal(e).into_response(),
+        Err(e) => {
+            let status = match e {
+                ProxyError::Stale => StatusCode::CONFLICT,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            return ApiError::new(status, e.code(), e.to_string()).into_response();
+        }
+    };
+    // Only a verified signature may spend the proxy's bucket
+    if let Err(retry_after) = state
+        .limiter
+        .check(RouteClass::Verify, &format!("pk:{}", ann.proxy_pubkey))
+    {
+        return RateLimited(retry_after).into_response();
+    }
+
+    match proxies::store(&db, &ann, expires_at, now) {
+        Ok(()) => {
+            println!(
+                "Proxy announced: {} ({})",
+                ann.proxy_address,
+                ann.schemes.join(",")
+            );
+            (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
+        }
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/proxies?scheme=&limit=&offset= -- one page of live proxies, cheapest first
+#[utoipa::path(
+    get,
+    path = "/proxies",
+    tag = "proxies",
+    params(ProxyParams, PageParams),
+    responses((status = 200, description = "A page of live announcements", body = Paged<ProxyAnnouncement>))
+)]
+pub async fn list_proxies(
+    State(state): State<AppState>,
+    Query(params): Query<ProxyParams>,
+    Query(page): Query<PageParams>,
+) -> impl IntoResponse {
+    let page = page.page();
+    let now = proxies::timestamp(chrono::Utc::now());
+    let db = state.db.lock().unwrap();
+    match db::live_proxies(&db, &now, params.scheme.as_deref(), Some(page)) {
+        Ok(items) => Json(Paged::new(items, page)).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Moderation blocklist (admin)
+// ---------------------------------------------------------------------------
+
+/// POST /api/admin/blocklist -- block a content hash or pubkey
+#[utoipa::path(
+    post,
+    path = "/admin/blocklist",
+    tag = "admin",
+    request_body = BlockEntry,
+    responses(
+        (status = 200, description = "Entry stored", body = OkResponse),
+        (status = 400, description = "value is not hex", body = ErrorResponse),
+    )
+)]
+pub async fn create_block(
+    State(state): State<AppState>,
+    Json(mut entry): Json<BlockEntry>,
+) -> impl IntoResponse {
+    // Hashes and pubkeys are hex; store them lowercase so case cannot dodge the block
+    if entry.value.is_empty() || !entry.value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return ApiError::bad_request("invalid_value", "value must be a hex hash or pubkey")
+            .into_response();
+    }
+    entry.value.make_ascii_lowercase();
+    if entry.blocked_at.is_empty() {
+        entry.blocked_at = chrono::Utc::now().to_rfc3339();
+    }
+    let db = state.db.lock().unwrap();
+    let result = db::insert_block(&db, &entry);
+    match result {
+        Ok(_) => {
+            println!(
+                "Blocked {} {}: {}",
+                entry.kind.as_str(),
+                entry.value,
+                entry.reason
+            );
+            (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
+        }
+        Err(e) => {
+            eprintln!("Failed to store blocklist entry: {}", e);
+            ApiError::internal(e).into_response()
+        }
+    }
+}
+
+/// GET /api/admin/blocklist -- list all blocklist entries
+#[utoipa::path(
+    get,
+    path = "/admin/blocklist",
+    tag = "admin",
+    responses((status = 200, description = "All blocklist entries", body = Items<BlockEntry>))
+)]
+pub async fn list_blocks(State(state): State<AppState>) -> impl IntoResponse {
+    let db = state.db.lock().unwrap();
+    match db::list_blocks(&db, None) {
+        Ok(items) => Json(serde_json::json!({ "items": items })).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// DELETE /api/admin/blocklist/{kind}/{value} -- lift a block
+#[utoipa::path(
+    delete,
+    path = "/admin/blocklist/{kind}/{value}",
+    tag = "admin",
+    params(
+        ("kind" = BlockKind, Path, description = "What the entry matches against"),
+        ("value" = String, Path, description = "Blocked hash or pubkey"),
+    ),
+    responses(
+        (status = 200, description = "Entry removed", body = OkResponse),
+        (status = 400, description = "Unknown kind", body = ErrorResponse),
+        (status = 404, description = "Entry not found", body = ErrorResponse),
+    )
+)]
+pub async fn delete_block(
+    State(state): State<AppState>,
+    Path((kind, value)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let Some(kind) = BlockKind::parse(&kind) else {
+        return ApiError::bad_request("invalid_kind", format!("Unknown blocklist kind: {}", kind))
+            .into_response();
+    };
     let db = state.db.lock().unwrap();
     let deleted = db
-        .execute("DELETE FROM manufacturers WHERE pk_hex = ?1", rusqlite::params![pk_hex])
+        .execute(
+            "DELETE FROM blocklist WHERE kind = ?1 AND lower(value) = lower(?2)",
+            rusqlite::params![kind.as_str(), value],
+        )
         .unwrap_or(0);
     if deleted > 0 {
-        println!("Manufacturer deregistered: {}", &pk_hex[..16.min(pk_hex.len())]);
-        (StatusCode::OK, Json(serde_json::json!({"ok": true, "deleted": deleted})))
+        println!("Unblocked {} {}", kind.as_str(), value);
+        (
+            StatusCode::OK,
+            Json(serde_json::json!({"ok": true, "deleted": deleted})),
+        )
+            .into_response()
     } else {
-        (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Manufacturer not found"})))
+        ApiError::not_found("Blocklist entry not found").into_response()
     }
 }
 
-/// DELETE /api/manufacturers -- clear all manufacturers (test re-provisioning)
-pub async fn delete_all_manufacturers(State(state): State<AppState>) -> impl IntoResponse {
+// ---------------------------------------------------------------------------
+// Seeders list (for dashboard)
+// ---------------------------------------------------------------------------
+
+/// GET /api/seeders -- list all seeder announcements
+#[utoipa::path(
+    get,
+    path = "/seeders",
+    tag = "seeders",
+    responses((status = 200, description = "All visible announcements", body = Items<SeederAnnouncement>))
+)]
+pub async fn list_seeders(State(state): State<AppState>) -> impl IntoResponse {
     let db = state.db.lock().unwrap();
-    let deleted = db.execute("DELETE FROM manufacturers", []).unwrap_or(0);
-    println!("Cleared {} manufacturers", deleted);
-    (StatusCode::OK, Json(serde_json::json!({ "deleted": deleted })))
+    match db::list_seeders(&db, None) {
+        Ok(items) => Json(serde_json::json!({ "items": items })).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
 }
 
 // ---------------------------------------------------------------------------
-// Seeders list (for dashboard)
+// v2: paginated list endpoints
 // ---------------------------------------------------------------------------
 
-pub async fn list_seeders(State(state): State<AppState>) -> impl IntoResponse {
+/// GET /api/v2/listings?limit=&offset= -- one page of content listings
+#[utoipa::path(
+    get,
+    path = "/listings",
+    tag = "listings",
+    operation_id = "list_listings",
+    params(PageParams),
+    responses((status = 200, description = "A page of visible listings", body = Paged<ContentListing>))
+)]
+pub async fn list_listings_v2(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+) -> impl IntoResponse {
+    let page = page.page();
     let db = state.db.lock().unwrap();
-    let mut stmt = db
-        .prepare(
-            "SELECT encrypted_hash, seeder_pubkey, seeder_address, seeder_ln_address, seeder_alias,
-                    transport_price, chunk_count, announced_at
-             FROM seeders ORDER BY announced_at DESC",
-        )
-        .unwrap();
-
-    let items: Vec<SeederAnnouncement> = stmt
-        .query_map([], |row| {
-            Ok(SeederAnnouncement {
-                encrypted_hash: row.get(0)?,
-                seeder_pubkey: row.get(1)?,
-                seeder_address: row.get(2)?,
-                seeder_ln_address: row.get(3)?,
-                seeder_alias: row.get(4)?,
-                transport_price: row.get(5)?,
-                chunk_count: row.get(6)?,
-                announced_at: row.get(7)?,
-            })
-        })
+    match db::search_listings(&db, &SearchParams::default(), Some(page)) {
+        Ok(items) => Json(Paged::new(items, page)).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/search?q=&type=&max_price=&policy=&limit=&offset= -- one page of search results
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "listings",
+    operation_id = "search_listings",
+    params(SearchParams, PageParams),
+    responses((status = 200, description = "A page of matching listings", body = Paged<ContentListing>))
+)]
+pub async fn search_listings_v2(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+    Query(page): Query<PageParams>,
+) -> impl IntoResponse {
+    let page = page.page();
+    let db = state.db.lock().unwrap();
+    match db::search_listings(&db, &params, Some(page)) {
+        Ok(items) => Json(Paged::new(items, page)).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/seeders?limit=&offset= -- one page of seeder announcements
+#[utoipa::path(
+    get,
+    path = "/seeders",
+    tag = "seeders",
+    operation_id = "list_seeders",
+    params(PageParams),
+    responses((status = 200, description = "A page of visible announcements", body = Paged<SeederAnnouncement>))
+)]
+pub async fn list_seeders_v2(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+) -> impl IntoResponse {
+    let page = page.page();
+    let db = state.db.lock().unwrap();
+    match db::list_seeders(&db, Some(page)) {
+        Ok(items) => Json(Paged::new(items, page)).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/manufacturers?limit=&offset= -- one page of manufacturers
+#[utoipa::path(
+    get,
+    path = "/manufacturers",
+    tag = "manufacturers",
+    operation_id = "list_manufacturers",
+    params(PageParams),
+    responses((status = 200, description = "A page of manufacturers", body = Paged<Manufacturer>))
+)]
+pub async fn list_manufacturers_v2(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+) -> impl IntoResponse {
+    let page = page.page();
+    let db = state.db.lock().unwrap();
+    match db::list_manufacturers(&db, Some(page)) {
+        Ok(items) => Json(Paged::new(items, page)).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// GET /api/v2/admin/blocklist?limit=&offset= -- one page of blocklist entries
+#[utoipa::path(
+    get,
+    path = "/admin/blocklist",
+    tag = "admin",
+    operation_id = "list_blocks",
+    params(PageParams),
+    responses((status = 200, description = "A page of blocklist entries", body = Paged<BlockEntry>))
+)]
+pub async fn list_blocks_v2(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+) -> impl IntoResponse {
+    let page = page.page();
+    let db = state.db.lock().unwrap();
+    match db::list_blocks(&db, Some(page)) {
+        Ok(items) => Json(Paged::new(items, page)).into_response(),
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// v2: NDJSON export / import (see export.rs)
+// ---------------------------------------------------------------------------
+
+/// `io::Write` that forwards output to a response body in chunks.
+struct ChunkWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl ChunkWriter {
+    const CHUNK: usize = 16 * 1024;
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buf));
+        // Fails only if the client has gone away
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl io::Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= Self::CHUNK {
+            self.send()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+/// GET /api/v2/admin/export -- stream every record as NDJSON
+#[utoipa::path(
+    get,
+    path = "/admin/export",
+    tag = "admin",
+    responses((
+        status = 200,
+        description = "One JSON record per line, tagged by `type`: manufacturer, listing, seeder, block",
+        body = String,
+        content_type = "application/x-ndjson"
+    ))
+)]
+pub async fn export_records(State(state): State<AppState>) -> impl IntoResponse {
+    let (tx, rx) = mpsc::channel(8);
+    let db = state.db.clone();
+    tokio::task::spawn_blocking(move || {
+        // Read under the lock and release it before streaming, so a slow
+        // client cannot hold up every other request
+        let records = export::records(&db.lock().unwrap()).map_err(io::Error::other);
+        let mut out = ChunkWriter {
+            tx: tx.clone(),
+            buf: Vec::new(),
+        };
+        match records.and_then(|records| export::write(records, &mut out)) {
+            Ok(counts) => println!("Exported {}", counts),
+            Err(e) => {
+                eprintln!("Export failed: {}", e);
+                let _ = tx.blocking_send(Err(e));
+            }
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    (
+        [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(stream),
+    )
+}
+
+/// POST /api/v2/admin/import -- load an NDJSON export
+#[utoipa::path(
+    post,
+    path = "/admin/import",
+    tag = "admin",
+    params(ImportParams),
+    request_body(content = String, content_type = "application/x-ndjson", description = "Output of `GET /admin/export`"),
+    responses(
+        (status = 200, description = "Records imported; invalid ones skipped under `on_invalid=skip`", body = ImportSummary),
+        (status = 413, description = "Body exceeds --max-import-bytes", body = ErrorResponse),
+        (status = 422, description = "Invalid record under `on_invalid=fail`; nothing imported", body = ErrorResponse),
+    )
+)]
+pub async fn import_records(
+    State(state): State<AppState>,
+    Query(params): Query<ImportParams>,
+    body: Body,
+) -> impl IntoResponse {
+    // The size cap is enforced by `limit_body`
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(b) => b,
+        Err(_) => {
+            return ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "Import too large",
+            )
+            .into_response()
+        }
+    };
+
+    let on_invalid = params.on_invalid.unwrap_or_default();
+    // Revocations are trusted only if this registry signed them
+    let issuer = state
+        .config
+        .read()
         .unwrap()
-        .filter_map(|r| r.ok())
-        .collect();
+        .registry
+        .signing_key()
+        .map(|sk| pubkey_hex(&sk));
+    let mut db = state.db.lock().unwrap();
+    match export::import(&mut db, bytes.as_ref(), on_invalid, issuer.as_deref()) {
+        Ok(summary) => {
+            println!(
+                "Imported {} ({} skipped)",
+                summary.imported,
+                summary.skipped.len()
+            );
+            Json(summary).into_response()
+        }
+        Err(ImportError::Invalid(rejected)) => ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_record",
+            format!("line {}: {}", rejected.line, rejected.reason),
+        )
+        .into_response(),
+        Err(ImportError::Io(e)) => {
+            ApiError::bad_request("invalid_body", e.to_string()).into_response()
+        }
+        Err(ImportError::Db(e)) => ApiError::internal(e).into_response(),
+    }
+}
+
+/// POST /api/v2/admin/snapshot -- take an online backup now
+#[utoipa::path(
+    post,
+    path = "/admin/snapshot",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Snapshot written", body = SnapshotInfo),
+        (status = 409, description = "No --snapshot-dir configured", body = ErrorResponse),
+        (status = 500, description = "Snapshot failed", body = ErrorResponse),
+    )
+)]
+pub async fn create_snapshot(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(cfg) = state.config.read().unwrap().snapshots.enabled() else {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "snapshots_disabled",
+            "Snapshots are disabled; set snapshots.dir or --snapshot-dir",
+        )
+        .into_response();
+    };
+    // Reads through its own connection, so requests are not held up
+    let db_path = state.config.read().unwrap().server.db_path.clone();
+    match tokio::task::spawn_blocking(move || snapshot::take(&db_path, &cfg)).await {
+        Ok(Ok(info)) => {
+            println!("Snapshot written: {} ({} bytes)", info.path, info.bytes);
+            Json(info).into_response()
+        }
+        Ok(Err(e)) => {
+            eprintln!("Snapshot failed: {}", e);
+            ApiError::internal(e).into_response()
+        }
+        Err(e) => ApiError::internal(e).into_response(),
+    }
+}
 
-    Json(serde_json::json!({ "items": items }))
+/// GET /api/info -- who runs this registry and what it supports
+#[utoipa::path(
+    get,
+    path = "/info",
+    tag = "info",
+    responses((status = 200, description = "Registry information document", body = RegistryInfo))
+)]
+pub async fn get_info(State(state): State<AppState>) -> impl IntoResponse {
+    Json(RegistryInfo::new(&state.config()))
 }
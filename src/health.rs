@@ -0,0 +1,140 @@
+//! Liveness and readiness probes for load balancers and process supervisors.
+//!
+//! `GET /healthz` answers as long as the process serves requests.
+//! `GET /readyz` additionally checks that the database answers, that its
+//! schema is current, and that `server.maintenance` is off; it returns
+//! `503` otherwise.  Neither is rate limited or versioned.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::db;
+use crate::types::AppState;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const OK: &str = "ok";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    /// `ok`, `ready` or `not_ready`
+    pub status: String,
+    pub version: String,
+    pub uptime_secs: u64,
+    /// Readiness checks: `ok` or the reason for failing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checks: Option<ReadyChecks>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadyChecks {
+    pub database: String,
+    pub migrations: String,
+    pub maintenance: String,
+}
+
+impl ReadyChecks {
+    fn ready(&self) -> bool {
+        [&self.database, &self.migrations, &self.maintenance]
+            .iter()
+            .all(|c| *c == OK)
+    }
+}
+
+fn health(state: &AppState, status: &str, checks: Option<ReadyChecks>) -> Health {
+    Health {
+        status: status.into(),
+        version: VERSION.into(),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        checks,
+    }
+}
+
+/// GET /healthz
+pub async fn healthz(State(state): State<AppState>) -> Json<Health> {
+    Json(health(&state, OK, None))
+}
+
+/// GET /readyz
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let (database, migrations) = {
+        let conn = state.db.lock().unwrap();
+        match conn.query_row("SELECT 1", [], |r| r.get::<_, i64>(0)) {
+            Err(e) => (e.to_string(), "unknown".to_string()),
+            Ok(_) => match db::schema_gap(&conn) {
+                Ok(None) => (OK.to_string(), OK.to_string()),
+                Ok(Some(gap)) => (OK.to_string(), gap),
+                Err(e) => (OK.to_string(), e.to_string()),
+            },
+        }
+    };
+    let maintenance = if state.config.read().unwrap().server.maintenance {
+        "maintenance mode".to_string()
+    } else {
+        OK.to_string()
+    };
+
+    let checks = ReadyChecks {
+        database,
+        migrations,
+        maintenance,
+    };
+    let (code, status) = if checks.ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    };
+    (code, Json(health(&state, status, Some(checks))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use rusqlite::Connection;
+
+    fn state() -> AppState {
+        let conn = Connection::open_in_memory().unwrap();
+        db::init_db(&conn);
+        AppState::new(conn, Config::default())
+    }
+
+    async fn ready(state: &AppState) -> (StatusCode, Health) {
+        let res = readyz(State(state.clone())).await.into_response();
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn ready_until_maintenance_or_schema_drift() {
+        let state = state();
+        let (code, body) = ready(&state).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body.status, "ready");
+        assert_eq!(body.version, VERSION);
+
+        state.config.write().unwrap().server.maintenance = true;
+        let (code, body) = ready(&state).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.checks.unwrap().maintenance, "maintenance mode");
+
+        state.config.write().unwrap().server.maintenance = false;
+        state
+            .db
+            .lock()
+            .unwrap()
+            .execute_batch("DROP TABLE blocklist")
+            .unwrap();
+        let (code, body) = ready(&state).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        let checks = body.checks.unwrap();
+        assert_eq!(checks.database, "ok");
+        assert_eq!(checks.migrations, "missing table blocklist");
+    }
+}
@@ -0,0 +1,213 @@
+//! The registry information document, after Nostr's NIP-11.
+//!
+//! `GET /api/info` (also `/api/v2/info`) tells clients who runs the registry
+//! and what it supports before they call anything else: API versions, the
+//! canonical messages it verifies signatures over, paging and body limits,
+//! rate limits, record lifetimes and which optional modules are enabled.
+//! Clients should check `modules` and `signed_messages` rather than infer
+//! capabilities from `version`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::health::VERSION;
+use crate::limits::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::policy::PLAYBACK_POLICIES;
+use crate::proxies;
+use crate::ratelimit::Limit;
+use crate::signature::{
+    ATTESTATION_MESSAGE_V1, BUYER_KEY_MESSAGE_V1, DEVICE_MESSAGE_V1, LIGHTNING_SIGNATURE_SCHEME,
+    LISTING_MESSAGE_V1, LISTING_MESSAGE_V2, MANUFACTURER_MESSAGE_V1, PROXY_MESSAGE_V1,
+    ROTATION_MESSAGE_V1,
+};
+
+pub const SOFTWARE: &str = "conduit-registry";
+
+/// API versions served, oldest first.
+pub const API_VERSIONS: &[&str] = &["v1", "v2"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RegistryInfo {
+    pub name: String,
+    pub description: String,
+    /// Operator's node public key (hex), if published; signs the
+    /// revocation list and attestation tokens when the `revocations` and
+    /// `attestations` modules are enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_pubkey: Option<String>,
+    /// Operator contact, e.g. an email address or URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    pub software: String,
+    pub version: String,
+    pub api_versions: Vec<String>,
+    /// Canonical messages whose signatures this registry verifies
+    pub signed_messages: Vec<SignedMessage>,
+    pub limits: InfoLimits,
+    /// Lifetime in seconds of records that expire, by record type; records
+    /// not listed are kept until deleted
+    pub ttls: BTreeMap<String, u64>,
+    /// Optional features enabled on this instance
+    pub modules: Vec<String>,
+    /// Accepted `playback_policy` values and the parameters each takes
+    #[serde(default)]
+    pub playback_policies: Vec<PlaybackPolicyInfo>,
+    /// Proxy re-encryption scheme the `pre_*` listing fields must follow
+    #[serde(default)]
+    pub pre_scheme: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlaybackPolicyInfo {
+    pub name: String,
+    /// Fields of `playback_params` this policy takes
+    pub params: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SignedMessage {
+    /// Record type, e.g. `listing`
+    pub record: String,
+    /// Message prefix up to and including the version, e.g. `conduit:listing:v1`
+    pub message: String,
+    /// Signature scheme, e.g. `lightning` (LN `signmessage`, zbase32)
+    pub scheme: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct InfoLimits {
+    pub default_page_size: u32,
+    pub max_page_size: u32,
+    pub max_body_bytes: usize,
+    pub max_import_bytes: usize,
+    pub rate_limits: InfoRateLimits,
+}
+
+/// Per-minute rates and bursts; a `per_minute` of 0 means unlimited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct InfoRateLimits {
+    pub read: RateLimitInfo,
+    pub write: RateLimitInfo,
+    pub verify: RateLimitInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RateLimitInfo {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl From<Limit> for RateLimitInfo {
+    fn from(l: Limit) -> Self {
+        RateLimitInfo {
+            per_minute: l.per_minute,
+            burst: l.burst,
+        }
+    }
+}
+
+impl RegistryInfo {
+    pub fn new(config: &Config) -> Self {
+        let registry = &config.registry;
+        let rate = &config.rate_limits;
+
+        let mut modules = vec![
+            "blocklist",
+            "buyer_keys",
+            "devices",
+            "export",
+            "manufacturers",
+            "proxies",
+        ];
+        let mut ttls = BTreeMap::new();
+        // Proxies choose their own TTL up to this
+        ttls.insert("proxy_announcement".to_string(), proxies::MAX_TTL.as_secs());
+        if config.snapshots.dir.is_some() {
+            modules.push("snapshots");
+        }
+        if registry.signing_key.is_some() {
+            modules.push("revocations");
+            modules.push("attestations");
+            ttls.insert(
+                "attestation_token".to_string(),
+                registry.attestation_ttl.as_secs(),
+            );
+        }
+
+        RegistryInfo {
+            name: registry.name.clone(),
+            description: registry.description.clone(),
+            operator_pubkey: registry.operator_pubkey(),
+            contact: registry.contact.clone(),
+            software: SOFTWARE.into(),
+            version: VERSION.into(),
+            api_versions: API_VERSIONS.iter().map(|v| v.to_string()).collect(),
+            signed_messages: [
+                ("listing", LISTING_MESSAGE_V1),
+                ("listing", LISTING_MESSAGE_V2),
+                ("manufacturer", MANUFACTURER_MESSAGE_V1),
+                ("manufacturer_key", ROTATION_MESSAGE_V1),
+                ("device", DEVICE_MESSAGE_V1),
+                ("attestation", ATTESTATION_MESSAGE_V1),
+                ("buyer_key", BUYER_KEY_MESSAGE_V1),
+                ("proxy", PROXY_MESSAGE_V1),
+            ]
+            .into_iter()
+            .map(|(record, message)| SignedMessage {
+                record: record.into(),
+                message: message.into(),
+                scheme: LIGHTNING_SIGNATURE_SCHEME.into(),
+            })
+            .collect(),
+            limits: InfoLimits {
+                default_page_size: DEFAULT_PAGE_SIZE,
+                max_page_size: MAX_PAGE_SIZE,
+                max_body_bytes: config.server.max_body_bytes,
+                max_import_bytes: config.server.max_import_bytes,
+                rate_limits: InfoRateLimits {
+                    read: rate.read.into(),
+                    write: rate.write.into(),
+                    verify: rate.verify.into(),
+                },
+            },
+            ttls,
+            modules: modules.into_iter().map(String::from).collect(),
+            playback_policies: PLAYBACK_POLICIES
+                .iter()
+                .map(|(name, params)| PlaybackPolicyInfo {
+                    name: name.to_string(),
+                    params: params.iter().map(|p| p.to_string()).collect(),
+                })
+                .collect(),
+            pre_scheme: registry.pre_scheme.clone(),
+        }
+    }
+
+    pub fn has_module(&self, module: &str) -> bool {
+        self.modules.iter().any(|m| m == module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn reflects_configuration() {
+        let mut config = Config::default();
+        config.registry.name = "Test Registry".into();
+        config.rate_limits.read = "0".parse().unwrap();
+        let info = RegistryInfo::new(&config);
+        assert_eq!(info.name, "Test Registry");
+        assert_eq!(info.limits.rate_limits.read.per_minute, 0);
+        assert_eq!(info.signed_messages[0].message, "conduit:listing:v1");
+        assert!(!info.has_module("snapshots"));
+
+        config.snapshots.dir = Some(PathBuf::from("/tmp/snaps"));
+        assert!(RegistryInfo::new(&config).has_module("snapshots"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Manufacturer key rotation.
+//!
+//! A manufacturer's `id` is the key it registered with and never changes;
+//! the keys it signs with are rows in `manufacturer_keys`, each valid over
+//! `[not_before, not_after)`.  A new key is added by
+//! `POST /manufacturers/{id}/keys` with a rotation statement signed by a
+//! key that is valid now, and that signer is retired when the new key
+//! takes over (`retired_at`), so each handover leaves exactly one key in
+//! use.  A new key's window cannot open in the past, so it cannot vouch for
+//! certificates backdated to before it existed.  Certificates keep verifying
+//! after a rotation as long as they were issued inside their key's window.
+//!
+//! Times are RFC 3339 (or `YYYY-MM-DD`).  An empty bound is open; a bound
+//! that does not parse makes the key invalid rather than unbounded.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db;
+use crate::proxies::MAX_CLOCK_SKEW;
+use crate::signature::{is_compressed_pubkey, verify_rotation};
+use crate::types::ManufacturerKey;
+use crate::versioning::parse_cutoff;
+
+/// Why a rotation was refused.
+#[derive(Debug)]
+pub enum RotationError {
+    UnknownManufacturer,
+    RevokedManufacturer,
+    ManufacturerMismatch,
+    InvalidKey,
+    InvalidWindow,
+    KeyExists,
+    SignerNotCurrent,
+    InvalidSignature,
+    Db(rusqlite::Error),
+}
+
+impl RotationError {
+    /// Machine-readable API error code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RotationError::UnknownManufacturer => "not_found",
+            RotationError::RevokedManufacturer => "manufacturer_revoked",
+            RotationError::ManufacturerMismatch => "manufacturer_mismatch",
+            RotationError::InvalidKey => "invalid_pubkey",
+            RotationError::InvalidWindow => "invalid_window",
+            RotationError::KeyExists => "key_exists",
+            RotationError::SignerNotCurrent => "signer_not_current",
+            RotationError::InvalidSignature => "invalid_signature",
+            RotationError::Db(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for RotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationError::UnknownManufacturer => write!(f, "Manufacturer not found"),
+            RotationError::RevokedManufacturer => write!(f, "Manufacturer has been revoked"),
+            RotationError::ManufacturerMismatch => {
+                write!(
+                    f,
+                    "manufacturer_id does not match the manufacturer in the path"
+                )
+            }
+            RotationError::InvalidKey => {
+                write!(f, "pk_hex is not a compressed secp256k1 public key")
+            }
+            RotationError::InvalidWindow => {
+                write!(
+                    f,
+                    "not_before must be a timestamp, not in the past, and not_after, if set, a later one"
+                )
+            }
+            RotationError::KeyExists => {
+                write!(f, "pk_hex is already registered as a manufacturer key")
+            }
+            RotationError::SignerNotCurrent => {
+                write!(f, "signed_by is not a key of this manufacturer valid now")
+            }
+            RotationError::InvalidSignature => {
+                write!(
+                    f,
+                    "Invalid signature: ECDSA verification failed against signed_by"
+                )
+            }
+            RotationError::Db(e) => write!(f, "database: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RotationError {}
+
+impl From<rusqlite::Error> for RotationError {
+    fn from(e: rusqlite::Error) -> Self {
+        RotationError::Db(e)
+    }
+}
+
+/// Parse an optional bound: `Some(None)` for empty, `None` if malformed.
+fn bound(s: &str) -> Option<Option<DateTime<Utc>>> {
+    if s.is_empty() {
+        return Some(None);
+    }
+    parse_cutoff(s).ok().map(Some)
+}
+
+/// Whether `key` may sign at `at`.
+pub fn valid_at(key: &ManufacturerKey, at: DateTime<Utc>) -> bool {
+    let Ok(not_before) = parse_cutoff(&key.not_before) else {
+        return false;
+    };
+    let (Some(not_after), Some(retired_at)) = (bound(&key.not_after), bound(&key.retired_at))
+    else {
+        return false;
+    };
+    not_before <= at
+        && not_after.is_none_or(|end| at < end)
+        && retired_at.is_none_or(|end| at < end)
+}
+
+/// Whether `key.signed_by` is a key of `key.manufacturer_id` that was
+/// current at `key.not_before`, as [`rotate`] requires when the key is
+/// added.  The signer's window counts as closed at the end, since `rotate`
+/// may retire it exactly at `not_before`.
+pub fn signer_was_current(conn: &Connection, key: &ManufacturerKey) -> rusqlite::Result<bool> {
+    let Ok(at) = parse_cutoff(&key.not_before) else {
+        return Ok(false);
+    };
+    let Some(signer) = db::get_key(conn, &key.signed_by).optional()? else {
+        return Ok(false);
+    };
+    let Ok(signer_from) = parse_cutoff(&signer.not_before) else {
+        return Ok(false);
+    };
+    let (Some(not_after), Some(retired_at)) = (bound(&signer.not_after), bound(&signer.retired_at))
+    else {
+        return Ok(false);
+    };
+    Ok(signer.manufacturer_id == key.manufacturer_id
+        && signer_from <= at
+        && not_after.is_none_or(|end| at <= end)
+        && retired_at.is_none_or(|end| at <= end))
+}
+
+/// The keys of `manufacturer_id` valid at `at`.
+pub fn keys_at(
+    conn: &Connection,
+    manufacturer_id: &str,
+    at: DateTime<Utc>,
+) -> rusqlite::Result<Vec<ManufacturerKey>> {
+    let mut keys = db::manufacturer_keys(conn, manufacturer_id)?;
+    keys.retain(|k| valid_at(k, at));
+    Ok(keys)
+}
+
+/// Add `key` to manufacturer `manufacturer_id` and retire its signer from
+/// the new key's `not_before` (or now, if that is in the past, so
+/// certificates the signer already issued stay valid).  Returns the key as
+/// stored.
+pub fn rotate(
+    conn: &Connection,
+    manufacturer_id: &str,
+    key: &ManufacturerKey,
+) -> Result<ManufacturerKey, RotationError> {
+    check_rotation(conn, manufacturer_id, key)?;
+    Ok(store_rotation(conn, key)?)
+}
+
+/// Every check [`rotate`] makes, signature last, without storing anything.
+pub fn check_rotation(
+    conn: &Connection,
+    manufacturer_id: &str,
+    key: &ManufacturerKey,
+) -> Result<(), RotationError> {
+    let manufacturer = db::get_manufacturer(conn, manufacturer_id)
+        .optional()?
+        .ok_or(RotationError::UnknownManufacturer)?;
+    if manufacturer.revocation.is_some() {
+        return Err(RotationError::RevokedManufacturer);
+    }
+    if key.manufacturer_id != manufacturer_id {
+        return Err(RotationError::ManufacturerMismatch);
+    }
+    if !is_compressed_pubkey(&key.pk_hex) {
+        return Err(RotationError::InvalidKey);
+    }
+    let now = Utc::now();
+    let not_before = parse_cutoff(&key.not_before).map_err(|_| RotationError::InvalidWindow)?;
+    // A key cannot vouch for certificates issued before it existed
+    if not_before < now - MAX_CLOCK_SKEW {
+        return Err(RotationError::InvalidWindow);
+    }
+    match bound(&key.not_after) {
+        Some(None) => {}
+        Some(Some(end)) if end > not_before => {}
+        _ => return Err(RotationError::InvalidWindow),
+    }
+    if db::get_key(conn, &key.pk_hex).optional()?.is_some() {
+        return Err(RotationError::KeyExists);
+    }
+    let signer = db::get_key(conn, &key.signed_by).optional()?;
+    if !signer.is_some_and(|s| s.manufacturer_id == manufacturer_id && valid_at(&s, now)) {
+        return Err(RotationError::SignerNotCurrent);
+    }
+    if !verify_rotation(key) {
+        return Err(RotationError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Store `key`, which passed [`check_rotation`], and retire its signer.
+pub fn store_rotation(
+    conn: &Connection,
+    key: &ManufacturerKey,
+) -> rusqlite::Result<ManufacturerKey> {
+    let now = Utc::now();
+    let not_before = parse_cutoff(&key.not_before).unwrap_or(now);
+    let stored = ManufacturerKey {
+        retired_at: String::new(),
+        ..key.clone()
+    };
+    db::insert_key(conn, &stored)?;
+    db::retire_key(conn, &key.signed_by, &not_before.max(now).to_rfc3339())?;
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::signature::{pubkey_hex, sign_manufacturer, sign_rotation};
+    use crate::types::Manufacturer;
+    use secp256k1::SecretKey;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        parse_cutoff(s).unwrap()
+    }
+
+    #[test]
+    fn rotation_hands_over_to_the_new_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let old = SecretKey::from_slice(&[5; 32]).unwrap();
+        let new = SecretKey::from_slice(&[6; 32]).unwrap();
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(&old, &mut mfr);
+        db::insert_manufacturer(&conn, &mfr).unwrap();
+        let id = mfr.pk_hex.clone();
+
+        let mut key = ManufacturerKey {
+            manufacturer_id: id.clone(),
+            pk_hex: pubkey_hex(&new),
+            not_before: "2099-01-01T00:00:00Z".into(),
+            not_after: "2100-01-01T00:00:00Z".into(),
+            retired_at: String::new(),
+            signed_by: String::new(),
+            signature: String::new(),
+        };
+        // Only a current key of this manufacturer may authorize a rotation
+        sign_rotation(&new, &mut key);
+        assert!(matches!(
+            rotate(&conn, &id, &key),
+            Err(RotationError::SignerNotCurrent)
+        ));
+        sign_rotation(&old, &mut key);
+        let mut backdated = key.clone();
+        backdated.not_before = "2026-01-01T00:00:00Z".into();
+        sign_rotation(&old, &mut backdated);
+        assert!(matches!(
+            rotate(&conn, &id, &backdated),
+            Err(RotationError::InvalidWindow)
+        ));
+        let mut tampered = key.clone();
+        tampered.not_after.clear();
+        assert!(matches!(
+            rotate(&conn, &id, &tampered),
+            Err(RotationError::InvalidSignature)
+        ));
+        rotate(&conn, &id, &key).unwrap();
+        assert!(matches!(
+            rotate(&conn, &id, &key),
+            Err(RotationError::KeyExists)
+        ));
+
+        // The old key signs until the new one takes over, then neither
+        // is valid once the new window closes
+        let pks = |s: &str| -> Vec<String> {
+            keys_at(&conn, &id, at(s))
+                .unwrap()
+                .into_iter()
+                .map(|k| k.pk_hex)
+                .collect()
+        };
+        assert_eq!(pks("2025-12-31"), Vec::<String>::new());
+        assert_eq!(pks("2050-01-01"), [mfr.pk_hex]);
+        assert_eq!(pks("2099-06-01"), [pubkey_hex(&new)]);
+        assert_eq!(pks("2100-01-01"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,174 @@
+//! Conduit Registry -- centralized content discovery index.
+//!
+//! A thin REST API over SQLite that stores content listings and seeder
+//! announcements. Stands in for Nostr relays / DHT during development.
+//! See `docs/12_registry.md` for the full specification.
+//!
+//! The library holds the data types, storage, handlers and router; the
+//! `conduit-registry` binary (`main.rs`) adds the CLI.  With the opt-in
+//! `client` feature it also provides [`client::RegistryClient`], a
+//! typed async client for other Conduit components.
+
+pub mod attestation;
+pub mod auth;
+pub mod buyer_keys;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod config;
+pub mod cors;
+pub mod dashboard;
+pub mod db;
+pub mod devices;
+pub mod error;
+pub mod export;
+pub mod handlers;
+pub mod health;
+pub mod info;
+pub mod keys;
+pub mod limits;
+pub mod openapi;
+pub mod policy;
+pub mod pre;
+pub mod proxies;
+pub mod ratelimit;
+pub mod revocation;
+pub mod signature;
+pub mod snapshot;
+pub mod tls;
+pub mod types;
+pub mod versioning;
+
+use std::sync::Arc;
+
+use axum::middleware;
+use axum::routing::get;
+use axum::Router;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+use crate::auth::require_admin;
+use crate::cors::{cors, CorsPolicy};
+use crate::dashboard::dashboard;
+use crate::health::{healthz, readyz};
+use crate::limits::{limit_body, BodyLimits};
+use crate::openapi::{docs, openapi_json, ApiDoc};
+use crate::ratelimit::limit_by_ip;
+use crate::types::AppState;
+use crate::versioning::{legacy_alias, v2_errors, LEGACY_PREFIX, V1_PREFIX, V2_PREFIX};
+
+/// The v1 API: the original routes, frozen.  Each `routes!` entry registers
+/// the handlers' axum routes and their OpenAPI operations together, from the
+/// handlers' `#[utoipa::path]` attributes.
+fn v1_routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(
+            handlers::create_listing,
+            handlers::list_listings,
+            handlers::delete_all_listings
+        ))
+        .routes(routes!(handlers::get_listing))
+        .routes(routes!(handlers::search_listings))
+        .routes(routes!(
+            handlers::create_seeder,
+            handlers::list_seeders,
+            handlers::delete_all_seeders
+        ))
+        .routes(routes!(handlers::discover))
+        .routes(routes!(
+            handlers::create_manufacturer,
+            handlers::list_manufacturers
+        ))
+        .routes(routes!(
+            handlers::get_manufacturer,
+            handlers::delete_manufacturer
+        ))
+        .routes(routes!(handlers::create_block, handlers::list_blocks))
+        .routes(routes!(handlers::delete_block))
+}
+
+/// The v2 API: paginated lists, device certificates and the admin
+/// extras; error envelopes are applied by `v2_errors`.
+fn v2_routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(
+            handlers::create_listing,
+            handlers::list_listings_v2,
+            handlers::delete_all_listings
+        ))
+        .routes(routes!(handlers::get_listing))
+        .routes(routes!(handlers::search_listings_v2))
+        .routes(routes!(
+            handlers::create_seeder,
+            handlers::list_seeders_v2,
+            handlers::delete_all_seeders
+        ))
+        .routes(routes!(handlers::discover))
+        .routes(routes!(
+            handlers::create_manufacturer,
+            handlers::list_manufacturers_v2
+        ))
+        .routes(routes!(
+            handlers::get_manufacturer,
+            handlers::delete_manufacturer
+        ))
+        .routes(routes!(handlers::list_revocations))
+        .routes(routes!(
+            handlers::rotate_manufacturer_key,
+            handlers::list_manufacturer_keys
+        ))
+        .routes(routes!(handlers::create_device))
+        .routes(routes!(handlers::get_device))
+        .routes(routes!(handlers::verify_device))
+        .routes(routes!(handlers::verify_attestation))
+        .routes(routes!(handlers::publish_buyer_key))
+        .routes(routes!(handlers::get_buyer_key))
+        .routes(routes!(handlers::buyer_key_history))
+        .routes(routes!(handlers::announce_proxy, handlers::list_proxies))
+        .routes(routes!(handlers::create_block, handlers::list_blocks_v2))
+        .routes(routes!(handlers::delete_block))
+        .routes(routes!(handlers::export_records))
+        .routes(routes!(handlers::import_records))
+        .routes(routes!(handlers::create_snapshot))
+        .routes(routes!(handlers::get_info))
+}
+
+/// The documented, versioned API and its OpenAPI document.
+pub fn api() -> (Router<AppState>, utoipa::openapi::OpenApi) {
+    let (router, spec) = OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .nest(V1_PREFIX, v1_routes())
+        .nest(V2_PREFIX, v2_routes())
+        .split_for_parts();
+    (router, openapi::finalize(spec))
+}
+
+/// The complete application: dashboard, docs, versioned API, legacy
+/// aliases, health probes and the cross-cutting layers.
+pub fn app(state: AppState, body_limits: BodyLimits) -> Router {
+    let cors_policy = CorsPolicy::new(&state.config.read().unwrap().cors);
+
+    let (api, spec) = api();
+    let spec = Arc::new(spec);
+
+    // Deprecated aliases: /api/... -> /api/v1/...
+    let (v1, _) = v1_routes().split_for_parts();
+    let legacy = v1.route_layer(middleware::from_fn_with_state(state.clone(), legacy_alias));
+
+    Router::new()
+        .route("/", get(dashboard))
+        .route("/api/openapi.json", get(move || openapi_json(spec.clone())))
+        .route("/api/docs", get(docs))
+        // Unversioned so clients can read it before choosing a version
+        .route("/api/info", get(handlers::get_info))
+        .merge(api)
+        .nest(LEGACY_PREFIX, legacy)
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+        .route_layer(middleware::from_fn_with_state(state.clone(), limit_by_ip))
+        // Probes are added after the rate limiter so they are never throttled
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .layer(middleware::from_fn_with_state(body_limits, limit_body))
+        .layer(middleware::from_fn_with_state(cors_policy, cors))
+        .layer(middleware::from_fn(v2_errors))
+        .with_state(state)
+}
@@ -0,0 +1,150 @@
+//! Request size caps for the Conduit Registry.
+//!
+//! The whole request body is bounded by `limit_body`; individual string
+//! fields are bounded here, at deserialization time, so an oversized value
+//! is rejected before it ever reaches a handler or the database.
+
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Deserializer};
+use tower::{ServiceBuilder, ServiceExt};
+use tower_http::limit::RequestBodyLimitLayer;
+
+/// Default cap on any request body, in bytes.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+/// Default cap on NDJSON import bodies (`POST /admin/import`), in bytes.
+pub const DEFAULT_MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Route suffix that gets the import cap instead of the default one.
+const IMPORT_PATH: &str = "/admin/import";
+
+/// Page size for paginated (v2) list endpoints when `limit` is omitted.
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Largest page a client may request.
+pub const MAX_PAGE_SIZE: u32 = 200;
+
+/// Hex-encoded hashes and Merkle roots (SHA-256 is 64).
+pub const HASH_MAX: usize = 128;
+/// Hex-encoded secp256k1 public keys (compressed is 66).
+pub const PUBKEY_MAX: usize = 132;
+/// zbase32 Lightning signatures (65 bytes encodes to 104).
+pub const SIGNATURE_MAX: usize = 128;
+/// Node aliases and display names.
+pub const ALIAS_MAX: usize = 64;
+/// File names.
+pub const FILE_NAME_MAX: usize = 255;
+/// Network and Lightning addresses, URLs.
+pub const ADDRESS_MAX: usize = 256;
+/// RFC 3339 timestamps and short identifiers such as playback policies.
+pub const SHORT_MAX: usize = 64;
+/// Free-form descriptions.
+pub const DESCRIPTION_MAX: usize = 1024;
+/// Hex-encoded PRE capsule components and keys.
+pub const PRE_HEX_MAX: usize = 2048;
+/// Entries in a listing's `playback_params.allowed_manufacturers`.
+pub const ALLOWED_MANUFACTURERS_MAX: usize = 32;
+/// Entries in a proxy announcement's `schemes`.
+pub const PROXY_SCHEMES_MAX: usize = 8;
+
+/// Request body caps, in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimits {
+    pub default: usize,
+    pub import: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        BodyLimits {
+            default: DEFAULT_MAX_BODY_BYTES,
+            import: DEFAULT_MAX_IMPORT_BYTES,
+        }
+    }
+}
+
+/// Middleware: bound the request body by the cap for its route.  Bodies
+/// over the cap get `413 Payload Too Large`.
+pub async fn limit_body(State(limits): State<BodyLimits>, req: Request, next: Next) -> Response {
+    let max = if req.uri().path().ends_with(IMPORT_PATH) {
+        limits.import
+    } else {
+        limits.default
+    };
+    let svc = ServiceBuilder::new()
+        .layer(RequestBodyLimitLayer::new(max))
+        .map_request(|req: Request<_>| req.map(Body::new))
+        .service(next);
+    match svc.oneshot(req).await {
+        Ok(res) => res.map(Body::new),
+        Err(never) => match never {},
+    }
+}
+
+/// `deserialize_with` helper: a `String` of at most `N` bytes.
+///
+/// ```ignore
+/// #[serde(deserialize_with = "max_len::<_, ALIAS_MAX>")]
+/// pub creator_alias: String,
+/// ```
+pub fn max_len<'de, D, const N: usize>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    if s.len() > N {
+        return Err(serde::de::Error::custom(format!(
+            "string of {} bytes exceeds maximum length of {}",
+            s.len(),
+            N
+        )));
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{ContentListing, SeederAnnouncement};
+
+    fn listing_json(alias: &str) -> serde_json::Value {
+        serde_json::json!({
+            "content_hash": "aa", "encrypted_hash": "bb", "file_name": "a.mp4",
+            "size_bytes": 1, "price_sats": 1, "chunk_size": 1, "chunk_count": 1,
+            "plaintext_root": "", "encrypted_root": "", "creator_pubkey": "02ab",
+            "creator_address": "127.0.0.1:1", "creator_ln_address": "", "creator_alias": alias,
+            "registered_at": "2026-01-01T00:00:00Z",
+        })
+    }
+
+    #[test]
+    fn accepts_fields_within_limits() {
+        let l: ContentListing = serde_json::from_value(listing_json("alice")).unwrap();
+        assert_eq!(l.creator_alias, "alice");
+        assert_eq!(l.playback_policy, "open");
+    }
+
+    #[test]
+    fn rejects_oversized_alias() {
+        let long = "x".repeat(super::ALIAS_MAX + 1);
+        let err = serde_json::from_value::<ContentListing>(listing_json(&long)).unwrap_err();
+        assert!(err.to_string().contains("exceeds maximum length"));
+    }
+
+    #[test]
+    fn rejects_oversized_defaulted_field() {
+        let mut v = listing_json("alice");
+        v["pre_c1_hex"] = "0".repeat(super::PRE_HEX_MAX + 1).into();
+        assert!(serde_json::from_value::<ContentListing>(v).is_err());
+    }
+
+    #[test]
+    fn seeder_fields_are_capped() {
+        let v = serde_json::json!({
+            "encrypted_hash": "bb", "seeder_pubkey": "02ab", "seeder_address": "x".repeat(1000),
+            "seeder_ln_address": "", "seeder_alias": "", "transport_price": 1,
+            "chunk_count": 1, "announced_at": "",
+        });
+        assert!(serde_json::from_value::<SeederAnnouncement>(v).is_err());
+    }
+}
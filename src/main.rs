@@ -1,81 +1,514 @@
-//! Conduit Registry -- centralized content discovery index.
-//!
-//! A thin REST API over SQLite that stores content listings and seeder
-//! announcements. Stands in for Nostr relays / DHT during development.
-//! See `docs/12_registry.md` for the full specification.
+//! Conduit Registry binary: the server and offline tooling.
 //!
 //! Usage:
-//!   conduit-registry --port 3003 --db-path /tmp/conduit-registry.db
+//!   conduit-registry [serve] --port 3003 --db-path /tmp/conduit-registry.db
+//!   conduit-registry [serve] --config registry.toml
+//!   conduit-registry config check --config registry.toml
+//!   conduit-registry --tls-cert fullchain.pem --tls-key privkey.pem --http-redirect-port 80
+//!   conduit-registry sign-listing listing.json --secret-key <hex>
+//!   conduit-registry verify-listing listing.json
+//!   conduit-registry sign-manufacturer manufacturer.json --secret-key <hex>
+//!   conduit-registry sign-device device.json --secret-key <hex>
+//!   conduit-registry list|search|show|purge --db-path /tmp/conduit-registry.db ...
+//!   conduit-registry export -o backup.ndjson / import backup.ndjson [--skip-invalid]
+//!   conduit-registry restore snapshots/registry-20260101T000000.000Z.sqlite --db-path ...
 
-mod dashboard;
-mod db;
-mod handlers;
-mod signature;
-mod types;
+mod commands;
 
-use std::sync::{Arc, Mutex};
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::routing::{get, post};
-use axum::Router;
-use clap::Parser;
+use axum::serve::ListenerExt;
+use clap::{Args, Parser, Subcommand};
 use rusqlite::Connection;
-use tower_http::cors::{Any, CorsLayer};
+use tokio::sync::watch;
 
-use crate::db::init_db;
-use crate::handlers::{
-    create_listing, create_manufacturer, create_seeder, delete_all_listings, delete_all_manufacturers,
-    delete_all_seeders, delete_manufacturer, discover, get_listing, get_manufacturer, list_listings,
-    list_manufacturers, list_seeders, search_listings,
-};
-use crate::dashboard::dashboard;
-use crate::types::AppState;
+use conduit_registry::app;
+use conduit_registry::config::{Config, ConfigError};
+use conduit_registry::db::init_db;
+use conduit_registry::limits::BodyLimits;
+use conduit_registry::ratelimit::Limit;
+use conduit_registry::revocation;
+use conduit_registry::snapshot::{self, format_interval, parse_interval};
+use conduit_registry::tls::{self, CertStore, TlsListener, CERT_POLL_INTERVAL};
+use conduit_registry::types::AppState;
+use conduit_registry::versioning::parse_cutoff;
 
 #[derive(Parser)]
 #[command(name = "conduit-registry")]
 #[command(about = "Conduit content discovery registry")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// HTTP port to listen on
-    #[arg(long, default_value = "3003")]
-    port: u16,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// With no subcommand, the server runs with these arguments
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP server (the default)
+    Serve(ServeArgs),
+    /// Sign a listing JSON file with a creator secret key
+    SignListing(commands::SignListingArgs),
+    /// Check the creator signature of a listing JSON file
+    VerifyListing(commands::VerifyListingArgs),
+    /// Sign a manufacturer registration with the manufacturer secret key
+    SignManufacturer(commands::SignManufacturerArgs),
+    /// Authorize a new manufacturer key with a current one
+    SignRotation(commands::SignRotationArgs),
+    /// Sign a buyer PRE key with the buyer's node secret key
+    SignBuyerKey(commands::SignBuyerKeyArgs),
+    /// Certify a device key with the manufacturer secret key
+    SignDevice(commands::SignDeviceArgs),
+    /// Print listings from the database
+    List(commands::ListArgs),
+    /// Search listings in the database
+    Search(commands::SearchArgs),
+    /// Print one listing and its seeders from the database
+    Show(commands::ShowArgs),
+    /// Delete a listing and its seeder announcements from the database
+    Purge(commands::PurgeArgs),
+    /// Write every record in the database as NDJSON
+    Export(commands::ExportArgs),
+    /// Load an NDJSON export, re-verifying signatures
+    Import(commands::ImportArgs),
+    /// Replace the database with a validated snapshot (server must be stopped)
+    Restore(commands::RestoreArgs),
+    /// Inspect the server configuration
+    Config(ConfigArgs),
+}
+
+/// Server settings.  Each overrides the same setting in the `--config`
+/// file; unset flags fall back to the file, then to the defaults.
+#[derive(Args, Clone)]
+struct ServeArgs {
+    /// TOML configuration file
+    #[arg(long, env = "CONDUIT_REGISTRY_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Registry name published in GET /api/info [default: Conduit Registry]
+    #[arg(long, env = "CONDUIT_REGISTRY_NAME")]
+    registry_name: Option<String>,
+
+    /// Operator's node public key (hex) published in GET /api/info
+    #[arg(long, env = "CONDUIT_REGISTRY_OPERATOR_PUBKEY")]
+    operator_pubkey: Option<String>,
+
+    /// Secret key (hex) for signing manufacturer revocations and attestation
+    /// tokens; enables both
+    #[arg(long, env = "CONDUIT_REGISTRY_SIGNING_KEY", hide_env_values = true)]
+    signing_key: Option<String>,
+
+    /// How long attestation tokens are valid, at most 24h, e.g. 5m [default: 5m]
+    #[arg(long, env = "CONDUIT_REGISTRY_ATTESTATION_TTL", value_parser = parse_interval)]
+    attestation_ttl: Option<Duration>,
+
+    /// HTTP port to listen on [default: 3003]
+    #[arg(long, env = "CONDUIT_REGISTRY_PORT")]
+    port: Option<u16>,
+
+    /// Path to the SQLite database file [default: /tmp/conduit-registry.db]
+    #[arg(long, env = "CONDUIT_REGISTRY_DB_PATH")]
+    db_path: Option<PathBuf>,
+
+    /// Maximum request body size in bytes [default: 65536]
+    #[arg(long, env = "CONDUIT_REGISTRY_MAX_BODY_BYTES")]
+    max_body_bytes: Option<usize>,
+
+    /// Maximum body size for NDJSON imports (POST /api/v2/admin/import) [default: 67108864]
+    #[arg(long, env = "CONDUIT_REGISTRY_MAX_IMPORT_BYTES")]
+    max_import_bytes: Option<usize>,
+
+    /// Directory for online snapshots (enables POST /api/v2/admin/snapshot)
+    #[arg(long, env = "CONDUIT_REGISTRY_SNAPSHOT_DIR")]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Take a snapshot this often, e.g. 30m, 6h, 1d (requires a snapshot directory)
+    #[arg(long, env = "CONDUIT_REGISTRY_SNAPSHOT_INTERVAL", value_parser = parse_interval)]
+    snapshot_interval: Option<Duration>,
+
+    /// Number of snapshots to keep; older ones are deleted [default: 7]
+    #[arg(long, env = "CONDUIT_REGISTRY_SNAPSHOT_KEEP")]
+    snapshot_keep: Option<usize>,
+
+    /// Stop serving the unprefixed /api/... aliases of /api/v1 after this
+    /// date (YYYY-MM-DD or RFC 3339); unset keeps them indefinitely
+    #[arg(long, env = "CONDUIT_REGISTRY_LEGACY_API_CUTOFF", value_parser = parse_cutoff)]
+    legacy_api_cutoff: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Per-IP limit for read routes: "<per_minute>[,<burst>]", 0 = unlimited [default: 600,120]
+    #[arg(long, env = "CONDUIT_REGISTRY_READ_LIMIT")]
+    read_limit: Option<Limit>,
+
+    /// Per-IP and per-pubkey limit for unsigned write routes [default: 60,20]
+    #[arg(long, env = "CONDUIT_REGISTRY_WRITE_LIMIT")]
+    write_limit: Option<Limit>,
+
+    /// Per-IP and per-pubkey limit for signature-verifying routes [default: 20,10]
+    #[arg(long, env = "CONDUIT_REGISTRY_VERIFY_LIMIT")]
+    verify_limit: Option<Limit>,
+
+    /// On SIGTERM/SIGINT, wait this long for in-flight requests, e.g. 30s [default: 30s]
+    #[arg(long, env = "CONDUIT_REGISTRY_SHUTDOWN_TIMEOUT", value_parser = parse_interval)]
+    shutdown_timeout: Option<Duration>,
+
+    /// Require "Authorization: Bearer <token>" on admin routes and manufacturer registration
+    #[arg(long, env = "CONDUIT_REGISTRY_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Start in maintenance mode: GET /readyz reports 503
+    #[arg(long, env = "CONDUIT_REGISTRY_MAINTENANCE", num_args = 0..=1, default_missing_value = "true")]
+    maintenance: Option<bool>,
+
+    /// Comma-separated origins allowed to call write routes cross-origin, or "*"
+    #[arg(
+        long,
+        env = "CONDUIT_REGISTRY_CORS_WRITE_ORIGINS",
+        value_delimiter = ','
+    )]
+    cors_write_origins: Option<Vec<String>>,
+
+    /// How long browsers may cache CORS preflights, e.g. 10m [default: 1h]
+    #[arg(long, env = "CONDUIT_REGISTRY_CORS_MAX_AGE", value_parser = parse_interval)]
+    cors_max_age: Option<Duration>,
+
+    /// Allow credentials on cross-origin writes
+    #[arg(long, env = "CONDUIT_REGISTRY_CORS_ALLOW_CREDENTIALS", num_args = 0..=1, default_missing_value = "true")]
+    cors_allow_credentials: Option<bool>,
+
+    /// Serve HTTPS with this PEM certificate chain (requires --tls-key)
+    #[arg(long, env = "CONDUIT_REGISTRY_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, env = "CONDUIT_REGISTRY_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+
+    /// Also listen for plain HTTP on this port and redirect it to HTTPS
+    #[arg(long, env = "CONDUIT_REGISTRY_HTTP_REDIRECT_PORT")]
+    http_redirect_port: Option<u16>,
+}
+
+impl ServeArgs {
+    /// The effective configuration: defaults, then the config file, then
+    /// environment variables and flags.
+    fn resolve(&self) -> Result<Config, ConfigError> {
+        let mut cfg = Config::load(self.config.as_deref())?;
+        set(&mut cfg.registry.name, self.registry_name.clone());
+        if self.operator_pubkey.is_some() {
+            cfg.registry.operator_pubkey = self.operator_pubkey.clone();
+        }
+        if self.signing_key.is_some() {
+            cfg.registry.signing_key = self.signing_key.clone();
+        }
+        set(&mut cfg.registry.attestation_ttl, self.attestation_ttl);
+        let server = &mut cfg.server;
+        set(&mut server.port, self.port);
+        set(&mut server.db_path, self.db_path.clone());
+        set(&mut server.max_body_bytes, self.max_body_bytes);
+        set(&mut server.max_import_bytes, self.max_import_bytes);
+        if self.legacy_api_cutoff.is_some() {
+            server.legacy_api_cutoff = self.legacy_api_cutoff;
+        }
+        set(&mut server.shutdown_timeout, self.shutdown_timeout);
+        set(&mut server.maintenance, self.maintenance);
+        if self.admin_token.is_some() {
+            server.admin_token = self.admin_token.clone();
+        }
+        let snapshots = &mut cfg.snapshots;
+        if self.snapshot_dir.is_some() {
+            snapshots.dir = self.snapshot_dir.clone();
+        }
+        if self.snapshot_interval.is_some() {
+            snapshots.interval = self.snapshot_interval;
+        }
+        set(&mut snapshots.keep, self.snapshot_keep);
+        let limits = &mut cfg.rate_limits;
+        set(&mut limits.read, self.read_limit);
+        set(&mut limits.write, self.write_limit);
+        set(&mut limits.verify, self.verify_limit);
+        let cors = &mut cfg.cors;
+        set(&mut cors.write_origins, self.cors_write_origins.clone());
+        set(&mut cors.max_age, self.cors_max_age);
+        set(&mut cors.allow_credentials, self.cors_allow_credentials);
+        let tls = &mut cfg.tls;
+        if self.tls_cert.is_some() {
+            tls.cert = self.tls_cert.clone();
+        }
+        if self.tls_key.is_some() {
+            tls.key = self.tls_key.clone();
+        }
+        if self.http_redirect_port.is_some() {
+            tls.http_redirect_port = self.http_redirect_port;
+        }
+        cfg.validate()?;
+        Ok(cfg)
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(v) = value {
+        *field = v;
+    }
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
 
-    /// Path to the SQLite database file
-    #[arg(long, default_value = "/tmp/conduit-registry.db")]
-    db_path: String,
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate the configuration and print the effective settings as TOML
+    Check(ServeArgs),
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
+    let result = match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(args) => serve(args).await,
+        Command::SignListing(args) => commands::sign_listing_cmd(args),
+        Command::VerifyListing(args) => commands::verify_listing_cmd(args),
+        Command::SignManufacturer(args) => commands::sign_manufacturer_cmd(args),
+        Command::SignRotation(args) => commands::sign_rotation_cmd(args),
+        Command::SignBuyerKey(args) => commands::sign_buyer_key_cmd(args),
+        Command::SignDevice(args) => commands::sign_device_cmd(args),
+        Command::List(args) => commands::list_cmd(args),
+        Command::Search(args) => commands::search_cmd(args),
+        Command::Show(args) => commands::show_cmd(args),
+        Command::Purge(args) => commands::purge_cmd(args),
+        Command::Export(args) => commands::export_cmd(args),
+        Command::Import(args) => commands::import_cmd(args),
+        Command::Restore(args) => commands::restore_cmd(args),
+        Command::Config(ConfigArgs {
+            command: ConfigCommand::Check(args),
+        }) => config_check(args),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn config_check(args: ServeArgs) -> Result<(), String> {
+    let cfg = args.resolve().map_err(|e| e.to_string())?;
+    print!("{}", cfg.to_toml());
+    Ok(())
+}
+
+async fn serve(args: ServeArgs) -> Result<(), String> {
+    let cfg = args.resolve().map_err(|e| e.to_string())?;
+    if let Some(path) = &args.config {
+        println!("Config: {}", path.display());
+    }
+
     // Open (or create) SQLite database
-    let conn = Connection::open(&cli.db_path).expect("Failed to open database");
+    let conn = Connection::open(&cfg.server.db_path).expect("Failed to open database");
     init_db(&conn);
-    println!("Database: {}", cli.db_path);
+    println!("Database: {}", cfg.server.db_path.display());
+    if let Some(key) = cfg.registry.signing_key() {
+        match revocation::resign(&conn, &key) {
+            Ok(0) => {}
+            Ok(n) => println!("Re-signed {} revocations under the current signing key", n),
+            Err(e) => return Err(format!("re-signing revocations: {}", e)),
+        }
+    }
+
+    let limits = cfg.rate_limits;
+    println!(
+        "Rate limits (per min, burst): read {}, write {}, verify {}",
+        limits.read, limits.write, limits.verify
+    );
+
+    if let Some(cutoff) = cfg.server.legacy_api_cutoff {
+        println!("Unprefixed /api/ aliases retire at {}", cutoff.to_rfc3339());
+    }
+    if cfg.server.maintenance {
+        println!("Maintenance mode: /readyz reports not ready");
+    }
+    if cfg.server.admin_token.is_none() {
+        eprintln!(
+            "Warning: no --admin-token; admin routes and manufacturer registration are disabled"
+        );
+    }
 
-    let state = AppState {
-        db: Arc::new(Mutex::new(conn)),
+    let snapshots = cfg.snapshots.enabled();
+    let db_path = cfg.server.db_path.clone();
+    let body_limits = BodyLimits {
+        default: cfg.server.max_body_bytes,
+        import: cfg.server.max_import_bytes,
     };
+    let addr = format!("0.0.0.0:{}", cfg.server.port);
+    let https_port = cfg.server.port;
+    let redirect_port = cfg.tls.http_redirect_port;
+    let cfg_tls = cfg.tls.enabled();
+    let state = AppState::new(conn, cfg);
+
+    if let Some(cfg) = snapshots {
+        match cfg.interval {
+            Some(every) => println!(
+                "Snapshots: every {} into {}, keeping {}",
+                format_interval(every),
+                cfg.dir.display(),
+                cfg.keep
+            ),
+            None => println!(
+                "Snapshots: on demand into {}, keeping {}",
+                cfg.dir.display(),
+                cfg.keep
+            ),
+        }
+        tokio::spawn(snapshot::run_periodic(db_path, cfg));
+    }
+
+    let certs = match cfg_tls {
+        Some((cert, key)) => {
+            let store = CertStore::load(cert, key).map_err(|e| e.to_string())?;
+            tokio::spawn(tls::watch(store.clone(), CERT_POLL_INTERVAL));
+            Some(store)
+        }
+        None => None,
+    };
+
+    #[cfg(unix)]
+    tokio::spawn(reload_on_sighup(state.clone(), args, certs.clone()));
+
+    let stop = stop_on_signal();
+    let app = app(state.clone(), body_limits).into_make_service_with_connect_info::<SocketAddr>();
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+
+    match certs {
+        Some(store) => {
+            if let Some(port) = redirect_port {
+                let redirect_addr = format!("0.0.0.0:{}", port);
+                let redirect = tokio::net::TcpListener::bind(&redirect_addr)
+                    .await
+                    .map_err(|e| format!("cannot listen on {}: {}", redirect_addr, e))?;
+                println!("Redirecting http://{} to HTTPS", redirect_addr);
+                let redirect = axum::serve(redirect, tls::redirect_app(https_port))
+                    .with_graceful_shutdown(stopped(stop.clone()));
+                tokio::spawn(redirect.into_future());
+            }
+            let config = store.server_config().map_err(|e| e.to_string())?;
+            let listener = TlsListener::new(listener, config).map_err(|e| e.to_string())?;
+            println!("Conduit Registry listening on https://{}", addr);
+            // `tap_io` gives the listener axum's ConnectInfo<SocketAddr> impl
+            let server = axum::serve(listener.tap_io(|_| {}), app)
+                .with_graceful_shutdown(stopped(stop.clone()));
+            drain(server, stop, &state).await
+        }
+        None => {
+            println!("Conduit Registry listening on {}", addr);
+            let server = axum::serve(listener, app).with_graceful_shutdown(stopped(stop.clone()));
+            drain(server, stop, &state).await
+        }
+    }
+}
+
+/// Flips to `true` on the first SIGTERM or SIGINT.
+fn stop_on_signal() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+    tokio::spawn(async move {
+        let interrupt = tokio::signal::ctrl_c();
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            signal(SignalKind::terminate())
+                .expect("Failed to install SIGTERM handler")
+                .recv()
+                .await;
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = interrupt => {}
+            _ = terminate => {}
+        }
+        println!("Shutting down: no new connections, finishing in-flight requests");
+        let _ = tx.send(true);
+    });
+    rx
+}
+
+async fn stopped(mut stop: watch::Receiver<bool>) {
+    let _ = stop.wait_for(|s| *s).await;
+}
+
+/// Run `server` until it has drained after a stop signal, giving up on
+/// open connections after `server.shutdown_timeout`.
+async fn drain<S>(server: S, stop: watch::Receiver<bool>, state: &AppState) -> Result<(), String>
+where
+    S: IntoFuture<Output = std::io::Result<()>>,
+{
+    let mut server = std::pin::pin!(server.into_future());
+    tokio::select! {
+        res = &mut server => return res.map_err(|e| format!("server error: {}", e)),
+        _ = stopped(stop) => {}
+    }
+    let timeout = state.config.read().unwrap().server.shutdown_timeout;
+    match tokio::time::timeout(timeout, server).await {
+        Ok(res) => {
+            println!("Shutdown complete");
+            res.map_err(|e| format!("server error: {}", e))
+        }
+        Err(_) => {
+            eprintln!(
+                "Shutdown timed out after {}; closing remaining connections",
+                format_interval(timeout)
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Re-read the configuration on SIGHUP and apply what can change live.
+/// Environment variables and flags are those the process started with.
+/// Also re-reads the TLS certificate files, if they changed.
+#[cfg(unix)]
+async fn reload_on_sighup(state: AppState, args: ServeArgs, certs: Option<Arc<CertStore>>) {
+    use tokio::signal::unix::{signal, SignalKind};
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    let app = Router::new()
-        .route("/", get(dashboard))
-        .route("/api/listings", post(create_listing).get(list_listings).delete(delete_all_listings))
-        .route("/api/listings/{content_hash}", get(get_listing))
-        .route("/api/search", get(search_listings))
-        .route("/api/seeders", post(create_seeder).get(list_seeders).delete(delete_all_seeders))
-        .route("/api/discover/{content_hash}", get(discover))
-        .route("/api/manufacturers", post(create_manufacturer).get(list_manufacturers).delete(delete_all_manufacturers))
-        .route("/api/manufacturers/{pk_hex}", get(get_manufacturer).delete(delete_manufacturer))
-        .layer(cors)
-        .with_state(state);
-
-    let addr = format!("0.0.0.0:{}", cli.port);
-    println!("Conduit Registry listening on {}", addr);
-
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to install SIGHUP handler");
+    while hangup.recv().await.is_some() {
+        match args.resolve() {
+            Ok(cfg) => {
+                let pending = state.reload(cfg);
+                let limits = state.limiter.limits();
+                println!(
+                    "Configuration reloaded; rate limits: read {}, write {}, verify {}",
+                    limits.read, limits.write, limits.verify
+                );
+                if !pending.is_empty() {
+                    println!(
+                        "Changes to {} take effect after a restart",
+                        pending.join(", ")
+                    );
+                }
+            }
+            Err(e) => eprintln!(
+                "Configuration reload failed, keeping the current one: {}",
+                e
+            ),
+        }
+        match certs.as_ref().map(|c| c.reload_if_changed()) {
+            Some(Ok(true)) => println!("TLS certificate reloaded"),
+            Some(Err(e)) => eprintln!(
+                "TLS certificate reload failed, keeping the current one: {}",
+                e
+            ),
+            _ => {}
+        }
+    }
 }
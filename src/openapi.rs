@@ -0,0 +1,177 @@
+//! OpenAPI document for the Conduit Registry.
+//!
+//! Paths come from the `#[utoipa::path]` attributes on the handlers and are
+//! registered together with their axum routes in `lib.rs`, so a route
+//! cannot be served without also appearing in the spec.  Only the versioned
+//! prefixes are documented; the deprecated `/api/...` aliases are not.
+//!
+//! The generated document is checked in as `openapi.json` and compared in
+//! tests; run `UPDATE_OPENAPI=1 cargo test openapi` after changing the API.
+
+use std::sync::Arc;
+
+use axum::response::Html;
+use axum::Json;
+use utoipa::OpenApi;
+
+use crate::types::{ErrorDetail, ErrorEnvelope};
+use crate::versioning::V2_PREFIX;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Conduit Registry",
+        license(name = "PolyForm-Noncommercial-1.0.0"),
+        description = "Content discovery registry for the Conduit network. \
+                       All routes are rate limited per source IP and answer \
+                       429 with a Retry-After header when the limit is exceeded."
+    ),
+    components(schemas(ErrorEnvelope, ErrorDetail)),
+    tags(
+        (name = "listings", description = "Creator content listings"),
+        (name = "seeders", description = "Seeder availability announcements"),
+        (name = "manufacturers", description = "TEE device manufacturers"),
+        (name = "devices", description = "Manufacturer-certified TEE device keys"),
+        (name = "buyers", description = "Buyer proxy re-encryption keys"),
+        (name = "proxies", description = "Re-encryption proxy announcements"),
+        (name = "admin", description = "Operator endpoints"),
+        (name = "info", description = "Registry information and capabilities"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Post-process the generated spec for the two mounted versions.
+///
+/// v1 and v2 share handlers, so the annotations describe the v1 error body
+/// and repeat operation ids.  Point every v2 error response at
+/// `ErrorEnvelope` (applied by middleware) and prefix each operation id with
+/// its version.
+pub fn finalize(spec: utoipa::openapi::OpenApi) -> utoipa::openapi::OpenApi {
+    const V1_ERROR: &str = "#/components/schemas/ErrorResponse";
+    const V2_ERROR: &str = "#/components/schemas/ErrorEnvelope";
+
+    fn patch(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if map.get("$ref").and_then(|r| r.as_str()) == Some(V1_ERROR) {
+                    map.insert("$ref".into(), V2_ERROR.into());
+                }
+                map.values_mut().for_each(patch);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(patch),
+            _ => {}
+        }
+    }
+
+    let mut value = serde_json::to_value(&spec).expect("OpenAPI spec serializes");
+    if let Some(paths) = value["paths"].as_object_mut() {
+        for (path, item) in paths.iter_mut() {
+            let version = if path.starts_with(V2_PREFIX) {
+                "v2"
+            } else {
+                "v1"
+            };
+            if version == "v2" {
+                patch(item);
+            }
+            for op in item
+                .as_object_mut()
+                .into_iter()
+                .flat_map(|m| m.values_mut())
+            {
+                if let Some(id) = op["operationId"].as_str() {
+                    op["operationId"] = format!("{}_{}", version, id).into();
+                }
+            }
+        }
+    }
+    serde_json::from_value(value).expect("patched OpenAPI spec deserializes")
+}
+
+/// GET /api/openapi.json -- the OpenAPI document
+pub async fn openapi_json(spec: Arc<utoipa::openapi::OpenApi>) -> Json<utoipa::openapi::OpenApi> {
+    Json((*spec).clone())
+}
+
+/// GET /api/docs -- Redoc viewer for the OpenAPI document
+pub async fn docs() -> Html<&'static str> {
+    Html(DOCS_HTML)
+}
+
+const DOCS_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Conduit Registry API</title>
+<style>body { margin: 0; padding: 0; }</style>
+</head>
+<body>
+<redoc spec-url="/api/openapi.json"></redoc>
+<script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    const CHECKED_IN: &str = include_str!("../openapi.json");
+
+    fn generated() -> serde_json::Value {
+        let (_, spec) = crate::api();
+        serde_json::to_value(&spec).unwrap()
+    }
+
+    #[test]
+    fn openapi_matches_checked_in_spec() {
+        let spec = generated();
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+            let mut text = serde_json::to_string_pretty(&spec).unwrap();
+            text.push('\n');
+            std::fs::write(path, text).unwrap();
+            return;
+        }
+        let checked_in: serde_json::Value = serde_json::from_str(CHECKED_IN).unwrap();
+        assert!(
+            spec == checked_in,
+            "openapi.json is out of date; regenerate with `UPDATE_OPENAPI=1 cargo test openapi`"
+        );
+    }
+
+    #[test]
+    fn every_route_has_documented_responses() {
+        let spec = generated();
+        let paths = spec["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/api/v1/listings"));
+        assert!(paths.contains_key("/api/v2/listings"));
+        assert!(paths.contains_key("/api/v1/discover/{content_hash}"));
+        assert!(
+            !paths.contains_key("/api/listings"),
+            "legacy aliases are undocumented"
+        );
+        for (path, item) in paths {
+            for (method, op) in item.as_object().unwrap() {
+                assert!(
+                    op["responses"]
+                        .as_object()
+                        .is_some_and(|r| r.contains_key("200")),
+                    "{} {} has no 200 response",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn v2_errors_use_envelope() {
+        let spec = generated();
+        let get = &spec["paths"]["/api/v2/listings/{content_hash}"]["get"];
+        let schema = &get["responses"]["404"]["content"]["application/json"]["schema"]["$ref"];
+        assert_eq!(schema, "#/components/schemas/ErrorEnvelope");
+        let get = &spec["paths"]["/api/v1/listings/{content_hash}"]["get"];
+        let schema = &get["responses"]["404"]["content"]["application/json"]["schema"]["$ref"];
+        assert_eq!(schema, "#/components/schemas/ErrorResponse");
+    }
+}
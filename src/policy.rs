@@ -0,0 +1,265 @@
+//! Playback policies.
+//!
+//! A listing names how its content may be played (`playback_policy`) and
+//! parameterizes it (`playback_params`).  The registry does not enforce
+//! playback; it makes sure the policy is one players understand, that its
+//! parameters fit it, and (through the creator signature over
+//! `conduit:listing:v2`) that nobody between creator and player changed
+//! them.
+//!
+//! | Policy | Meaning | Parameters |
+//! |--------|---------|------------|
+//! | `open` | Any player | none |
+//! | `tee_required` | Only in a TEE of a certified device | `allowed_manufacturers`, `max_devices`, `expires_at` |
+//! | `stream_only` | Play while streaming; no offline copy | `max_devices`, `expires_at` |
+//!
+//! `allowed_manufacturers` must also name registered manufacturers that are
+//! not revoked when the listing is published ([`check_manufacturers`]).
+//! `discover` returns their current records, so a buyer's device can tell
+//! whether it qualifies before paying.
+
+use std::fmt;
+
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db;
+use crate::limits::ALLOWED_MANUFACTURERS_MAX;
+use crate::types::PlaybackParams;
+use crate::versioning::parse_cutoff;
+
+/// Every known policy, with the parameters it takes.
+pub const PLAYBACK_POLICIES: &[(&str, &[&str])] = &[
+    ("open", &[]),
+    (
+        "tee_required",
+        &["allowed_manufacturers", "max_devices", "expires_at"],
+    ),
+    ("stream_only", &["max_devices", "expires_at"]),
+];
+
+/// Why a listing's playback policy was refused.
+#[derive(Debug)]
+pub enum PolicyError {
+    UnknownPolicy(String),
+    /// The policy does not take this parameter
+    UnexpectedParam(&'static str),
+    InvalidParam(&'static str, String),
+    /// An `allowed_manufacturers` entry that is not registered
+    UnknownManufacturer(String),
+    /// An `allowed_manufacturers` entry that has been revoked
+    RevokedManufacturer(String),
+    Db(rusqlite::Error),
+}
+
+impl PolicyError {
+    /// Machine-readable API error code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PolicyError::UnknownPolicy(_) => "unknown_policy",
+            PolicyError::UnexpectedParam(_) | PolicyError::InvalidParam(..) => {
+                "invalid_policy_params"
+            }
+            PolicyError::UnknownManufacturer(_) => "unknown_manufacturer",
+            PolicyError::RevokedManufacturer(_) => "manufacturer_revoked",
+            PolicyError::Db(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::UnknownPolicy(p) => {
+                let known: Vec<&str> = PLAYBACK_POLICIES.iter().map(|(name, _)| *name).collect();
+                write!(
+                    f,
+                    "Unknown playback_policy '{}': expected one of {}",
+                    p,
+                    known.join(", ")
+                )
+            }
+            PolicyError::UnexpectedParam(param) => {
+                write!(
+                    f,
+                    "playback_params.{} does not apply to this playback_policy",
+                    param
+                )
+            }
+            PolicyError::InvalidParam(param, why) => {
+                write!(f, "playback_params.{}: {}", param, why)
+            }
+            PolicyError::UnknownManufacturer(id) => {
+                write!(
+                    f,
+                    "playback_params.allowed_manufacturers: '{}' is not a registered manufacturer",
+                    id
+                )
+            }
+            PolicyError::RevokedManufacturer(id) => {
+                write!(
+                    f,
+                    "playback_params.allowed_manufacturers: '{}' has been revoked",
+                    id
+                )
+            }
+            PolicyError::Db(e) => write!(f, "database: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+impl From<rusqlite::Error> for PolicyError {
+    fn from(e: rusqlite::Error) -> Self {
+        PolicyError::Db(e)
+    }
+}
+
+fn is_pubkey(s: &str) -> bool {
+    hex::decode(s)
+        .ok()
+        .is_some_and(|b| b.len() == 33 && secp256k1::PublicKey::from_slice(&b).is_ok())
+}
+
+/// Check that `policy` is known and `params` are the ones it takes, each
+/// well-formed.
+pub fn validate(policy: &str, params: &PlaybackParams) -> Result<(), PolicyError> {
+    let Some((_, accepted)) = PLAYBACK_POLICIES.iter().find(|(name, _)| *name == policy) else {
+        return Err(PolicyError::UnknownPolicy(policy.into()));
+    };
+    let given = [
+        (
+            "allowed_manufacturers",
+            !params.allowed_manufacturers.is_empty(),
+        ),
+        ("max_devices", params.max_devices.is_some()),
+        ("expires_at", !params.expires_at.is_empty()),
+    ];
+    if let Some((param, _)) = given
+        .iter()
+        .find(|(param, set)| *set && !accepted.contains(param))
+    {
+        return Err(PolicyError::UnexpectedParam(param));
+    }
+
+    let ids = &params.allowed_manufacturers;
+    if ids.len() > ALLOWED_MANUFACTURERS_MAX {
+        let why = format!("at most {} entries", ALLOWED_MANUFACTURERS_MAX);
+        return Err(PolicyError::InvalidParam("allowed_manufacturers", why));
+    }
+    for (i, id) in ids.iter().enumerate() {
+        if !is_pubkey(id) {
+            let why = format!(
+                "'{}' is not a manufacturer id (compressed public key, hex)",
+                id
+            );
+            return Err(PolicyError::InvalidParam("allowed_manufacturers", why));
+        }
+        if ids[..i].contains(id) {
+            return Err(PolicyError::InvalidParam(
+                "allowed_manufacturers",
+                format!("'{}' is listed twice", id),
+            ));
+        }
+    }
+    if params.max_devices == Some(0) {
+        return Err(PolicyError::InvalidParam(
+            "max_devices",
+            "must be at least 1".into(),
+        ));
+    }
+    if !params.expires_at.is_empty() {
+        parse_cutoff(&params.expires_at).map_err(|e| PolicyError::InvalidParam("expires_at", e))?;
+    }
+    Ok(())
+}
+
+/// Check that every `allowed_manufacturers` entry is a registered,
+/// non-revoked manufacturer.
+pub fn check_manufacturers(conn: &Connection, params: &PlaybackParams) -> Result<(), PolicyError> {
+    for id in &params.allowed_manufacturers {
+        let manufacturer = db::get_manufacturer(conn, id)
+            .optional()?
+            .ok_or_else(|| PolicyError::UnknownManufacturer(id.clone()))?;
+        if manufacturer.revocation.is_some() {
+            return Err(PolicyError::RevokedManufacturer(id.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// The parameters as they appear in the signed listing message:
+/// `{allowed_manufacturers, comma-separated}:{max_devices}:{expires_at}`,
+/// unset fields empty.
+pub fn params_message(params: &PlaybackParams) -> String {
+    format!(
+        "{}:{}:{}",
+        params.allowed_manufacturers.join(","),
+        params
+            .max_devices
+            .map(|n| n.to_string())
+            .unwrap_or_default(),
+        params.expires_at
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_must_fit_the_policy() {
+        // The generator point: a valid compressed key
+        let acme = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".to_string();
+        let tee = PlaybackParams {
+            allowed_manufacturers: vec![acme.clone()],
+            max_devices: Some(3),
+            expires_at: "2027-01-01".into(),
+        };
+        assert!(validate("open", &PlaybackParams::default()).is_ok());
+        assert!(validate("tee_required", &tee).is_ok());
+        assert_eq!(params_message(&tee), format!("{}:3:2027-01-01", acme));
+
+        assert_eq!(validate("drm", &tee).unwrap_err().code(), "unknown_policy");
+        assert!(matches!(
+            validate("open", &tee),
+            Err(PolicyError::UnexpectedParam("allowed_manufacturers"))
+        ));
+        assert!(matches!(
+            validate("stream_only", &tee),
+            Err(PolicyError::UnexpectedParam("allowed_manufacturers"))
+        ));
+
+        let bad = |params: PlaybackParams| validate("tee_required", &params).unwrap_err();
+        let twice = vec![acme.clone(), acme.clone()];
+        assert!(matches!(
+            bad(PlaybackParams {
+                allowed_manufacturers: twice,
+                ..tee.clone()
+            }),
+            PolicyError::InvalidParam(..)
+        ));
+        let not_a_key = vec!["acme".into()];
+        assert!(matches!(
+            bad(PlaybackParams {
+                allowed_manufacturers: not_a_key,
+                ..tee.clone()
+            }),
+            PolicyError::InvalidParam(..)
+        ));
+        assert!(matches!(
+            bad(PlaybackParams {
+                max_devices: Some(0),
+                ..tee.clone()
+            }),
+            PolicyError::InvalidParam(..)
+        ));
+        assert!(matches!(
+            bad(PlaybackParams {
+                expires_at: "soon".into(),
+                ..tee
+            }),
+            PolicyError::InvalidParam(..)
+        ));
+    }
+}
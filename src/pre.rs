@@ -0,0 +1,275 @@
+//! Structural checks of a listing's proxy re-encryption (PRE) capsule.
+//!
+//! `pre_c1_hex`, `pre_c2_hex` and `pre_pk_creator_hex` are produced by the
+//! creator and only used after a buyer has paid, when a proxy re-encrypts
+//! the capsule for them.  The registry cannot check that the capsule
+//! decrypts, but it can refuse one that no implementation of the scheme
+//! could use: bad hex, wrong lengths, points off the curve.  The scheme in
+//! use is `registry.pre_scheme`; schemes implement [`PreScheme`] and are
+//! listed in [`SCHEMES`].
+//!
+//! The fields are optional, but a listing sets all three or none.  Buyer
+//! keys in the key directory (see `buyer_keys`) are checked the same way.
+
+use std::fmt;
+
+use bls12_381::G1Affine;
+
+use crate::types::ContentListing;
+
+/// Scheme used unless `registry.pre_scheme` says otherwise.
+pub const DEFAULT_PRE_SCHEME: &str = "afgh06-bls12-381";
+
+/// Every scheme the registry can check capsules for.
+pub const SCHEMES: &[&dyn PreScheme] = &[&Afgh06Bls12381];
+
+/// The scheme called `name`, if known.
+pub fn scheme(name: &str) -> Option<&'static dyn PreScheme> {
+    SCHEMES.iter().copied().find(|s| s.name() == name)
+}
+
+/// One of the PRE fields of a listing, or a buyer's key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreField {
+    C1,
+    C2,
+    PkCreator,
+    PkBuyer,
+}
+
+impl PreField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PreField::C1 => "pre_c1_hex",
+            PreField::C2 => "pre_c2_hex",
+            PreField::PkCreator => "pre_pk_creator_hex",
+            PreField::PkBuyer => "pre_pk_hex",
+        }
+    }
+}
+
+/// A proxy re-encryption scheme, as far as the registry needs to know it.
+pub trait PreScheme: Send + Sync {
+    /// Name as configured in `registry.pre_scheme`, e.g. `afgh06-bls12-381`
+    fn name(&self) -> &'static str;
+
+    /// Check the decoded bytes of one field.
+    fn check_field(&self, field: PreField, bytes: &[u8]) -> Result<(), CapsuleError>;
+}
+
+/// Why a listing's PRE capsule was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapsuleError {
+    /// Some of the PRE fields are set, but not all
+    Incomplete(PreField),
+    InvalidHex(PreField),
+    InvalidLength {
+        field: PreField,
+        expected: usize,
+        actual: usize,
+    },
+    /// Not an element of the group the scheme expects
+    InvalidPoint(PreField),
+}
+
+impl CapsuleError {
+    /// Machine-readable API error code.
+    pub fn code(&self) -> &'static str {
+        "invalid_pre_capsule"
+    }
+}
+
+impl fmt::Display for CapsuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CapsuleError::Incomplete(field) => {
+                write!(
+                    f,
+                    "{} is required when any PRE field is set",
+                    field.as_str()
+                )
+            }
+            CapsuleError::InvalidHex(field) => write!(f, "{} is not valid hex", field.as_str()),
+            CapsuleError::InvalidLength {
+                field,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "{} must be {} bytes, got {}",
+                    field.as_str(),
+                    expected,
+                    actual
+                )
+            }
+            CapsuleError::InvalidPoint(field) => {
+                write!(
+                    f,
+                    "{} is not a valid group element for this scheme",
+                    field.as_str()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CapsuleError {}
+
+/// Check the PRE fields of `listing` under `scheme`.  Listings without
+/// any PRE field pass.
+pub fn check_listing(scheme: &dyn PreScheme, listing: &ContentListing) -> Result<(), CapsuleError> {
+    let fields = [
+        (PreField::C1, &listing.pre_c1_hex),
+        (PreField::C2, &listing.pre_c2_hex),
+        (PreField::PkCreator, &listing.pre_pk_creator_hex),
+    ];
+    if fields.iter().all(|(_, hex)| hex.is_empty()) {
+        return Ok(());
+    }
+    if let Some((field, _)) = fields.iter().find(|(_, hex)| hex.is_empty()) {
+        return Err(CapsuleError::Incomplete(*field));
+    }
+    for (field, hex) in fields {
+        check_hex(scheme, field, hex)?;
+    }
+    Ok(())
+}
+
+/// Decode `hex` and check it as `field` under `scheme`.
+pub fn check_hex(scheme: &dyn PreScheme, field: PreField, hex: &str) -> Result<(), CapsuleError> {
+    let bytes = hex::decode(hex).map_err(|_| CapsuleError::InvalidHex(field))?;
+    scheme.check_field(field, &bytes)
+}
+
+fn expect_len(field: PreField, bytes: &[u8], expected: usize) -> Result<(), CapsuleError> {
+    if bytes.len() != expected {
+        return Err(CapsuleError::InvalidLength {
+            field,
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// AFGH06 (Ateniese, Fu, Green, Hohenberger) over BLS12-381, with the
+/// creator key `g^a` and capsule `(g^(ak), m * Z^k)`:
+///
+/// | Field | Group | Encoding |
+/// |-------|-------|----------|
+/// | `pre_pk_creator_hex`, buyer `pre_pk_hex` | G1 | compressed, 48 bytes |
+/// | `pre_c1_hex` | G1 | compressed, 48 bytes |
+/// | `pre_c2_hex` | GT | 12 base field elements, big-endian, 576 bytes |
+///
+/// G1 points must be on the curve, in the prime-order subgroup and not the
+/// identity.  GT elements can only be checked for canonical coordinates.
+pub struct Afgh06Bls12381;
+
+/// The BLS12-381 base field modulus, big-endian.
+const BLS12_381_P: [u8; 48] = [
+    0x1a, 0x01, 0x11, 0xea, 0x39, 0x7f, 0xe6, 0x9a, 0x4b, 0x1b, 0xa7, 0xb6, 0x43, 0x4b, 0xac, 0xd7,
+    0x64, 0x77, 0x4b, 0x84, 0xf3, 0x85, 0x12, 0xbf, 0x67, 0x30, 0xd2, 0xa0, 0xf6, 0xb0, 0xf6, 0x24,
+    0x1e, 0xab, 0xff, 0xfe, 0xb1, 0x53, 0xff, 0xff, 0xb9, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xaa, 0xab,
+];
+
+impl PreScheme for Afgh06Bls12381 {
+    fn name(&self) -> &'static str {
+        DEFAULT_PRE_SCHEME
+    }
+
+    fn check_field(&self, field: PreField, bytes: &[u8]) -> Result<(), CapsuleError> {
+        match field {
+            PreField::C1 | PreField::PkCreator | PreField::PkBuyer => {
+                expect_len(field, bytes, 48)?;
+                let compressed: &[u8; 48] = bytes.try_into().expect("length checked");
+                let point: Option<G1Affine> = G1Affine::from_compressed(compressed).into();
+                if point.is_none_or(|p| bool::from(p.is_identity())) {
+                    return Err(CapsuleError::InvalidPoint(field));
+                }
+            }
+            PreField::C2 => {
+                expect_len(field, bytes, 576)?;
+                if bytes.iter().all(|b| *b == 0) || bytes.chunks(48).any(|c| c >= &BLS12_381_P[..])
+                {
+                    return Err(CapsuleError::InvalidPoint(field));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compressed hex of the G1 point `n·G`, a valid key or capsule for tests.
+#[cfg(test)]
+pub(crate) fn g1(n: u64) -> String {
+    use bls12_381::{G1Projective, Scalar};
+    hex::encode(G1Affine::from(G1Projective::generator() * Scalar::from(n)).to_compressed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capsule_fields_must_be_group_elements() {
+        let mut listing: ContentListing = serde_json::from_value(serde_json::json!({
+            "content_hash": "c", "encrypted_hash": "e", "file_name": "f", "size_bytes": 1,
+            "price_sats": 1, "chunk_size": 1, "chunk_count": 1, "plaintext_root": "p",
+            "encrypted_root": "r", "creator_pubkey": "", "creator_address": "",
+            "creator_ln_address": "", "creator_alias": "", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        let afgh = scheme(DEFAULT_PRE_SCHEME).unwrap();
+        assert_eq!(check_listing(afgh, &listing), Ok(()));
+
+        listing.pre_pk_creator_hex = g1(7);
+        assert_eq!(
+            check_listing(afgh, &listing),
+            Err(CapsuleError::Incomplete(PreField::C1))
+        );
+        listing.pre_c1_hex = g1(21);
+        listing.pre_c2_hex = "01".repeat(576);
+        assert_eq!(check_listing(afgh, &listing), Ok(()));
+
+        let bad = |l: ContentListing| check_listing(afgh, &l).unwrap_err();
+        assert_eq!(
+            bad(ContentListing {
+                pre_c1_hex: "zz".into(),
+                ..listing.clone()
+            }),
+            CapsuleError::InvalidHex(PreField::C1)
+        );
+        assert!(matches!(
+            bad(ContentListing {
+                pre_c1_hex: "02".repeat(33),
+                ..listing.clone()
+            }),
+            CapsuleError::InvalidLength { .. }
+        ));
+        // x = 1 has no point on the curve; 0xc0... is the identity
+        let off_curve = format!("8{}1", "0".repeat(94));
+        assert_eq!(
+            bad(ContentListing {
+                pre_c1_hex: off_curve,
+                ..listing.clone()
+            }),
+            CapsuleError::InvalidPoint(PreField::C1)
+        );
+        let identity = format!("c{}", "0".repeat(95));
+        assert_eq!(
+            bad(ContentListing {
+                pre_pk_creator_hex: identity,
+                ..listing.clone()
+            }),
+            CapsuleError::InvalidPoint(PreField::PkCreator)
+        );
+        assert_eq!(
+            bad(ContentListing {
+                pre_c2_hex: "ff".repeat(576),
+                ..listing
+            }),
+            CapsuleError::InvalidPoint(PreField::C2)
+        );
+    }
+}
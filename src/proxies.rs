@@ -0,0 +1,288 @@
+//! Re-encryption proxy announcements.
+//!
+//! A listing's PRE capsule (`pre_c1_hex`, `pre_c2_hex`) has to be
+//! re-encrypted for each buyer, which third-party proxies offer for a fee.
+//! Proxies announce themselves with `POST /proxies`, signed by their node
+//! key, listing the schemes they support and how long the announcement
+//! should stay up (`ttl_secs`, at most [`MAX_TTL`]).  Announcements that
+//! are not renewed lapse: they drop out of `GET /proxies` and `discover`,
+//! and are deleted on the next announcement.  They are not exported, since
+//! any announcement expires within its TTL.
+
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db;
+use crate::limits::PROXY_SCHEMES_MAX;
+use crate::pre;
+use crate::signature::{is_compressed_pubkey, verify_proxy};
+use crate::types::{ContentListing, Page, ProxyAnnouncement};
+use crate::versioning::parse_cutoff;
+
+/// Longest an announcement may stay live without being renewed.
+pub const MAX_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// How far in the future a signed timestamp may be, for clock drift.
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(300);
+
+/// Proxies `discover` includes for a listing with a PRE capsule.
+pub const DISCOVER_PROXIES: u32 = 10;
+
+/// Why a proxy announcement was refused.
+#[derive(Debug)]
+pub enum ProxyError {
+    InvalidPubkey,
+    MissingAddress,
+    /// Contains `:`, which would make the signed message ambiguous
+    InvalidLnAddress,
+    InvalidSchemes(String),
+    InvalidTtl,
+    InvalidTimestamp,
+    /// Older than the announcement already stored
+    Stale,
+    InvalidSignature,
+    Db(rusqlite::Error),
+}
+
+impl ProxyError {
+    /// Machine-readable API error code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProxyError::InvalidPubkey => "invalid_pubkey",
+            ProxyError::MissingAddress => "address_required",
+            ProxyError::InvalidLnAddress => "invalid_ln_address",
+            ProxyError::InvalidSchemes(_) => "invalid_schemes",
+            ProxyError::InvalidTtl => "invalid_ttl",
+            ProxyError::InvalidTimestamp => "invalid_timestamp",
+            ProxyError::Stale => "stale_announcement",
+            ProxyError::InvalidSignature => "invalid_signature",
+            ProxyError::Db(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyError::InvalidPubkey => {
+                write!(f, "proxy_pubkey is not a compressed secp256k1 public key")
+            }
+            ProxyError::MissingAddress => write!(f, "proxy_address is required"),
+            ProxyError::InvalidLnAddress => write!(f, "proxy_ln_address must not contain ':'"),
+            ProxyError::InvalidSchemes(why) => write!(f, "schemes: {}", why),
+            ProxyError::InvalidTtl => {
+                write!(f, "ttl_secs must be between 1 and {}", MAX_TTL.as_secs())
+            }
+            ProxyError::InvalidTimestamp => {
+                write!(
+                    f,
+                    "announced_at must be an RFC 3339 timestamp within ttl_secs before now"
+                )
+            }
+            ProxyError::Stale => write!(
+                f,
+                "announced_at is older than the proxy's current announcement"
+            ),
+            ProxyError::InvalidSignature => {
+                write!(
+                    f,
+                    "Invalid signature: ECDSA verification failed against proxy_pubkey"
+                )
+            }
+            ProxyError::Db(e) => write!(f, "database: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProxyError {}
+
+impl From<rusqlite::Error> for ProxyError {
+    fn from(e: rusqlite::Error) -> Self {
+        ProxyError::Db(e)
+    }
+}
+
+/// `at` in the form stored in `proxies.expires_at`, so timestamps compare
+/// as strings.
+pub fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn check_schemes(schemes: &[String]) -> Result<(), ProxyError> {
+    if schemes.is_empty() || schemes.len() > PROXY_SCHEMES_MAX {
+        let why = format!("between 1 and {} entries", PROXY_SCHEMES_MAX);
+        return Err(ProxyError::InvalidSchemes(why));
+    }
+    for (i, scheme) in schemes.iter().enumerate() {
+        if pre::scheme(scheme).is_none() {
+            return Err(ProxyError::InvalidSchemes(format!(
+                "unknown scheme '{}'",
+                scheme
+            )));
+        }
+        if schemes[..i].contains(scheme) {
+            return Err(ProxyError::InvalidSchemes(format!(
+                "'{}' is listed twice",
+                scheme
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Store `ann` as the proxy's current announcement, as of `now`.
+pub fn announce(
+    conn: &Connection,
+    ann: &ProxyAnnouncement,
+    now: DateTime<Utc>,
+) -> Result<(), ProxyError> {
+    let expires_at = check(conn, ann, now)?;
+    Ok(store(conn, ann, expires_at, now)?)
+}
+
+/// Every check [`announce`] makes, signature last, without storing
+/// anything.  Returns when the announcement expires.
+pub fn check(
+    conn: &Connection,
+    ann: &ProxyAnnouncement,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, ProxyError> {
+    if !is_compressed_pubkey(&ann.proxy_pubkey) {
+        return Err(ProxyError::InvalidPubkey);
+    }
+    if ann.proxy_address.trim().is_empty() {
+        return Err(ProxyError::MissingAddress);
+    }
+    // `proxy_address` is `host:port`, so the signed message can only be split
+    // unambiguously if the field after it has no `:`
+    if ann.proxy_ln_address.contains(':') {
+        return Err(ProxyError::InvalidLnAddress);
+    }
+    check_schemes(&ann.schemes)?;
+    if ann.ttl_secs == 0 || ann.ttl_secs > MAX_TTL.as_secs() {
+        return Err(ProxyError::InvalidTtl);
+    }
+    let announced_at = parse_cutoff(&ann.announced_at).map_err(|_| ProxyError::InvalidTimestamp)?;
+    let expires_at = announced_at + Duration::from_secs(ann.ttl_secs);
+    if announced_at > now + MAX_CLOCK_SKEW || expires_at <= now {
+        return Err(ProxyError::InvalidTimestamp);
+    }
+    if let Some(current) = db::get_proxy(conn, &ann.proxy_pubkey).optional()? {
+        if parse_cutoff(&current.announced_at).is_ok_and(|at| at > announced_at) {
+            return Err(ProxyError::Stale);
+        }
+    }
+    if !verify_proxy(ann) {
+        return Err(ProxyError::InvalidSignature);
+    }
+    Ok(expires_at)
+}
+
+/// Store `ann`, which passed [`check`], dropping lapsed announcements.
+pub fn store(
+    conn: &Connection,
+    ann: &ProxyAnnouncement,
+    expires_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> rusqlite::Result<()> {
+    db::purge_expired_proxies(conn, &timestamp(now))?;
+    db::insert_proxy(conn, ann, &timestamp(expires_at))?;
+    Ok(())
+}
+
+/// Live proxies that can re-encrypt `listing`'s capsule under `scheme`,
+/// cheapest first; none if the listing has no PRE capsule.
+pub fn candidates(
+    conn: &Connection,
+    listing: &ContentListing,
+    scheme: &str,
+) -> rusqlite::Result<Vec<ProxyAnnouncement>> {
+    if listing.pre_c1_hex.is_empty() {
+        return Ok(Vec::new());
+    }
+    let page = Page {
+        limit: DISCOVER_PROXIES,
+        offset: 0,
+    };
+    db::live_proxies(conn, &timestamp(Utc::now()), Some(scheme), Some(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::pre::DEFAULT_PRE_SCHEME;
+    use crate::signature::{proxy_canonical_message, sign_proxy};
+    use secp256k1::SecretKey;
+
+    #[test]
+    fn announcements_lapse_after_their_ttl() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let key = SecretKey::from_slice(&[2; 32]).unwrap();
+        let now = parse_cutoff("2026-05-01T12:00:00Z").unwrap();
+        let mut ann = ProxyAnnouncement {
+            proxy_pubkey: String::new(),
+            proxy_address: "proxy.example:9735".into(),
+            proxy_ln_address: String::new(),
+            proxy_alias: "Proxy".into(),
+            fee_sats: 5,
+            schemes: vec![DEFAULT_PRE_SCHEME.into()],
+            ttl_secs: 600,
+            announced_at: "2026-05-01T12:00:00Z".into(),
+            signature: String::new(),
+        };
+        sign_proxy(&key, &mut ann);
+        announce(&conn, &ann, now).unwrap();
+
+        let live = |at: &str, scheme: Option<&str>| {
+            db::live_proxies(&conn, &timestamp(parse_cutoff(at).unwrap()), scheme, None).unwrap()
+        };
+        assert_eq!(
+            live("2026-05-01T12:09:59Z", Some(DEFAULT_PRE_SCHEME)),
+            [ann.clone()]
+        );
+        assert_eq!(live("2026-05-01T12:09:59Z", Some("umbral")), []);
+        assert_eq!(live("2026-05-01T12:10:00Z", None), []);
+
+        let mut tampered = ann.clone();
+        tampered.fee_sats = 1;
+        assert!(matches!(
+            announce(&conn, &tampered, now),
+            Err(ProxyError::InvalidSignature)
+        ));
+        // Moving the address boundary keeps the message, so it is refused
+        let shifted = ProxyAnnouncement {
+            proxy_address: "proxy.example".into(),
+            proxy_ln_address: "9735:".into(),
+            ..ann.clone()
+        };
+        assert_eq!(
+            proxy_canonical_message(&shifted),
+            proxy_canonical_message(&ann)
+        );
+        assert!(matches!(
+            announce(&conn, &shifted, now),
+            Err(ProxyError::InvalidLnAddress)
+        ));
+        let unknown = ProxyAnnouncement {
+            schemes: vec!["umbral".into()],
+            ..ann.clone()
+        };
+        assert_eq!(
+            announce(&conn, &unknown, now).unwrap_err().code(),
+            "invalid_schemes"
+        );
+        let future = ProxyAnnouncement {
+            announced_at: "2026-05-01T13:00:00Z".into(),
+            ..ann
+        };
+        assert!(matches!(
+            announce(&conn, &future, now),
+            Err(ProxyError::InvalidTimestamp)
+        ));
+    }
+}
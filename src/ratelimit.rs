@@ -0,0 +1,423 @@
+//! Token-bucket rate limiting for the Conduit Registry.
+//!
+//! Every request is charged against a bucket keyed by source IP, and the
+//! write handlers additionally charge a bucket keyed by the creator or
+//! seeder pubkey from the body.  Limits are configured per route class so
+//! that the signature-verifying routes (ECDSA recovery is the expensive
+//! part) can be throttled harder than plain reads.  Those routes charge
+//! the pubkey bucket only once the signature verifies, so requests forged
+//! in someone else's name cannot drain it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ErrorCode;
+use crate::types::AppState;
+
+/// Buckets are pruned once the table grows past this many keys...
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// ...but at most this often, so a table kept full of live buckets does not
+/// make every request scan it.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Hard cap on the table.  A new key arriving at the cap evicts an
+/// arbitrary bucket rather than growing it further.
+const MAX_BUCKETS: usize = 100_000;
+
+/// POST paths whose handlers verify a signature before storing anything.
+const VERIFY_PATHS: &[&str] = &[
+    "/listings",
+    "/manufacturers",
+    "/keys",
+    "/devices",
+    "/devices/verify",
+    "/attestations/verify",
+    "/buyer-keys",
+    "/proxies",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Read,
+    Write,
+    Verify,
+}
+
+impl RouteClass {
+    /// Classify a request by method and path.
+    pub fn of(method: &Method, path: &str) -> Self {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => RouteClass::Read,
+            Method::POST if VERIFY_PATHS.iter().any(|p| path.ends_with(p)) => RouteClass::Verify,
+            _ => RouteClass::Write,
+        }
+    }
+}
+
+/// A per-key allowance: `per_minute` sustained, up to `burst` at once.
+/// A `per_minute` of zero disables the limit.  Written as `"600,120"` in
+/// flags and config files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Limit {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl Limit {
+    const fn new(per_minute: u32, burst: u32) -> Self {
+        Limit { per_minute, burst }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+/// Parses `"<per_minute>"` or `"<per_minute>,<burst>"`.  Burst defaults to
+/// the per-minute rate.
+impl FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rate, burst) = match s.split_once(',') {
+            Some((r, b)) => (r, Some(b)),
+            None => (s, None),
+        };
+        let per_minute: u32 = rate
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid rate '{}': expected requests per minute", rate))?;
+        let burst = match burst {
+            Some(b) => b
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid burst '{}'", b))?,
+            None => per_minute,
+        };
+        Ok(Limit { per_minute, burst })
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.per_minute, self.burst)
+    }
+}
+
+impl TryFrom<String> for Limit {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Limit> for String {
+    fn from(l: Limit) -> Self {
+        l.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimits {
+    /// Per IP, for GET/HEAD/OPTIONS
+    pub read: Limit,
+    /// Per IP and per pubkey, for unsigned writes and admin routes
+    pub write: Limit,
+    /// Per IP and per pubkey, for signature-verifying routes
+    pub verify: Limit,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            read: Limit::new(600, 120),
+            write: Limit::new(60, 20),
+            verify: Limit::new(20, 10),
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn for_class(&self, class: RouteClass) -> Limit {
+        match class {
+            RouteClass::Read => self.read,
+            RouteClass::Write => self.write,
+            RouteClass::Verify => self.verify,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+struct Table {
+    buckets: HashMap<(RouteClass, String), Bucket>,
+    pruned: Option<Instant>,
+}
+
+impl Table {
+    /// Drop buckets that have refilled to capacity, which behave exactly
+    /// like missing ones.
+    fn prune(&mut self, limits: &RateLimits, now: Instant) {
+        self.buckets.retain(|(c, _), b| {
+            let l = limits.for_class(*c);
+            let elapsed = now.saturating_duration_since(b.updated).as_secs_f64();
+            b.tokens + elapsed * l.refill_per_sec() < l.burst.max(1) as f64
+        });
+        self.pruned = Some(now);
+    }
+}
+
+pub struct RateLimiter {
+    limits: RwLock<RateLimits>,
+    table: Mutex<Table>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        RateLimiter {
+            limits: RwLock::new(limits),
+            table: Mutex::new(Table {
+                buckets: HashMap::new(),
+                pruned: None,
+            }),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        *self.limits.read().unwrap()
+    }
+
+    /// Replace the limits (config reload).  Existing buckets keep their
+    /// tokens and are capped to the new burst on their next use.
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().unwrap() = limits;
+    }
+
+    /// Take one token from the bucket for `(class, key)`.
+    ///
+    /// Returns `Err(retry_after)` when the bucket is empty.
+    pub fn check(&self, class: RouteClass, key: &str) -> Result<(), Duration> {
+        self.check_at(class, key, Instant::now())
+    }
+
+    fn check_at(&self, class: RouteClass, key: &str, now: Instant) -> Result<(), Duration> {
+        let limits = self.limits();
+        let limit = limits.for_class(class);
+        if limit.per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = limit.burst.max(1) as f64;
+        let rate = limit.refill_per_sec();
+
+        let mut table = self.table.lock().unwrap();
+        let due = table
+            .pruned
+            .is_none_or(|at| now.saturating_duration_since(at) >= PRUNE_INTERVAL);
+        if table.buckets.len() > PRUNE_THRESHOLD && due {
+            table.prune(&limits, now);
+        }
+        let id = (class, key.to_string());
+        if table.buckets.len() >= MAX_BUCKETS && !table.buckets.contains_key(&id) {
+            let victim = table.buckets.keys().next().cloned();
+            if let Some(victim) = victim {
+                table.buckets.remove(&victim);
+            }
+        }
+
+        let bucket = table.buckets.entry(id).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+}
+
+/// 429 response carrying a `Retry-After` header (whole seconds, rounded up).
+pub struct RateLimited(pub Duration);
+
+impl IntoResponse for RateLimited {
+    fn into_response(self) -> Response {
+        let secs = self.0.as_secs_f64().ceil().max(1.0) as u64;
+        let mut res = (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, secs.to_string())],
+            Json(serde_json::json!({
+                "error": "Rate limit exceeded",
+                "retry_after_secs": secs,
+            })),
+        )
+            .into_response();
+        res.extensions_mut().insert(ErrorCode("rate_limited"));
+        res
+    }
+}
+
+/// Middleware: charge the request against the source IP's bucket for its
+/// route class.
+pub async fn limit_by_ip(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let class = RouteClass::of(req.method(), req.uri().path());
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    if let Err(retry_after) = state.limiter.check(class, &format!("ip:{}", ip)) {
+        eprintln!("Rate limited {:?} request from {}", class, ip);
+        return RateLimited(retry_after).into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lim(per_minute: u32, burst: u32) -> Limit {
+        Limit { per_minute, burst }
+    }
+
+    fn limiter(limit: Limit) -> RateLimiter {
+        RateLimiter::new(RateLimits {
+            read: limit,
+            write: limit,
+            verify: limit,
+        })
+    }
+
+    #[test]
+    fn parse_limit() {
+        assert_eq!("60".parse::<Limit>().unwrap(), lim(60, 60));
+        assert_eq!("60,5".parse::<Limit>().unwrap(), lim(60, 5));
+        assert!("fast".parse::<Limit>().is_err());
+        assert_eq!(lim(60, 5).to_string().parse::<Limit>().unwrap(), lim(60, 5));
+    }
+
+    #[test]
+    fn classify_routes() {
+        assert_eq!(
+            RouteClass::of(&Method::GET, "/api/listings"),
+            RouteClass::Read
+        );
+        assert_eq!(
+            RouteClass::of(&Method::POST, "/api/listings"),
+            RouteClass::Verify
+        );
+        assert_eq!(
+            RouteClass::of(&Method::POST, "/api/seeders"),
+            RouteClass::Write
+        );
+        assert_eq!(
+            RouteClass::of(&Method::DELETE, "/api/listings"),
+            RouteClass::Write
+        );
+    }
+
+    #[test]
+    fn burst_then_refill() {
+        let rl = limiter(lim(60, 2));
+        let t0 = Instant::now();
+        assert!(rl.check_at(RouteClass::Write, "k", t0).is_ok());
+        assert!(rl.check_at(RouteClass::Write, "k", t0).is_ok());
+        let retry = rl.check_at(RouteClass::Write, "k", t0).unwrap_err();
+        assert!(retry <= Duration::from_secs(1));
+
+        // Other keys and classes have their own buckets.
+        assert!(rl.check_at(RouteClass::Write, "other", t0).is_ok());
+        assert!(rl.check_at(RouteClass::Read, "k", t0).is_ok());
+
+        // One token per second at 60/min.
+        assert!(rl
+            .check_at(RouteClass::Write, "k", t0 + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn limits_can_be_replaced() {
+        let rl = limiter(lim(60, 1));
+        let t0 = Instant::now();
+        assert!(rl.check_at(RouteClass::Read, "k", t0).is_ok());
+        assert!(rl.check_at(RouteClass::Read, "k", t0).is_err());
+        rl.set_limits(RateLimits {
+            read: lim(0, 0),
+            ..rl.limits()
+        });
+        assert!(rl.check_at(RouteClass::Read, "k", t0).is_ok());
+    }
+
+    #[test]
+    fn pruning_is_spaced_out_and_the_table_capped() {
+        let rl = limiter(lim(60, 1));
+        let t0 = Instant::now();
+        for i in 0..=PRUNE_THRESHOLD {
+            rl.check_at(RouteClass::Write, &i.to_string(), t0).unwrap();
+        }
+        // Every bucket is drained, so pruning keeps them all; the next
+        // prune waits for the interval even though the table is still big
+        let t1 = t0 + Duration::from_millis(10);
+        rl.check_at(RouteClass::Write, "a", t1).unwrap();
+        assert_eq!(rl.table.lock().unwrap().pruned, Some(t1));
+        rl.check_at(RouteClass::Write, "b", t1 + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(rl.table.lock().unwrap().pruned, Some(t1));
+
+        // Refilled buckets go at the next due prune
+        let t2 = t1 + PRUNE_INTERVAL;
+        rl.check_at(RouteClass::Write, "c", t2).unwrap();
+        assert_eq!(rl.table.lock().unwrap().buckets.len(), 1);
+
+        let table = Table {
+            buckets: (0..MAX_BUCKETS)
+                .map(|i| {
+                    let bucket = Bucket {
+                        tokens: 0.0,
+                        updated: t2,
+                    };
+                    ((RouteClass::Write, i.to_string()), bucket)
+                })
+                .collect(),
+            pruned: Some(t2),
+        };
+        *rl.table.lock().unwrap() = table;
+        rl.check_at(RouteClass::Write, "new", t2).unwrap();
+        assert_eq!(rl.table.lock().unwrap().buckets.len(), MAX_BUCKETS);
+    }
+
+    #[test]
+    fn zero_rate_is_unlimited() {
+        let rl = limiter(lim(0, 0));
+        let t0 = Instant::now();
+        for _ in 0..100 {
+            assert!(rl.check_at(RouteClass::Verify, "k", t0).is_ok());
+        }
+    }
+}
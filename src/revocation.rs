@@ -0,0 +1,126 @@
+//! Manufacturer key revocation.
+//!
+//! Manufacturers are never deleted: `DELETE /manufacturers/{pk_hex}` marks
+//! the key revoked with a statement signed by the registry signing key
+//! (`registry.signing_key`), so history survives and devices that cached
+//! the key can find out.  `GET /api/v2/manufacturers/revocations` serves
+//! every statement as a [`RevocationList`] signed afresh on each request.
+//! Its `sequence` is the number of revocations, which only grows because a
+//! revoked key cannot be registered again.
+//!
+//! Every entry must be signed by the list's issuer, so the signing key only
+//! changes on a restart, which re-signs the stored statements under the new
+//! key ([`resign`]) before serving; a `SIGHUP` keeps the old key.
+
+use rusqlite::Connection;
+use secp256k1::SecretKey;
+
+use crate::db;
+use crate::signature::{pubkey_hex, sign_revocation, sign_revocation_list, verify_revocation};
+use crate::types::{Revocation, RevocationList};
+
+/// Revoke `pk_hex` now.  `Ok(None)` if there is no such manufacturer or it
+/// is already revoked.
+pub fn revoke(
+    conn: &Connection,
+    signing_key: &SecretKey,
+    pk_hex: &str,
+    reason: &str,
+) -> rusqlite::Result<Option<Revocation>> {
+    let mut rev = Revocation {
+        pk_hex: pk_hex.into(),
+        revoked_at: chrono::Utc::now().to_rfc3339(),
+        reason: reason.into(),
+        signature: String::new(),
+    };
+    sign_revocation(signing_key, &mut rev);
+    Ok((db::revoke_manufacturer(conn, &rev)? > 0).then_some(rev))
+}
+
+/// The current revocation list, signed with `signing_key`.
+pub fn list(conn: &Connection, signing_key: &SecretKey) -> rusqlite::Result<RevocationList> {
+    let entries = db::revocations(conn)?;
+    let mut list = RevocationList {
+        issuer: String::new(),
+        sequence: entries.len() as u64,
+        issued_at: chrono::Utc::now().to_rfc3339(),
+        entries,
+        signature: String::new(),
+    };
+    sign_revocation_list(signing_key, &mut list);
+    Ok(list)
+}
+
+/// Re-sign every stored revocation that `signing_key` did not sign, after
+/// a key rollover.  Returns how many were re-signed.
+pub fn resign(conn: &Connection, signing_key: &SecretKey) -> rusqlite::Result<usize> {
+    let issuer = pubkey_hex(signing_key);
+    let mut resigned = 0;
+    for mut rev in db::revocations(conn)? {
+        if !verify_revocation(&rev, &issuer) {
+            sign_revocation(signing_key, &mut rev);
+            resigned += db::resign_revocation(conn, &rev)?;
+        }
+    }
+    Ok(resigned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::signature::verify_revocation_list;
+    use crate::types::Manufacturer;
+
+    fn manufacturer(pk_hex: &str) -> Manufacturer {
+        serde_json::from_value(serde_json::json!({
+            "pk_hex": pk_hex, "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn revocations_are_signed_and_kept() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn);
+        let registry = SecretKey::from_slice(&[8; 32]).unwrap();
+        for pk in ["aa", "bb"] {
+            db::insert_manufacturer(&conn, &manufacturer(pk)).unwrap();
+        }
+
+        let rev = revoke(&conn, &registry, "aa", "key compromised")
+            .unwrap()
+            .unwrap();
+        assert!(revoke(&conn, &registry, "aa", "again").unwrap().is_none());
+        assert!(revoke(&conn, &registry, "cc", "unknown").unwrap().is_none());
+
+        // The row stays, carrying its revocation
+        let mfr = db::get_manufacturer(&conn, "aa").unwrap();
+        assert_eq!(mfr.revocation, Some(rev.clone()));
+        assert!(db::get_manufacturer(&conn, "bb")
+            .unwrap()
+            .revocation
+            .is_none());
+
+        let mut list = list(&conn, &registry).unwrap();
+        assert_eq!(
+            (list.sequence, list.issuer.clone()),
+            (1, pubkey_hex(&registry))
+        );
+        assert_eq!(list.entries, std::slice::from_ref(&rev));
+        assert!(verify_revocation_list(&list));
+
+        // Dropping an entry breaks the list signature
+        list.entries.clear();
+        assert!(!verify_revocation_list(&list));
+
+        // After a rollover every entry verifies under the new key
+        let next = SecretKey::from_slice(&[9; 32]).unwrap();
+        assert_eq!(resign(&conn, &next).unwrap(), 1);
+        assert_eq!(resign(&conn, &next).unwrap(), 0);
+        let list = super::list(&conn, &next).unwrap();
+        assert_eq!(list.entries[0].revoked_at, rev.revoked_at);
+        assert!(verify_revocation(&list.entries[0], &pubkey_hex(&next)));
+        assert!(verify_revocation_list(&list));
+    }
+}
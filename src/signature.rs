@@ -1,22 +1,50 @@
-//! Lightning-standard message signature verification (standalone, no ldk-node).
+//! Lightning-standard message signing and verification (standalone, no ldk-node).
 //!
-//! Replicates the verification half of `lightning::util::message_signing`
-//! using only `secp256k1` and `sha2`.  The signature format is:
+//! Replicates `lightning::util::message_signing` using only `secp256k1` and
+//! `sha2`, so tests, tooling and non-LDK clients can produce the same
+//! signatures a Conduit node does.  The signature format is:
 //!
 //!   1. Message digest: SHA256(SHA256("Lightning Signed Message:" || msg))
 //!   2. Signature: zbase32-encoded 65-byte recoverable ECDSA signature
 //!      byte[0] = recovery_id + 31, bytes[1..65] = compact (r, s)
 
 use secp256k1::ecdsa::RecoverableSignature;
-use secp256k1::{Message, Secp256k1};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey, SECP256K1};
 use sha2::{Digest, Sha256};
 
+use crate::policy::params_message;
+use crate::types::{
+    Attestation, AttestationToken, BuyerKey, ContentListing, Device, Manufacturer, ManufacturerKey,
+    ProxyAnnouncement, Revocation, RevocationList,
+};
+
 // -----------------------------------------------------------------------
-// zbase32 decode (RFC 6189 human-oriented encoding)
+// zbase32 encode / decode (RFC 6189 human-oriented encoding)
 // -----------------------------------------------------------------------
 
 const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
 
+fn zbase32_encode(data: &[u8]) -> String {
+    let mut bits: u64 = 0;
+    let mut num_bits: u32 = 0;
+    let mut output = String::with_capacity(data.len() * 8 / 5 + 1);
+
+    for &byte in data {
+        bits = (bits << 8) | byte as u64;
+        num_bits += 8;
+        while num_bits >= 5 {
+            num_bits -= 5;
+            output.push(ZBASE32_ALPHABET[((bits >> num_bits) & 31) as usize] as char);
+        }
+        bits &= (1u64 << num_bits) - 1;
+    }
+    // Pad the final partial group with zero bits
+    if num_bits > 0 {
+        output.push(ZBASE32_ALPHABET[((bits << (5 - num_bits)) & 31) as usize] as char);
+    }
+    output
+}
+
 fn zbase32_decode(input: &str) -> Option<Vec<u8>> {
     let mut lookup = [255u8; 128];
     for (i, &ch) in ZBASE32_ALPHABET.iter().enumerate() {
@@ -57,7 +85,7 @@ fn lightning_message_hash(msg: &[u8]) -> [u8; 32] {
     let first = h1.finalize();
 
     let mut h2 = Sha256::new();
-    h2.update(&first);
+    h2.update(first);
     let second = h2.finalize();
 
     let mut out = [0u8; 32];
@@ -69,6 +97,394 @@ fn lightning_message_hash(msg: &[u8]) -> [u8; 32] {
 // Public API
 // -----------------------------------------------------------------------
 
+/// Sign `msg` the way `lightning::util::message_signing::sign` does.
+///
+/// Returns the zbase32-encoded recoverable signature, ready for
+/// `creator_signature` and accepted by [`verify_lightning_signature`].
+pub fn sign_lightning_message(secret_key: &SecretKey, msg: &[u8]) -> String {
+    let message = Message::from_digest(lightning_message_hash(msg));
+    let sig = SECP256K1.sign_ecdsa_recoverable(&message, secret_key);
+    let (recovery_id, compact) = sig.serialize_compact();
+
+    let mut bytes = [0u8; 65];
+    bytes[0] = recovery_id.to_i32() as u8 + 31;
+    bytes[1..].copy_from_slice(&compact);
+    zbase32_encode(&bytes)
+}
+
+/// Hex-encoded compressed public key for `secret_key`, the form used for
+/// `creator_pubkey` and friends.
+pub fn pubkey_hex(secret_key: &SecretKey) -> String {
+    hex::encode(PublicKey::from_secret_key(SECP256K1, secret_key).serialize())
+}
+
+/// Whether `key` is the hex of a 33-byte compressed secp256k1 public key.
+pub fn is_compressed_pubkey(key: &str) -> bool {
+    hex::decode(key)
+        .ok()
+        .is_some_and(|b| b.len() == 33 && PublicKey::from_slice(&b).is_ok())
+}
+
+/// The first 16 characters of `key`, for log lines.  Counts characters
+/// rather than bytes, so unvalidated input cannot panic the slice.
+pub fn abbrev(key: &str) -> String {
+    key.chars().take(16).collect()
+}
+
+/// Signature scheme name for `sign_lightning_message` signatures.
+pub const LIGHTNING_SIGNATURE_SCHEME: &str = "lightning";
+
+/// Prefix and version of the original listing message, which does not
+/// cover the playback policy.
+pub const LISTING_MESSAGE_V1: &str = "conduit:listing:v1";
+
+/// Prefix and version of the listing canonical message.
+pub const LISTING_MESSAGE_V2: &str = "conduit:listing:v2";
+
+/// The canonical message a creator signs for a listing:
+/// `conduit:listing:v2:{content_hash}:{encrypted_hash}:{encrypted_root}:{price_sats}:{creator_pubkey}:{playback_policy}:{params}`
+/// with `params` as in `policy::params_message`.
+pub fn listing_canonical_message(listing: &ContentListing) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}:{}:{}",
+        LISTING_MESSAGE_V2,
+        listing.content_hash,
+        listing.encrypted_hash,
+        listing.encrypted_root,
+        listing.price_sats,
+        listing.creator_pubkey,
+        listing.playback_policy,
+        params_message(&listing.playback_params)
+    )
+}
+
+/// `conduit:listing:v1:{content_hash}:{encrypted_hash}:{encrypted_root}:{price_sats}:{creator_pubkey}`
+pub fn listing_canonical_message_v1(listing: &ContentListing) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}",
+        LISTING_MESSAGE_V1,
+        listing.content_hash,
+        listing.encrypted_hash,
+        listing.encrypted_root,
+        listing.price_sats,
+        listing.creator_pubkey
+    )
+}
+
+/// Set `creator_pubkey` from `secret_key` and fill in `creator_signature`
+/// over the canonical listing message.
+pub fn sign_listing(secret_key: &SecretKey, listing: &mut ContentListing) {
+    listing.creator_pubkey = pubkey_hex(secret_key);
+    listing.creator_signature =
+        sign_lightning_message(secret_key, listing_canonical_message(listing).as_bytes());
+}
+
+/// Whether `listing.creator_signature` is valid for its canonical message.
+/// `open` listings without playback parameters may still be signed over the
+/// v1 message, as before policies were signed; v1 does not cover the policy,
+/// so any other policy needs a v2 signature.
+pub fn verify_listing(listing: &ContentListing) -> bool {
+    let verify = |msg: String| {
+        verify_lightning_signature(
+            msg.as_bytes(),
+            &listing.creator_signature,
+            &listing.creator_pubkey,
+        )
+    };
+    let v1_allowed = listing.playback_policy == "open" && listing.playback_params.is_empty();
+    verify(listing_canonical_message(listing))
+        || (v1_allowed && verify(listing_canonical_message_v1(listing)))
+}
+
+/// Prefix and version of the manufacturer registration message.
+pub const MANUFACTURER_MESSAGE_V1: &str = "conduit:manufacturer:v1";
+
+/// The canonical message a manufacturer key signs to prove possession when
+/// registering: `conduit:manufacturer:v1:{pk_hex}:{name}:{registered_at}`
+pub fn manufacturer_canonical_message(mfr: &Manufacturer) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        MANUFACTURER_MESSAGE_V1, mfr.pk_hex, mfr.name, mfr.registered_at
+    )
+}
+
+/// Set `pk_hex` from `secret_key` and fill in `signature` over the
+/// canonical manufacturer message.
+pub fn sign_manufacturer(secret_key: &SecretKey, mfr: &mut Manufacturer) {
+    mfr.pk_hex = pubkey_hex(secret_key);
+    mfr.signature =
+        sign_lightning_message(secret_key, manufacturer_canonical_message(mfr).as_bytes());
+}
+
+/// Whether `mfr.signature` is valid for its canonical message, i.e. the
+/// registrant holds the secret key for `pk_hex`.
+pub fn verify_manufacturer(mfr: &Manufacturer) -> bool {
+    verify_lightning_signature(
+        manufacturer_canonical_message(mfr).as_bytes(),
+        &mfr.signature,
+        &mfr.pk_hex,
+    )
+}
+
+/// Prefix and version of the key rotation message.
+pub const ROTATION_MESSAGE_V1: &str = "conduit:rotation:v1";
+
+/// The canonical message a current manufacturer key signs to add a key:
+/// `conduit:rotation:v1:{manufacturer_id}:{pk_hex}:{not_before}:{not_after}`
+pub fn rotation_canonical_message(key: &ManufacturerKey) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        ROTATION_MESSAGE_V1, key.manufacturer_id, key.pk_hex, key.not_before, key.not_after
+    )
+}
+
+/// Set `signed_by` from `secret_key` (a current key) and fill in
+/// `signature` over the canonical rotation message.
+pub fn sign_rotation(secret_key: &SecretKey, key: &mut ManufacturerKey) {
+    key.signed_by = pubkey_hex(secret_key);
+    key.signature = sign_lightning_message(secret_key, rotation_canonical_message(key).as_bytes());
+}
+
+/// Whether `key.signature` is valid for its canonical message under
+/// `key.signed_by`.  Whether that signer was entitled to add the key is
+/// checked by `keys::rotate`.
+pub fn verify_rotation(key: &ManufacturerKey) -> bool {
+    verify_lightning_signature(
+        rotation_canonical_message(key).as_bytes(),
+        &key.signature,
+        &key.signed_by,
+    )
+}
+
+/// Prefix and version of the buyer key directory message.
+pub const BUYER_KEY_MESSAGE_V1: &str = "conduit:buyer_key:v1";
+
+/// The canonical message a buyer's node signs to publish a PRE key:
+/// `conduit:buyer_key:v1:{node_pubkey}:{scheme}:{pre_pk_hex}:{published_at}`
+pub fn buyer_key_canonical_message(key: &BuyerKey) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        BUYER_KEY_MESSAGE_V1, key.node_pubkey, key.scheme, key.pre_pk_hex, key.published_at
+    )
+}
+
+/// Set `node_pubkey` from `secret_key` and fill in `signature` over the
+/// canonical buyer key message.
+pub fn sign_buyer_key(secret_key: &SecretKey, key: &mut BuyerKey) {
+    key.node_pubkey = pubkey_hex(secret_key);
+    key.signature = sign_lightning_message(secret_key, buyer_key_canonical_message(key).as_bytes());
+}
+
+/// Whether `key.signature` is valid for its canonical message under
+/// `key.node_pubkey`.
+pub fn verify_buyer_key(key: &BuyerKey) -> bool {
+    verify_lightning_signature(
+        buyer_key_canonical_message(key).as_bytes(),
+        &key.signature,
+        &key.node_pubkey,
+    )
+}
+
+/// Prefix and version of the proxy announcement message.
+pub const PROXY_MESSAGE_V1: &str = "conduit:proxy:v1";
+
+/// The canonical message a re-encryption proxy signs to announce itself:
+/// `conduit:proxy:v1:{proxy_pubkey}:{proxy_address}:{proxy_ln_address}:{fee_sats}:{schemes}:{ttl_secs}:{announced_at}`
+/// with `schemes` comma-separated.  `proxy_address` contains `:` itself, so
+/// `proxies::check` refuses a `proxy_ln_address` with one.
+pub fn proxy_canonical_message(ann: &ProxyAnnouncement) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}:{}:{}",
+        PROXY_MESSAGE_V1,
+        ann.proxy_pubkey,
+        ann.proxy_address,
+        ann.proxy_ln_address,
+        ann.fee_sats,
+        ann.schemes.join(","),
+        ann.ttl_secs,
+        ann.announced_at
+    )
+}
+
+/// Set `proxy_pubkey` from `secret_key` and fill in `signature` over the
+/// canonical proxy message.
+pub fn sign_proxy(secret_key: &SecretKey, ann: &mut ProxyAnnouncement) {
+    ann.proxy_pubkey = pubkey_hex(secret_key);
+    ann.signature = sign_lightning_message(secret_key, proxy_canonical_message(ann).as_bytes());
+}
+
+/// Whether `ann.signature` is valid for its canonical message under
+/// `ann.proxy_pubkey`.
+pub fn verify_proxy(ann: &ProxyAnnouncement) -> bool {
+    verify_lightning_signature(
+        proxy_canonical_message(ann).as_bytes(),
+        &ann.signature,
+        &ann.proxy_pubkey,
+    )
+}
+
+/// Prefix and version of the device certificate message.
+pub const DEVICE_MESSAGE_V1: &str = "conduit:device:v1";
+
+/// The canonical message a manufacturer signs to certify a device key:
+/// `conduit:device:v1:{device_pk}:{manufacturer_pk}:{model}:{issued_at}`
+pub fn device_canonical_message(device: &Device) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        DEVICE_MESSAGE_V1, device.device_pk, device.manufacturer_pk, device.model, device.issued_at
+    )
+}
+
+/// Set `manufacturer_pk` from `secret_key` and fill in
+/// `manufacturer_signature` over the canonical device message.
+pub fn sign_device(secret_key: &SecretKey, device: &mut Device) {
+    device.manufacturer_pk = pubkey_hex(secret_key);
+    device.manufacturer_signature =
+        sign_lightning_message(secret_key, device_canonical_message(device).as_bytes());
+}
+
+/// Whether `device.manufacturer_signature` is valid for its canonical
+/// message.  This checks the signature only; see `devices::verify_chain`
+/// for the check against registered manufacturers.
+pub fn verify_device(device: &Device) -> bool {
+    verify_lightning_signature(
+        device_canonical_message(device).as_bytes(),
+        &device.manufacturer_signature,
+        &device.manufacturer_pk,
+    )
+}
+
+/// Prefix and version of the message a device signs to answer a challenge.
+pub const ATTESTATION_MESSAGE_V1: &str = "conduit:attestation:v1";
+
+/// Prefix and version of the registry's attestation token.
+pub const ATTESTATION_TOKEN_MESSAGE_V1: &str = "conduit:attestation_token:v1";
+
+/// The canonical message a device signs with its key:
+/// `conduit:attestation:v1:{device_pk}:{challenge}`
+pub fn attestation_canonical_message(att: &Attestation) -> String {
+    format!(
+        "{}:{}:{}",
+        ATTESTATION_MESSAGE_V1, att.device.device_pk, att.challenge
+    )
+}
+
+/// Fill in `challenge_signature` with the device secret key.
+pub fn sign_attestation(device_key: &SecretKey, att: &mut Attestation) {
+    att.challenge_signature =
+        sign_lightning_message(device_key, attestation_canonical_message(att).as_bytes());
+}
+
+/// Whether `att.challenge_signature` is valid under `att.device.device_pk`.
+/// The device certificate itself is checked by `devices::verify_chain`.
+pub fn verify_attestation(att: &Attestation) -> bool {
+    verify_lightning_signature(
+        attestation_canonical_message(att).as_bytes(),
+        &att.challenge_signature,
+        &att.device.device_pk,
+    )
+}
+
+/// `conduit:attestation_token:v1:{issuer}:{device_pk}:{manufacturer_id}:{challenge}:{issued_at}:{expires_at}`
+pub fn attestation_token_message(token: &AttestationToken) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}:{}",
+        ATTESTATION_TOKEN_MESSAGE_V1,
+        token.issuer,
+        token.device_pk,
+        token.manufacturer_id,
+        token.challenge,
+        token.issued_at,
+        token.expires_at
+    )
+}
+
+/// Set `issuer` from the registry signing key and sign the token.
+pub fn sign_attestation_token(signing_key: &SecretKey, token: &mut AttestationToken) {
+    token.issuer = pubkey_hex(signing_key);
+    token.signature =
+        sign_lightning_message(signing_key, attestation_token_message(token).as_bytes());
+}
+
+/// Whether the token signature is valid under its `issuer`.  Callers must
+/// still check `issuer` and `expires_at`.
+pub fn verify_attestation_token(token: &AttestationToken) -> bool {
+    verify_lightning_signature(
+        attestation_token_message(token).as_bytes(),
+        &token.signature,
+        &token.issuer,
+    )
+}
+
+/// Prefix and version of a single revocation statement.
+pub const REVOCATION_MESSAGE_V1: &str = "conduit:revocation:v1";
+
+/// Prefix and version of the revocation list message.
+pub const REVOCATION_LIST_MESSAGE_V1: &str = "conduit:revocations:v1";
+
+/// `conduit:revocation:v1:{pk_hex}:{revoked_at}:{reason}`
+pub fn revocation_canonical_message(rev: &Revocation) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        REVOCATION_MESSAGE_V1, rev.pk_hex, rev.revoked_at, rev.reason
+    )
+}
+
+/// `conduit:revocations:v1:{issuer}:{sequence}:{issued_at}:{digest}`, where
+/// `digest` commits to every entry in order.
+pub fn revocation_list_message(list: &RevocationList) -> String {
+    let mut hasher = Sha256::new();
+    for (i, entry) in list.entries.iter().enumerate() {
+        if i > 0 {
+            hasher.update(b"\n");
+        }
+        hasher.update(revocation_canonical_message(entry).as_bytes());
+    }
+    format!(
+        "{}:{}:{}:{}:{}",
+        REVOCATION_LIST_MESSAGE_V1,
+        list.issuer,
+        list.sequence,
+        list.issued_at,
+        hex::encode(hasher.finalize())
+    )
+}
+
+/// Fill in `rev.signature` with the registry signing key.
+pub fn sign_revocation(secret_key: &SecretKey, rev: &mut Revocation) {
+    rev.signature =
+        sign_lightning_message(secret_key, revocation_canonical_message(rev).as_bytes());
+}
+
+/// Whether `rev.signature` is valid for its statement and made by `issuer`
+/// (the registry signing key's public key, hex).
+pub fn verify_revocation(rev: &Revocation, issuer: &str) -> bool {
+    verify_lightning_signature(
+        revocation_canonical_message(rev).as_bytes(),
+        &rev.signature,
+        issuer,
+    )
+}
+
+/// Set `list.issuer` from `secret_key` and fill in `list.signature`.
+pub fn sign_revocation_list(secret_key: &SecretKey, list: &mut RevocationList) {
+    list.issuer = pubkey_hex(secret_key);
+    list.signature = sign_lightning_message(secret_key, revocation_list_message(list).as_bytes());
+}
+
+/// Whether the list and every entry in it are signed by `list.issuer`.
+/// Callers must still check `issuer` against the operator key they trust.
+pub fn verify_revocation_list(list: &RevocationList) -> bool {
+    verify_lightning_signature(
+        revocation_list_message(list).as_bytes(),
+        &list.signature,
+        &list.issuer,
+    ) && list
+        .entries
+        .iter()
+        .all(|rev| verify_revocation(rev, &list.issuer))
+}
+
 /// Verify a Lightning-standard ECDSA recoverable signature.
 ///
 /// Returns `true` if the recovered public key matches `expected_pubkey_hex`
@@ -108,9 +524,7 @@ pub fn verify_lightning_signature(
     };
 
     let digest = lightning_message_hash(msg);
-    let message = match Message::from_digest(digest) {
-        msg => msg,
-    };
+    let message = Message::from_digest(digest);
 
     let secp = Secp256k1::verification_only();
     let recovered_pk = match secp.recover_ecdsa(&message, &sig) {
@@ -125,8 +539,8 @@ pub fn verify_lightning_signature(
     if recovered_hex != expected_pubkey_hex {
         eprintln!(
             "verify_lightning_signature: pubkey mismatch: recovered={} expected={}",
-            &recovered_hex[..16],
-            &expected_pubkey_hex[..16.min(expected_pubkey_hex.len())]
+            abbrev(&recovered_hex),
+            abbrev(expected_pubkey_hex)
         );
         return false;
     }
@@ -145,6 +559,20 @@ mod tests {
         assert_eq!(decoded, vec![0]);
     }
 
+    #[test]
+    fn zbase32_encode_decode_roundtrip() {
+        for data in [
+            &[][..],
+            &[0],
+            &[0xff, 0x01],
+            &[0xde, 0xad, 0xbe, 0xef, 0x42],
+        ] {
+            assert_eq!(zbase32_decode(&zbase32_encode(data)).unwrap(), data);
+        }
+        let sig = [0x1fu8; 65];
+        assert_eq!(zbase32_decode(&zbase32_encode(&sig)).unwrap(), sig);
+    }
+
     #[test]
     fn bad_zbase32_returns_none() {
         assert!(zbase32_decode("!!!").is_none());
@@ -160,8 +588,133 @@ mod tests {
         h1.update(b"test");
         let first = h1.finalize();
         let mut h2 = Sha256::new();
-        h2.update(&first);
+        h2.update(first);
         let expected = h2.finalize();
         assert_eq!(hash, expected.as_slice());
     }
+
+    // Vector from the `lightning::util::message_signing` tests: the secret
+    // key is the scalar 1, whose public key is the secp256k1 generator.
+    const LDK_SIG: &str = "d9tibmnic9t5y41hg7hkakdcra94akas9ku3rmmj4ag9mritc8ok4p5qzefs78c9pqfhpuftqqzhydbdwfg7u6w6wdxcqpqn4sj4e73e";
+    const GENERATOR_HEX: &str =
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn one_key() -> SecretKey {
+        let mut k = [0u8; 32];
+        k[31] = 1;
+        SecretKey::from_slice(&k).unwrap()
+    }
+
+    #[test]
+    fn ldk_sign_vector() {
+        assert_eq!(pubkey_hex(&one_key()), GENERATOR_HEX);
+        assert_eq!(sign_lightning_message(&one_key(), b"test message"), LDK_SIG);
+    }
+
+    #[test]
+    fn ldk_verify_vector() {
+        assert!(verify_lightning_signature(
+            b"test message",
+            LDK_SIG,
+            GENERATOR_HEX
+        ));
+        assert!(!verify_lightning_signature(
+            b"test message!",
+            LDK_SIG,
+            GENERATOR_HEX
+        ));
+    }
+
+    #[test]
+    fn signed_listing_verifies() {
+        let sk = SecretKey::from_slice(&[7; 32]).unwrap();
+        let mut listing: ContentListing = serde_json::from_value(serde_json::json!({
+            "content_hash": "aa", "encrypted_hash": "bb", "file_name": "f.mp4",
+            "size_bytes": 1, "price_sats": 100, "chunk_size": 1, "chunk_count": 1,
+            "plaintext_root": "cc", "encrypted_root": "dd", "creator_pubkey": "",
+            "creator_address": "", "creator_ln_address": "", "creator_alias": "",
+            "registered_at": ""
+        }))
+        .unwrap();
+        sign_listing(&sk, &mut listing);
+        assert_eq!(listing.creator_pubkey, pubkey_hex(&sk));
+        assert!(verify_listing(&listing));
+
+        listing.price_sats = 1;
+        assert!(!verify_listing(&listing));
+    }
+
+    #[test]
+    fn listing_signature_covers_playback_policy() {
+        let sk = SecretKey::from_slice(&[7; 32]).unwrap();
+        let mut listing: ContentListing = serde_json::from_value(serde_json::json!({
+            "content_hash": "aa", "encrypted_hash": "bb", "file_name": "f.mp4",
+            "size_bytes": 1, "price_sats": 100, "chunk_size": 1, "chunk_count": 1,
+            "plaintext_root": "cc", "encrypted_root": "dd", "creator_pubkey": "",
+            "creator_address": "", "creator_ln_address": "", "creator_alias": "",
+            "registered_at": "", "playback_policy": "tee_required",
+            "playback_params": { "max_devices": 2 }
+        }))
+        .unwrap();
+        sign_listing(&sk, &mut listing);
+        assert!(verify_listing(&listing));
+        let mut loosened = listing.clone();
+        loosened.playback_params.max_devices = Some(5);
+        assert!(!verify_listing(&loosened));
+
+        // v1 signatures stay valid, but only for open listings without parameters
+        listing.creator_signature =
+            sign_lightning_message(&sk, listing_canonical_message_v1(&listing).as_bytes());
+        assert!(!verify_listing(&listing));
+        listing.playback_params = Default::default();
+        assert!(!verify_listing(&listing));
+        listing.playback_policy = "open".into();
+        assert!(verify_listing(&listing));
+    }
+
+    #[test]
+    fn v1_signature_does_not_cover_a_changed_policy() {
+        let sk = SecretKey::from_slice(&[7; 32]).unwrap();
+        let mut listing: ContentListing = serde_json::from_value(serde_json::json!({
+            "content_hash": "aa", "encrypted_hash": "bb", "file_name": "f.mp4",
+            "size_bytes": 1, "price_sats": 100, "chunk_size": 1, "chunk_count": 1,
+            "plaintext_root": "cc", "encrypted_root": "dd", "creator_pubkey": pubkey_hex(&sk),
+            "creator_address": "", "creator_ln_address": "", "creator_alias": "",
+            "registered_at": ""
+        }))
+        .unwrap();
+        listing.creator_signature =
+            sign_lightning_message(&sk, listing_canonical_message_v1(&listing).as_bytes());
+        assert!(verify_listing(&listing));
+        for policy in ["tee_required", "stream_only"] {
+            let flipped = ContentListing {
+                playback_policy: policy.into(),
+                ..listing.clone()
+            };
+            assert!(
+                !verify_listing(&flipped),
+                "{} accepted under a v1 signature",
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn signed_manufacturer_verifies() {
+        let sk = SecretKey::from_slice(&[9; 32]).unwrap();
+        let mut mfr: Manufacturer = serde_json::from_value(serde_json::json!({
+            "pk_hex": "", "name": "Acme", "registered_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        sign_manufacturer(&sk, &mut mfr);
+        assert_eq!(mfr.pk_hex, pubkey_hex(&sk));
+        assert!(verify_manufacturer(&mfr));
+
+        // Someone else's key, or a changed name, does not verify
+        let mut claimed = mfr.clone();
+        claimed.pk_hex = GENERATOR_HEX.into();
+        assert!(!verify_manufacturer(&claimed));
+        mfr.name = "Acme Corp".into();
+        assert!(!verify_manufacturer(&mfr));
+    }
 }
@@ -0,0 +1,351 @@
+//! Online SQLite snapshots.
+//!
+//! Copying `registry.sqlite` while the server writes to it can catch a
+//! half-applied transaction.  Snapshots instead go through SQLite's online
+//! backup API, which copies a consistent image page by page, into
+//! `registry-<UTC timestamp>.sqlite` files in the snapshot directory.  The
+//! copy reads through its own read-only connection and pauses between
+//! steps, so requests keep being served while it runs.  A
+//! snapshot is written under a `.partial` name and renamed once complete,
+//! so the directory only ever holds whole files.  Only the newest `keep`
+//! snapshots are retained.
+//!
+//! `restore` refuses a snapshot that fails `PRAGMA integrity_check` or lacks
+//! any table or column of the current schema.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Utc;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::db::schema_gap;
+
+const PREFIX: &str = "registry-";
+const SUFFIX: &str = ".sqlite";
+
+/// Pages copied per backup step, and the pause that lets writers in between.
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+
+/// Where snapshots go and how many to keep.
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    pub dir: PathBuf,
+    /// Time between periodic snapshots; `None` for on-demand only
+    pub interval: Option<Duration>,
+    /// Number of snapshots retained; older ones are deleted after each snapshot
+    pub keep: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SnapshotInfo {
+    /// Path of the snapshot file on the server
+    pub path: String,
+    pub bytes: u64,
+    pub taken_at: String,
+    /// Number of older snapshots deleted by the retention policy
+    pub pruned: usize,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Db(rusqlite::Error),
+    /// The file is not a usable registry database.
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::Db(e) => write!(f, "database: {}", e),
+            SnapshotError::Invalid(why) => write!(f, "invalid snapshot: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for SnapshotError {
+    fn from(e: rusqlite::Error) -> Self {
+        SnapshotError::Db(e)
+    }
+}
+
+/// Parse an interval such as `90s`, `30m`, `6h` or `1d` (bare numbers are seconds).
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let invalid = || format!("invalid interval '{}': expected e.g. 30m, 6h, 1d", s);
+    let n: u64 = num.parse().map_err(|_| invalid())?;
+    let per_unit = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => {
+            return Err(format!(
+                "invalid interval unit '{}': use s, m, h or d",
+                unit
+            ))
+        }
+    };
+    let secs = n.checked_mul(per_unit).ok_or_else(invalid)?;
+    if secs == 0 {
+        return Err("interval must be positive".into());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// The inverse of `parse_interval`, in the largest whole unit.
+pub fn format_interval(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Snapshot files in `dir`, oldest first.
+pub fn list_snapshots(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(PREFIX) && n.ends_with(SUFFIX))
+        })
+        .collect();
+    // Timestamps in the names sort chronologically
+    files.sort();
+    Ok(files)
+}
+
+/// Delete all but the newest `keep` snapshots; returns how many were removed.
+pub fn prune(dir: &Path, keep: usize) -> io::Result<usize> {
+    let files = list_snapshots(dir)?;
+    let excess = files.len().saturating_sub(keep);
+    for old in &files[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(excess)
+}
+
+/// Take a snapshot of the database at `db_path` into `cfg.dir`, then apply
+/// retention.
+pub fn take(db_path: &Path, cfg: &SnapshotConfig) -> Result<SnapshotInfo, SnapshotError> {
+    fs::create_dir_all(&cfg.dir)?;
+    let now = Utc::now();
+    let name = format!("{}{}{}", PREFIX, now.format("%Y%m%dT%H%M%S%.3fZ"), SUFFIX);
+    let path = cfg.dir.join(&name);
+    let partial = cfg.dir.join(format!("{}.partial", name));
+
+    if let Err(e) = copy(db_path, &partial) {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    fs::rename(&partial, &path)?;
+
+    let bytes = fs::metadata(&path)?.len();
+    let pruned = prune(&cfg.dir, cfg.keep.max(1))?;
+    Ok(SnapshotInfo {
+        path: path.display().to_string(),
+        bytes,
+        taken_at: now.to_rfc3339(),
+        pruned,
+    })
+}
+
+fn copy(db_path: &Path, dest: &Path) -> rusqlite::Result<()> {
+    let src = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut dst = Connection::open(dest)?;
+    let backup = Backup::new(&src, &mut dst)?;
+    backup.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)
+}
+
+/// Take snapshots of `db_path` every `cfg.interval` for the life of the
+/// process.
+pub async fn run_periodic(db_path: PathBuf, cfg: SnapshotConfig) {
+    let Some(interval) = cfg.interval else {
+        return;
+    };
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick fires immediately; skip it so startup is not slowed.
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let (db_path, cfg) = (db_path.clone(), cfg.clone());
+        let result = tokio::task::spawn_blocking(move || take(&db_path, &cfg)).await;
+        match result {
+            Ok(Ok(info)) => println!("Snapshot written: {} ({} bytes)", info.path, info.bytes),
+            Ok(Err(e)) => eprintln!("Snapshot failed: {}", e),
+            Err(e) => eprintln!("Snapshot task panicked: {}", e),
+        }
+    }
+}
+
+/// Check that `path` is an intact database with the current schema.
+/// Returns the number of listings it holds.
+pub fn validate(path: &Path) -> Result<u64, SnapshotError> {
+    let snap = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let integrity: String = snap
+        .query_row("PRAGMA integrity_check", [], |r| r.get(0))
+        .map_err(|e| SnapshotError::Invalid(e.to_string()))?;
+    if integrity != "ok" {
+        return Err(SnapshotError::Invalid(format!(
+            "integrity check: {}",
+            integrity
+        )));
+    }
+
+    if let Some(gap) = schema_gap(&snap)? {
+        return Err(SnapshotError::Invalid(gap));
+    }
+
+    Ok(snap.query_row("SELECT COUNT(*) FROM listings", [], |r| r.get(0))?)
+}
+
+/// What `restore` did.
+#[derive(Debug)]
+pub struct Restored {
+    /// Listings in the restored database
+    pub listings: u64,
+    /// Where the replaced database was kept, if there was one
+    pub previous: Option<PathBuf>,
+}
+
+/// Replace the database at `db_path` with `snapshot`, after validating it.
+/// The current database is kept as `<db_path>.pre-restore-<timestamp>`.
+/// The server must not be running.
+pub fn restore(snapshot: &Path, db_path: &Path) -> Result<Restored, SnapshotError> {
+    let listings = validate(snapshot)?;
+
+    let staged = db_path.with_extension("restoring");
+    fs::copy(snapshot, &staged)?;
+
+    let previous = if db_path.exists() {
+        let mut name = db_path.as_os_str().to_owned();
+        name.push(format!(
+            ".pre-restore-{}",
+            Utc::now().format("%Y%m%dT%H%M%SZ")
+        ));
+        let previous = PathBuf::from(name);
+        fs::rename(db_path, &previous)?;
+        Some(previous)
+    } else {
+        None
+    };
+    fs::rename(&staged, db_path)?;
+    Ok(Restored { listings, previous })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    fn tmpdir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("conduit-snap-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn db_with_listing(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        init_db(&conn);
+        conn.execute(
+            "INSERT INTO listings (content_hash, encrypted_hash, file_name, size_bytes, price_sats,
+             creator_pubkey, creator_address, creator_ln_address, registered_at)
+             VALUES ('a', 'enc-a', 'f', 1, 1, 'pk', '', '', '')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(parse_interval("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86400));
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("6w").is_err());
+        assert!(parse_interval(&format!("{}d", u64::MAX / 60)).is_err());
+        assert_eq!(format_interval(Duration::from_secs(90)), "90s");
+        assert_eq!(format_interval(Duration::from_secs(7200)), "2h");
+    }
+
+    #[test]
+    fn snapshot_prune_and_restore() {
+        let dir = tmpdir("roundtrip");
+        let cfg = SnapshotConfig {
+            dir: dir.join("snaps"),
+            interval: None,
+            keep: 2,
+        };
+        let live = dir.join("live.sqlite");
+        // The server's connection stays open while snapshots are taken
+        let _conn = db_with_listing(&live);
+        let mut taken = Vec::new();
+        for _ in 0..3 {
+            taken.push(take(&live, &cfg).unwrap());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(taken[2].pruned, 1);
+        let left = list_snapshots(&cfg.dir).unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(left[1], PathBuf::from(&taken[2].path));
+
+        let db_path = dir.join("registry.sqlite");
+        fs::write(&db_path, b"old").unwrap();
+        let restored = restore(&left[1], &db_path).unwrap();
+        assert_eq!(restored.listings, 1);
+        assert_eq!(fs::read(restored.previous.unwrap()).unwrap(), b"old");
+        assert_eq!(validate(&db_path).unwrap(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restore_rejects_foreign_schema() {
+        let dir = tmpdir("foreign");
+        let bogus = dir.join("bogus.sqlite");
+        // Current schema, minus one column
+        let conn = Connection::open(&bogus).unwrap();
+        init_db(&conn);
+        conn.execute_batch("ALTER TABLE listings DROP COLUMN creator_alias;")
+            .unwrap();
+        drop(conn);
+        let db_path = dir.join("registry.sqlite");
+        match restore(&bogus, &db_path) {
+            Err(SnapshotError::Invalid(why)) => {
+                assert_eq!(why, "table listings lacks column creator_alias")
+            }
+            other => panic!("expected invalid snapshot, got {:?}", other),
+        }
+        assert!(!db_path.exists());
+
+        fs::write(&bogus, b"not a database").unwrap();
+        assert!(restore(&bogus, &db_path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
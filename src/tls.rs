@@ -0,0 +1,375 @@
+//! Native HTTPS with rustls.
+//!
+//! The certificate chain and key are read from PEM files and served through
+//! a [`CertStore`], which swaps in new files without dropping connections:
+//! [`watch`] polls the files' modification times, and the binary also
+//! re-reads them on SIGHUP.  A failed reload keeps the previous certificate.
+//!
+//! [`TlsListener`] plugs into `axum::serve`; handshakes run on their own
+//! tasks so a slow client cannot hold up the accept loop.  [`redirect_app`]
+//! answers plain HTTP with a permanent redirect to the HTTPS port.
+
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::extract::Request;
+use axum::http::{header, uri::Authority, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use rustls::crypto::ring::{default_provider, sign::any_supported_type};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+/// How often [`watch`] checks the certificate files for changes.
+pub const CERT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Connections that have not finished the handshake by then are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting for `axum::serve` to pick them up.
+const ACCEPT_BACKLOG: usize = 64;
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(PathBuf, io::Error),
+    /// The file holds no usable certificate or key.
+    Pem(PathBuf, String),
+    Rustls(rustls::Error),
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Io(path, e) => write!(f, "reading {}: {}", path.display(), e),
+            TlsError::Pem(path, why) => write!(f, "{}: {}", path.display(), why),
+            TlsError::Rustls(e) => write!(f, "TLS: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+impl From<rustls::Error> for TlsError {
+    fn from(e: rustls::Error) -> Self {
+        TlsError::Rustls(e)
+    }
+}
+
+fn modified(path: &Path) -> Result<SystemTime, TlsError> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| TlsError::Io(path.into(), e))
+}
+
+/// Read a PEM certificate chain and private key.
+pub fn load_certified_key(cert: &Path, key: &Path) -> Result<CertifiedKey, TlsError> {
+    let pem_err = |path: &Path, e: rustls::pki_types::pem::Error| match e {
+        rustls::pki_types::pem::Error::Io(e) => TlsError::Io(path.into(), e),
+        other => TlsError::Pem(path.into(), other.to_string()),
+    };
+    let chain = CertificateDer::pem_file_iter(cert)
+        .map_err(|e| pem_err(cert, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| pem_err(cert, e))?;
+    if chain.is_empty() {
+        return Err(TlsError::Pem(cert.into(), "no certificates found".into()));
+    }
+    let key_der = PrivateKeyDer::from_pem_file(key).map_err(|e| pem_err(key, e))?;
+    let signing_key = any_supported_type(&key_der)?;
+    let certified = CertifiedKey::new(chain, signing_key);
+    certified.keys_match()?;
+    Ok(certified)
+}
+
+/// The served certificate, reloadable from its files.
+#[derive(Debug)]
+pub struct CertStore {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// Modification times of the files last loaded
+    loaded: Mutex<(SystemTime, SystemTime)>,
+}
+
+impl CertStore {
+    pub fn load(cert_path: PathBuf, key_path: PathBuf) -> Result<Arc<Self>, TlsError> {
+        let stamps = (modified(&cert_path)?, modified(&key_path)?);
+        let key = load_certified_key(&cert_path, &key_path)?;
+        Ok(Arc::new(CertStore {
+            cert_path,
+            key_path,
+            current: RwLock::new(Arc::new(key)),
+            loaded: Mutex::new(stamps),
+        }))
+    }
+
+    /// The certificate chain currently served.
+    pub fn chain(&self) -> Vec<CertificateDer<'static>> {
+        self.current.read().unwrap().cert.clone()
+    }
+
+    /// Reload if either file changed since the last load.  Returns whether
+    /// a new certificate is now served; on error the old one stays.
+    pub fn reload_if_changed(&self) -> Result<bool, TlsError> {
+        let stamps = (modified(&self.cert_path)?, modified(&self.key_path)?);
+        let mut loaded = self.loaded.lock().unwrap();
+        if *loaded == stamps {
+            return Ok(false);
+        }
+        let key = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self.current.write().unwrap() = Arc::new(key);
+        *loaded = stamps;
+        Ok(true)
+    }
+
+    /// A rustls server configuration that always serves the current certificate.
+    pub fn server_config(self: &Arc<Self>) -> Result<Arc<ServerConfig>, TlsError> {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+impl ResolvesServerCert for CertStore {
+    fn resolve(&self, _hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+/// Reload the certificate whenever its files change, for the life of the process.
+pub async fn watch(store: Arc<CertStore>, every: Duration) {
+    let mut ticker = tokio::time::interval(every);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        match store.reload_if_changed() {
+            Ok(true) => println!(
+                "TLS certificate reloaded from {}",
+                store.cert_path.display()
+            ),
+            Ok(false) => {}
+            Err(e) => eprintln!(
+                "TLS certificate reload failed, keeping the current one: {}",
+                e
+            ),
+        }
+    }
+}
+
+/// Accepts TCP connections and yields them once the TLS handshake is done.
+pub struct TlsListener {
+    ready: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(tcp: TcpListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let local_addr = tcp.local_addr()?;
+        let (tx, ready) = mpsc::channel(ACCEPT_BACKLOG);
+        let acceptor = TlsAcceptor::from(config);
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match tcp.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        // Typically out of file descriptors; back off
+                        eprintln!("accept error: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                if tx.is_closed() {
+                    return;
+                }
+                let (acceptor, tx) = (acceptor.clone(), tx.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(tls)) => {
+                            let _ = tx.send((tls, peer)).await;
+                        }
+                        Ok(Err(e)) => eprintln!("TLS handshake with {} failed: {}", peer, e),
+                        Err(_) => eprintln!("TLS handshake with {} timed out", peer),
+                    }
+                });
+            }
+        });
+        Ok(TlsListener { ready, local_addr })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.ready.recv().await {
+            Some(conn) => conn,
+            // The accept task only exits once this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Plain-HTTP app that sends every request to the same path on `https_port`.
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(move |req: Request| async move { https_redirect(req, https_port) })
+}
+
+fn https_redirect(req: Request, https_port: u16) -> Response {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<Authority>().ok());
+    let Some(host) = host else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header; use https://").into_response();
+    };
+    let authority = match https_port {
+        443 => host.host().to_string(),
+        port => format!("{}:{}", host.host(), port),
+    };
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::serve::ListenerExt;
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, RootCertStore};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsConnector;
+
+    fn tmpdir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("conduit-tls-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write a fresh self-signed certificate for `localhost`; returns its DER.
+    fn self_signed(dir: &Path) -> CertificateDer<'static> {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+        cert.cert.der().clone()
+    }
+
+    /// GET `/` over TLS, trusting only `trusted`; returns the served
+    /// certificate and the response head.
+    async fn https_get(
+        addr: SocketAddr,
+        trusted: &CertificateDer<'static>,
+    ) -> (CertificateDer<'static>, String) {
+        let mut roots = RootCertStore::empty();
+        roots.add(trusted.clone()).unwrap();
+        let config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut tls = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+            .unwrap();
+        let served = tls.get_ref().1.peer_certificates().unwrap()[0].clone();
+        tls.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        tls.read_to_string(&mut response).await.unwrap();
+        (served, response)
+    }
+
+    #[tokio::test]
+    async fn serves_https_and_reloads_certificate() {
+        let dir = tmpdir("reload");
+        let first = self_signed(&dir);
+        let store = CertStore::load(dir.join("cert.pem"), dir.join("key.pem")).unwrap();
+
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp.local_addr().unwrap();
+        let listener = TlsListener::new(tcp, store.server_config().unwrap()).unwrap();
+        let app = Router::new().route("/", get(|| async { "hello" }));
+        tokio::spawn(async move { axum::serve(listener.tap_io(|_| {}), app).await.unwrap() });
+
+        let (served, response) = https_get(addr, &first).await;
+        assert_eq!(served, first);
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("hello"));
+        assert!(
+            !store.reload_if_changed().unwrap(),
+            "unchanged files are not reloaded"
+        );
+
+        // Mangled files are rejected and the old certificate stays
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(dir.join("cert.pem"), "not a certificate").unwrap();
+        assert!(store.reload_if_changed().is_err());
+        assert_eq!(store.chain(), vec![first.clone()]);
+
+        let second = self_signed(&dir);
+        assert!(store.reload_if_changed().unwrap());
+        let (served, _) = https_get(addr, &second).await;
+        assert_eq!(served, second);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_mismatched_key() {
+        let dir = tmpdir("mismatch");
+        self_signed(&dir);
+        let other = rcgen::KeyPair::generate().unwrap();
+        std::fs::write(dir.join("key.pem"), other.serialize_pem()).unwrap();
+        let err = CertStore::load(dir.join("cert.pem"), dir.join("key.pem")).unwrap_err();
+        assert!(matches!(err, TlsError::Rustls(_)), "{:?}", err);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn redirects_to_https_port() {
+        let req = |uri: &str, host: &str| {
+            Request::builder()
+                .uri(uri)
+                .header(header::HOST, host)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let location = |res: Response| {
+            res.headers()[header::LOCATION]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let res = https_redirect(req("/api/v2/search?q=x", "registry.example:80"), 8443);
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            location(res),
+            "https://registry.example:8443/api/v2/search?q=x"
+        );
+        let res = https_redirect(req("/", "registry.example"), 443);
+        assert_eq!(location(res), "https://registry.example/");
+    }
+}
@@ -1,40 +1,94 @@
 //! Data types for the Conduit Registry API.
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
 
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::config::Config;
+use crate::export::OnInvalid;
+use crate::limits::{
+    max_len, ADDRESS_MAX, ALIAS_MAX, DEFAULT_PAGE_SIZE, DESCRIPTION_MAX, FILE_NAME_MAX, HASH_MAX,
+    MAX_PAGE_SIZE, PRE_HEX_MAX, PUBKEY_MAX, SHORT_MAX, SIGNATURE_MAX,
+};
+use crate::ratelimit::RateLimiter;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Arc<Mutex<Connection>>,
+    pub limiter: Arc<RateLimiter>,
+    /// The effective configuration; replaced in part on reload.
+    pub config: Arc<RwLock<Config>>,
+    pub started_at: Instant,
+}
+
+impl AppState {
+    pub fn new(conn: Connection, config: Config) -> Self {
+        AppState {
+            db: Arc::new(Mutex::new(conn)),
+            limiter: Arc::new(RateLimiter::new(config.rate_limits)),
+            config: Arc::new(RwLock::new(config)),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// A snapshot of the current configuration.
+    pub fn config(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Apply the reloadable parts of `new`; see [`Config::reload`].
+    pub fn reload(&self, new: Config) -> Vec<&'static str> {
+        let mut config = self.config.write().unwrap();
+        let pending = config.reload(new);
+        self.limiter.set_limits(config.rate_limits);
+        pending
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ContentListing {
+    #[serde(deserialize_with = "max_len::<_, HASH_MAX>")]
     pub content_hash: String,
+    #[serde(deserialize_with = "max_len::<_, HASH_MAX>")]
     pub encrypted_hash: String,
+    #[serde(deserialize_with = "max_len::<_, FILE_NAME_MAX>")]
     pub file_name: String,
     pub size_bytes: u64,
     pub price_sats: u64,
     pub chunk_size: u64,
     pub chunk_count: u64,
+    #[serde(deserialize_with = "max_len::<_, HASH_MAX>")]
     pub plaintext_root: String,
+    #[serde(deserialize_with = "max_len::<_, HASH_MAX>")]
     pub encrypted_root: String,
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
     pub creator_pubkey: String,
+    #[serde(deserialize_with = "max_len::<_, ADDRESS_MAX>")]
     pub creator_address: String,
+    #[serde(deserialize_with = "max_len::<_, ADDRESS_MAX>")]
     pub creator_ln_address: String,
+    #[serde(deserialize_with = "max_len::<_, ALIAS_MAX>")]
     pub creator_alias: String,
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
     pub registered_at: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "max_len::<_, PRE_HEX_MAX>")]
     pub pre_c1_hex: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "max_len::<_, PRE_HEX_MAX>")]
     pub pre_c2_hex: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "max_len::<_, PRE_HEX_MAX>")]
     pub pre_pk_creator_hex: String,
-    #[serde(default = "default_playback_policy")]
+    /// One of `policy::PLAYBACK_POLICIES`
+    #[serde(
+        default = "default_playback_policy",
+        deserialize_with = "max_len::<_, SHORT_MAX>"
+    )]
     pub playback_policy: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "PlaybackParams::is_empty")]
+    pub playback_params: PlaybackParams,
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
     pub creator_signature: String,
 }
 
@@ -42,40 +96,483 @@ pub fn default_playback_policy() -> String {
     "open".to_string()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Parameters of a listing's `playback_policy`, for the player to enforce.
+/// Which ones a policy takes is checked by `policy::validate`; all are
+/// covered by the creator signature.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlaybackParams {
+    /// Manufacturer ids whose devices may play the content; empty for any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_manufacturers: Vec<String>,
+    /// How many devices one purchase may be played on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_devices: Option<u32>,
+    /// RFC 3339; playback is not allowed after this
+    #[serde(
+        default,
+        skip_serializing_if = "String::is_empty",
+        deserialize_with = "max_len::<_, SHORT_MAX>"
+    )]
+    pub expires_at: String,
+}
+
+impl PlaybackParams {
+    pub fn is_empty(&self) -> bool {
+        *self == PlaybackParams::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct SeederAnnouncement {
+    #[serde(deserialize_with = "max_len::<_, HASH_MAX>")]
     pub encrypted_hash: String,
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
     pub seeder_pubkey: String,
+    #[serde(deserialize_with = "max_len::<_, ADDRESS_MAX>")]
     pub seeder_address: String,
+    #[serde(deserialize_with = "max_len::<_, ADDRESS_MAX>")]
     pub seeder_ln_address: String,
+    #[serde(deserialize_with = "max_len::<_, ALIAS_MAX>")]
     pub seeder_alias: String,
     pub transport_price: u64,
     pub chunk_count: u64,
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub announced_at: String,
+}
+
+/// A proxy re-encryption node offering to re-key listing capsules for
+/// buyers.  Signed by the proxy's node key; it lapses `ttl_secs` after
+/// `announced_at` unless announced again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProxyAnnouncement {
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub proxy_pubkey: String,
+    #[serde(deserialize_with = "max_len::<_, ADDRESS_MAX>")]
+    pub proxy_address: String,
+    #[serde(default, deserialize_with = "max_len::<_, ADDRESS_MAX>")]
+    pub proxy_ln_address: String,
+    #[serde(default, deserialize_with = "max_len::<_, ALIAS_MAX>")]
+    pub proxy_alias: String,
+    /// Fee per re-encryption, in sats
+    pub fee_sats: u64,
+    /// PRE schemes the proxy can re-encrypt, from `pre::SCHEMES`
+    pub schemes: Vec<String>,
+    /// Seconds after `announced_at` the announcement stays live
+    pub ttl_secs: u64,
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
     pub announced_at: String,
+    /// Lightning signature by `proxy_pubkey` over
+    /// `conduit:proxy:v1:{proxy_pubkey}:{proxy_address}:{proxy_ln_address}:{fee_sats}:{schemes}:{ttl_secs}:{announced_at}`
+    /// with `schemes` comma-separated
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub signature: String,
+}
+
+/// `?scheme=` on `GET /proxies`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct ProxyParams {
+    /// Only proxies supporting this PRE scheme
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Manufacturer {
+    /// Stable id: the key the manufacturer registered with.  Set by the
+    /// registry; it does not change when keys rotate
+    #[serde(default)]
+    pub id: String,
+    /// The registration key; see `ManufacturerKey` for the keys in use
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
     pub pk_hex: String,
+    #[serde(deserialize_with = "max_len::<_, ALIAS_MAX>")]
     pub name: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "max_len::<_, DESCRIPTION_MAX>")]
     pub description: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "max_len::<_, ADDRESS_MAX>")]
     pub website: String,
-    #[serde(default)]
+    /// Covered by `signature`, so the registrant must set it
+    #[serde(default, deserialize_with = "max_len::<_, SHORT_MAX>")]
     pub registered_at: String,
+    /// Lightning signature by the `pk_hex` key over
+    /// `conduit:manufacturer:v1:{pk_hex}:{name}:{registered_at}`
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub signature: String,
+    /// Set by the registry when the key is revoked; ignored on registration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation: Option<Revocation>,
+}
+
+/// One of a manufacturer's keys and when it may sign.
+///
+/// The registration key is added with `not_before = registered_at`; later
+/// keys are added by rotation, signed by a key valid at the time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ManufacturerKey {
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub manufacturer_id: String,
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub pk_hex: String,
+    /// RFC 3339; the key is valid from this instant
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub not_before: String,
+    /// RFC 3339; the key is valid until this instant.  Empty for no end
+    #[serde(default, deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub not_after: String,
+    /// Set by the registry when a later rotation retired this key; the key
+    /// is not valid from then on
+    #[serde(
+        default,
+        skip_serializing_if = "String::is_empty",
+        deserialize_with = "max_len::<_, SHORT_MAX>"
+    )]
+    pub retired_at: String,
+    /// The key that authorized this one; empty for the registration key
+    #[serde(default, deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub signed_by: String,
+    /// Lightning signature by `signed_by` over
+    /// `conduit:rotation:v1:{manufacturer_id}:{pk_hex}:{not_before}:{not_after}`
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub signature: String,
+}
+
+/// A buyer's proxy re-encryption public key, published under their
+/// Lightning node key so creators can re-key capsules for them.
+///
+/// A node has one current key; publishing a newer one supersedes it, and
+/// superseded keys stay in the node's history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BuyerKey {
+    /// The buyer's Lightning node public key (33-byte compressed, hex)
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub node_pubkey: String,
+    /// One of `pre::SCHEMES`, e.g. `afgh06-bls12-381`
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub scheme: String,
+    /// PRE public key, hex, encoded as the scheme requires
+    #[serde(deserialize_with = "max_len::<_, PRE_HEX_MAX>")]
+    pub pre_pk_hex: String,
+    /// RFC 3339; must be later than the node's current key
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub published_at: String,
+    /// Set by the registry when a newer key replaced this one
+    #[serde(
+        default,
+        skip_serializing_if = "String::is_empty",
+        deserialize_with = "max_len::<_, SHORT_MAX>"
+    )]
+    pub superseded_at: String,
+    /// Lightning signature by `node_pubkey` over
+    /// `conduit:buyer_key:v1:{node_pubkey}:{scheme}:{pre_pk_hex}:{published_at}`
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub signature: String,
+}
+
+/// `?at=` on `GET /manufacturers/{id}/keys`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct KeysAtParams {
+    /// RFC 3339 timestamp; defaults to now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+}
+
+/// The registry's signed statement that a manufacturer is revoked.  It
+/// covers every key of the manufacturer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Revocation {
+    /// The manufacturer's `id`
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub pk_hex: String,
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub revoked_at: String,
+    #[serde(default, deserialize_with = "max_len::<_, DESCRIPTION_MAX>")]
+    pub reason: String,
+    /// Lightning signature by the registry signing key over
+    /// `conduit:revocation:v1:{pk_hex}:{revoked_at}:{reason}`
+    #[serde(deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub signature: String,
+}
+
+/// Every revoked manufacturer key, signed by the registry so devices can
+/// cache it and check it offline against `operator_pubkey`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RevocationList {
+    /// Registry signing key (hex) that signed the list and its entries
+    pub issuer: String,
+    /// Increases with every revocation; a device can ignore lists older
+    /// than the one it holds
+    pub sequence: u64,
+    pub issued_at: String,
+    /// Oldest first
+    pub entries: Vec<Revocation>,
+    /// Lightning signature by `issuer` over
+    /// `conduit:revocations:v1:{issuer}:{sequence}:{issued_at}:{digest}`, where
+    /// `digest` is the hex SHA-256 of the entries' messages joined by `\n`
+    pub signature: String,
+}
+
+/// `?reason=` on `DELETE /manufacturers/{pk_hex}`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct RevokeParams {
+    /// Why the key is revoked, e.g. `key compromised`
+    pub reason: Option<String>,
+}
+
+/// A TEE device key certified by its manufacturer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Device {
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub device_pk: String,
+    /// The issuing manufacturer's key that signed the certificate
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub manufacturer_pk: String,
+    #[serde(default, deserialize_with = "max_len::<_, ALIAS_MAX>")]
+    pub model: String,
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub issued_at: String,
+    /// Lightning signature by the `manufacturer_pk` key over
+    /// `conduit:device:v1:{device_pk}:{manufacturer_pk}:{model}:{issued_at}`
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub manufacturer_signature: String,
+}
+
+/// A registered device and the manufacturer that certified it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceResponse {
+    pub device: Device,
+    pub manufacturer: Manufacturer,
 }
 
-#[derive(Debug, Deserialize)]
+/// Outcome of checking a device certificate against the registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceVerification {
+    pub valid: bool,
+    /// Why the chain does not verify, e.g. `unknown_manufacturer`,
+    /// `invalid_signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The issuing manufacturer, if registered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<Manufacturer>,
+    /// Whether this exact certificate is in the device registry
+    pub registered: bool,
+}
+
+/// A playback device's proof that it runs on certified hardware: its
+/// certificate, plus the verifier's challenge signed with the device key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Attestation {
+    pub device: Device,
+    /// Nonce chosen by the party asking for the proof, e.g. a creator
+    #[serde(deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub challenge: String,
+    /// Lightning signature by `device.device_pk` over
+    /// `conduit:attestation:v1:{device_pk}:{challenge}`
+    #[serde(default, deserialize_with = "max_len::<_, SIGNATURE_MAX>")]
+    pub challenge_signature: String,
+}
+
+/// The registry's short-lived statement that a device passed attestation.
+/// Check `signature` against `issuer`, `issuer` against the
+/// `operator_pubkey` you trust, and `expires_at` against the clock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AttestationToken {
+    /// Registry signing key (hex)
+    pub issuer: String,
+    pub device_pk: String,
+    /// `id` of the manufacturer that certified the device
+    pub manufacturer_id: String,
+    pub challenge: String,
+    pub issued_at: String,
+    pub expires_at: String,
+    /// Lightning signature by `issuer` over
+    /// `conduit:attestation_token:v1:{issuer}:{device_pk}:{manufacturer_id}:{challenge}:{issued_at}:{expires_at}`
+    pub signature: String,
+}
+
+/// Outcome of `POST /attestations/verify`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AttestationVerdict {
+    pub valid: bool,
+    /// Why the attestation failed, e.g. `manufacturer_revoked`,
+    /// `invalid_challenge_signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The manufacturer that certified the device, if registered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<Manufacturer>,
+    /// Present when `valid`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<AttestationToken>,
+}
+
+/// What a blocklist entry matches against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    ContentHash,
+    EncryptedHash,
+    CreatorPubkey,
+    SeederPubkey,
+}
+
+impl BlockKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockKind::ContentHash => "content_hash",
+            BlockKind::EncryptedHash => "encrypted_hash",
+            BlockKind::CreatorPubkey => "creator_pubkey",
+            BlockKind::SeederPubkey => "seeder_pubkey",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "content_hash" => Some(BlockKind::ContentHash),
+            "encrypted_hash" => Some(BlockKind::EncryptedHash),
+            "creator_pubkey" => Some(BlockKind::CreatorPubkey),
+            "seeder_pubkey" => Some(BlockKind::SeederPubkey),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BlockEntry {
+    pub kind: BlockKind,
+    #[serde(deserialize_with = "max_len::<_, PUBKEY_MAX>")]
+    pub value: String,
+    #[serde(default, deserialize_with = "max_len::<_, DESCRIPTION_MAX>")]
+    pub reason: String,
+    #[serde(default, deserialize_with = "max_len::<_, SHORT_MAX>")]
+    pub blocked_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
 pub struct SearchParams {
+    /// Substring match on file_name
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub q: Option<String>,
-    #[serde(rename = "type")]
+    /// File extension, e.g. `mp4`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub content_type: Option<String>,
+    /// Maximum price in sats
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_price: Option<u64>,
+    /// Exact `playback_policy`, e.g. `tee_required`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+}
+
+/// `?limit=&offset=` on paginated (v2) list endpoints.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PageParams {
+    /// Page size (default 50, at most 200)
+    pub limit: Option<u32>,
+    /// Number of items to skip
+    pub offset: Option<u32>,
+}
+
+/// `?on_invalid=` on `POST /admin/import`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ImportParams {
+    /// `fail` (default) aborts on the first invalid record; `skip` leaves
+    /// invalid records out and reports them
+    #[param(inline)]
+    pub on_invalid: Option<OnInvalid>,
+}
+
+/// A `LIMIT`/`OFFSET` window over a result set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl PageParams {
+    pub fn page(&self) -> Page {
+        Page {
+            limit: self
+                .limit
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE),
+            offset: self.offset.unwrap_or(0),
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct DiscoverResponse {
     pub listing: ContentListing,
     pub seeders: Vec<SeederAnnouncement>,
+    /// The manufacturers in `listing.playback_params.allowed_manufacturers`
+    /// as currently registered, including any `revocation`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub manufacturers: Vec<Manufacturer>,
+    /// Live re-encryption proxies for the registry's `pre_scheme`,
+    /// cheapest first; only for listings with a PRE capsule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proxies: Vec<ProxyAnnouncement>,
+}
+
+// ---------------------------------------------------------------------------
+// Response envelopes (documented in the OpenAPI spec)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Items<T> {
+    pub items: Vec<T>,
+}
+
+/// Paginated list envelope (v2).  `next_offset` is absent on the last page.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub limit: u32,
+    pub offset: u32,
+    pub next_offset: Option<u32>,
+}
+
+impl<T> Paged<T> {
+    pub fn new(items: Vec<T>, page: Page) -> Self {
+        let next_offset = (items.len() == page.limit as usize).then(|| page.offset + page.limit);
+        Paged {
+            items,
+            limit: page.limit,
+            offset: page.offset,
+            next_offset,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OkResponse {
+    pub ok: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeletedResponse {
+    pub deleted: usize,
+}
+
+/// v1 error body.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// v2 error body.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorEnvelope {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorDetail {
+    /// Stable machine-readable code, e.g. `not_found`, `invalid_signature`
+    pub code: String,
+    pub message: String,
+    /// Seconds to wait before retrying, on `rate_limited`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
 }
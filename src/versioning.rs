@@ -0,0 +1,154 @@
+//! API versioning for the Conduit Registry.
+//!
+//! * `/api/v1/...` -- the original behaviour, frozen.
+//! * `/api/v2/...` -- paginated list envelopes and structured error codes.
+//! * `/api/...`    -- deprecated aliases for v1, served with `Deprecation`,
+//!   `Sunset` and `Link: rel="successor-version"` headers until the
+//!   configured cutoff, and `410 Gone` afterwards.
+//!
+//! Handlers are shared between versions.  The v2 error envelope is applied
+//! after the fact by `v2_errors`, using the `ErrorCode` extension that
+//! `ApiError` attaches to every error response.
+
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::error::{code_for_status, ApiError, ErrorCode};
+use crate::types::{AppState, ErrorDetail, ErrorEnvelope};
+
+pub const V1_PREFIX: &str = "/api/v1";
+pub const V2_PREFIX: &str = "/api/v2";
+pub const LEGACY_PREFIX: &str = "/api";
+
+/// Error bodies larger than this are passed through unchanged.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// Parse a legacy-API cutoff: RFC 3339 timestamp or a bare `YYYY-MM-DD`
+/// (midnight UTC).
+pub fn parse_cutoff(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("invalid cutoff '{}': expected YYYY-MM-DD or RFC 3339", s))
+}
+
+fn http_date(dt: &DateTime<Utc>) -> String {
+    dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Middleware for the unprefixed `/api/...` aliases of v1.
+pub async fn legacy_alias(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    let successor = format!(
+        "{}{}",
+        V1_PREFIX,
+        path.strip_prefix(LEGACY_PREFIX).unwrap_or(path)
+    );
+    let link = format!("<{}>; rel=\"successor-version\"", successor);
+    let legacy_cutoff = state.config.read().unwrap().server.legacy_api_cutoff;
+
+    if let Some(cutoff) = legacy_cutoff {
+        if Utc::now() >= cutoff {
+            let mut res = ApiError::new(
+                StatusCode::GONE,
+                "gone",
+                format!(
+                    "Unversioned API retired on {}; use {}",
+                    cutoff.to_rfc3339(),
+                    successor
+                ),
+            )
+            .into_response();
+            if let Ok(v) = HeaderValue::from_str(&link) {
+                res.headers_mut().insert(header::LINK, v);
+            }
+            return res;
+        }
+    }
+
+    let mut res = next.run(req).await;
+    let headers = res.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Some(cutoff) = legacy_cutoff {
+        if let Ok(v) = HeaderValue::from_str(&http_date(&cutoff)) {
+            headers.insert("sunset", v);
+        }
+    }
+    if let Ok(v) = HeaderValue::from_str(&link) {
+        headers.insert(header::LINK, v);
+    }
+    res
+}
+
+/// Middleware: rewrite error responses under `/api/v2` into the structured
+/// `{"error": {"code", "message"}}` envelope.
+pub async fn v2_errors(req: Request, next: Next) -> Response {
+    if !req.uri().path().starts_with(V2_PREFIX) {
+        return next.run(req).await;
+    }
+    let res = next.run(req).await;
+    let status = res.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return res;
+    }
+
+    let code = res
+        .extensions()
+        .get::<ErrorCode>()
+        .map(|c| c.0)
+        .unwrap_or_else(|| code_for_status(status));
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_ERROR_BODY).await else {
+        return (status, "error body too large").into_response();
+    };
+
+    // v1 bodies are {"error": "...", ...}; extractor rejections are plain text.
+    let (message, retry_after_secs) = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(v) => (
+            v["error"].as_str().unwrap_or_default().to_string(),
+            v["retry_after_secs"].as_u64(),
+        ),
+        Err(_) => (String::from_utf8_lossy(&bytes).trim().to_string(), None),
+    };
+
+    let envelope = ErrorEnvelope {
+        error: ErrorDetail {
+            code: code.to_string(),
+            message,
+            retry_after_secs,
+        },
+    };
+    let body = serde_json::to_vec(&envelope).unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_and_timestamps() {
+        let d = parse_cutoff("2027-01-01").unwrap();
+        assert_eq!(d.to_rfc3339(), "2027-01-01T00:00:00+00:00");
+        let t = parse_cutoff("2027-01-01T12:00:00+02:00").unwrap();
+        assert_eq!(t.to_rfc3339(), "2027-01-01T10:00:00+00:00");
+        assert!(parse_cutoff("next year").is_err());
+    }
+
+    #[test]
+    fn sunset_uses_http_date() {
+        let d = parse_cutoff("2027-01-01").unwrap();
+        assert_eq!(http_date(&d), "Fri, 01 Jan 2027 00:00:00 GMT");
+    }
+}
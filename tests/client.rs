@@ -0,0 +1,686 @@
+//! `RegistryClient` against the real router, served in-process.
+
+#![cfg(feature = "client")]
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use conduit_registry::client::{ClientError, RegistryClient, RetryPolicy};
+use conduit_registry::config::Config;
+use conduit_registry::db::init_db;
+use conduit_registry::limits::BodyLimits;
+use conduit_registry::pre::DEFAULT_PRE_SCHEME;
+use conduit_registry::proxies;
+use conduit_registry::ratelimit::{Limit, RateLimits, RouteClass};
+use conduit_registry::signature::{
+    pubkey_hex, sign_attestation, sign_buyer_key, sign_device, sign_listing, sign_manufacturer,
+    sign_proxy, sign_rotation, verify_attestation_token, verify_buyer_key,
+};
+use conduit_registry::types::{
+    AppState, Attestation, BuyerKey, ContentListing, Device, Manufacturer, ManufacturerKey, Page,
+    ProxyAnnouncement, Revocation, SearchParams, SeederAnnouncement,
+};
+use rusqlite::Connection;
+use secp256k1::SecretKey;
+
+/// Serve a fresh in-memory registry on an ephemeral port.
+async fn spawn(limits: RateLimits) -> (String, AppState) {
+    spawn_with(Config {
+        rate_limits: limits,
+        ..Config::default()
+    })
+    .await
+}
+
+async fn spawn_with(config: Config) -> (String, AppState) {
+    let conn = Connection::open_in_memory().unwrap();
+    init_db(&conn);
+    let state = AppState::new(conn, config);
+    let app = conduit_registry::app(state.clone(), BodyLimits::default());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    (format!("http://{}", addr), state)
+}
+
+fn unlimited() -> RateLimits {
+    let off: Limit = "0".parse().unwrap();
+    RateLimits {
+        read: off,
+        write: off,
+        verify: off,
+    }
+}
+
+fn listing(n: u32) -> ContentListing {
+    ContentListing {
+        content_hash: format!("{:064x}", n),
+        encrypted_hash: format!("{:064x}", n + 1000),
+        file_name: format!("lecture-{}.mp4", n),
+        size_bytes: 1024,
+        price_sats: 100 * n as u64,
+        chunk_size: 256,
+        chunk_count: 4,
+        plaintext_root: "aa".repeat(32),
+        encrypted_root: "bb".repeat(32),
+        creator_pubkey: format!("02{}", "11".repeat(32)),
+        creator_address: "127.0.0.1:9735".into(),
+        creator_ln_address: "creator@example.com".into(),
+        creator_alias: "creator".into(),
+        registered_at: "2026-01-01T00:00:00Z".into(),
+        pre_c1_hex: String::new(),
+        pre_c2_hex: String::new(),
+        pre_pk_creator_hex: String::new(),
+        playback_policy: "open".into(),
+        playback_params: Default::default(),
+        creator_signature: String::new(),
+    }
+}
+
+/// A listing carrying a valid AFGH capsule, so proxies are offered for it.
+fn pre_listing(n: u32) -> ContentListing {
+    ContentListing {
+        pre_c1_hex: g1(21),
+        pre_c2_hex: "01".repeat(576),
+        pre_pk_creator_hex: g1(7),
+        ..listing(n)
+    }
+}
+
+/// Compressed hex of the G1 point `n·G`.
+fn g1(n: u64) -> String {
+    hex::encode(G1Affine::from(G1Projective::generator() * Scalar::from(n)).to_compressed())
+}
+
+/// A deterministic secp256k1 key filled with `byte`.
+fn secret(byte: u8) -> SecretKey {
+    SecretKey::from_slice(&[byte; 32]).unwrap()
+}
+
+/// An unsigned device certificate for `device_pk`.
+fn device(device_pk: String) -> Device {
+    Device {
+        device_pk,
+        manufacturer_pk: String::new(),
+        model: "Player 1".into(),
+        issued_at: "2026-02-01T00:00:00Z".into(),
+        manufacturer_signature: String::new(),
+    }
+}
+
+/// Insert directly, skipping signature checks and rate limits.
+fn seed_listing(state: &AppState, l: &ContentListing) {
+    state
+        .db
+        .lock()
+        .unwrap()
+        .execute(
+            "INSERT INTO listings (content_hash, encrypted_hash, file_name, size_bytes, price_sats,
+             chunk_size, chunk_count, plaintext_root, encrypted_root, creator_pubkey,
+             creator_address, creator_ln_address, creator_alias, registered_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            rusqlite::params![
+                l.content_hash,
+                l.encrypted_hash,
+                l.file_name,
+                l.size_bytes,
+                l.price_sats,
+                l.chunk_size,
+                l.chunk_count,
+                l.plaintext_root,
+                l.encrypted_root,
+                l.creator_pubkey,
+                l.creator_address,
+                l.creator_ln_address,
+                l.creator_alias,
+                l.registered_at,
+            ],
+        )
+        .unwrap();
+}
+
+#[tokio::test]
+async fn search_pages_and_filters() {
+    let (base, state) = spawn(unlimited()).await;
+    for n in 1..=3 {
+        seed_listing(&state, &listing(n));
+    }
+    let client = RegistryClient::new(base);
+
+    let first = client
+        .search(
+            &SearchParams::default(),
+            Some(Page {
+                limit: 2,
+                offset: 0,
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.items.len(), 2);
+    assert_eq!(first.next_offset, Some(2));
+
+    let cheap = SearchParams {
+        max_price: Some(150),
+        ..Default::default()
+    };
+    let page = client.search(&cheap, None).await.unwrap();
+    assert_eq!(page.items, vec![listing(1)]);
+    assert_eq!(page.next_offset, None);
+}
+
+#[tokio::test]
+async fn announce_then_discover() {
+    let (base, state) = spawn(unlimited()).await;
+    let l = listing(7);
+    seed_listing(&state, &l);
+    let client = RegistryClient::new(base);
+
+    let announcement = SeederAnnouncement {
+        encrypted_hash: l.encrypted_hash.clone(),
+        seeder_pubkey: format!("03{}", "22".repeat(32)),
+        seeder_address: "10.0.0.2:9735".into(),
+        seeder_ln_address: "seeder@example.com".into(),
+        seeder_alias: "seeder".into(),
+        transport_price: 5,
+        chunk_count: 4,
+        announced_at: "2026-01-02T00:00:00Z".into(),
+    };
+    client.announce(&announcement).await.unwrap();
+
+    let found = client.discover(&l.content_hash).await.unwrap();
+    assert_eq!(found.listing, l);
+    assert_eq!(found.seeders, vec![announcement]);
+    assert_eq!(client.listing(&l.content_hash).await.unwrap(), l);
+}
+
+#[tokio::test]
+async fn manufacturer_lookup() {
+    let (base, state) = spawn(unlimited()).await;
+    state
+        .db
+        .lock()
+        .unwrap()
+        .execute(
+            "INSERT INTO manufacturers (pk_hex, name, registered_at) VALUES ('ab01', 'Acme', '2026-01-01')",
+            [],
+        )
+        .unwrap();
+    let client = RegistryClient::new(base);
+
+    let mfr = client.manufacturer("ab01").await.unwrap();
+    assert_eq!(mfr.name, "Acme");
+    assert_eq!(client.manufacturers(None).await.unwrap().items, vec![mfr]);
+
+    let err = client.manufacturer("ffff").await.unwrap_err();
+    assert!(err.is_not_found(), "{}", err);
+}
+
+/// Register "Acme" under `sk` directly in the database.
+fn register_acme(state: &AppState, sk: &SecretKey) -> Manufacturer {
+    let mut mfr = Manufacturer {
+        id: String::new(),
+        pk_hex: String::new(),
+        name: "Acme".into(),
+        description: String::new(),
+        website: String::new(),
+        registered_at: "2026-01-01T00:00:00Z".into(),
+        signature: String::new(),
+        revocation: None,
+    };
+    sign_manufacturer(sk, &mut mfr);
+    mfr.id = mfr.pk_hex.clone();
+    conduit_registry::db::insert_manufacturer(&state.db.lock().unwrap(), &mfr).unwrap();
+    mfr
+}
+
+#[tokio::test]
+async fn device_certificates_chain_to_manufacturer() {
+    let (base, state) = spawn(unlimited()).await;
+    let acme = secret(5);
+    let mfr = register_acme(&state, &acme);
+    let client = RegistryClient::new(base);
+
+    let mut device = device(pubkey_hex(&secret(30)));
+    sign_device(&acme, &mut device);
+    let check = client.verify_device(&device).await.unwrap();
+    assert!(check.valid && !check.registered);
+
+    client.register_device(&device).await.unwrap();
+    let found = client.device(&device.device_pk).await.unwrap();
+    assert_eq!((found.device, found.manufacturer), (device.clone(), mfr));
+
+    device.model = "Player 2".into();
+    let check = client.verify_device(&device).await.unwrap();
+    assert_eq!(check.reason.as_deref(), Some("invalid_signature"));
+    match client.register_device(&device).await.unwrap_err() {
+        ClientError::Api { code, .. } => assert_eq!(code, "invalid_signature"),
+        e => panic!("unexpected error: {}", e),
+    }
+
+    // A multi-byte key is refused without panicking while the database is locked
+    let malformed = Device {
+        device_pk: format!("a{}", "é".repeat(20)),
+        ..device.clone()
+    };
+    let err = client.register_device(&malformed).await.unwrap_err();
+    assert_eq!(err.code(), Some("invalid_pubkey"));
+    assert!(client.device(&device.device_pk).await.is_ok());
+}
+
+#[tokio::test]
+async fn rotated_keys_keep_old_certificates_valid() {
+    let (base, state) = spawn(unlimited()).await;
+    let old = secret(5);
+    let new = secret(6);
+    let mfr = register_acme(&state, &old);
+    let client = RegistryClient::new(base);
+
+    let mut before = device(pubkey_hex(&secret(30)));
+    sign_device(&old, &mut before);
+
+    let mut key = ManufacturerKey {
+        manufacturer_id: mfr.id.clone(),
+        pk_hex: pubkey_hex(&new),
+        not_before: chrono::Utc::now().to_rfc3339(),
+        not_after: String::new(),
+        retired_at: String::new(),
+        signed_by: String::new(),
+        signature: String::new(),
+    };
+    sign_rotation(&old, &mut key);
+    client.rotate_key(&key).await.unwrap();
+
+    // The old key is retired from now on, but what it signed earlier stands
+    assert_eq!(
+        client.manufacturer_keys(&mfr.id, None).await.unwrap(),
+        [key.clone()]
+    );
+    let then = client
+        .manufacturer_keys(&mfr.id, Some("2026-02-01T00:00:00Z"))
+        .await
+        .unwrap();
+    assert_eq!(
+        then.iter().map(|k| &k.pk_hex).collect::<Vec<_>>(),
+        [&mfr.pk_hex]
+    );
+    assert!(client.verify_device(&before).await.unwrap().valid);
+
+    let mut after = Device {
+        device_pk: pubkey_hex(&secret(31)),
+        issued_at: chrono::Utc::now().to_rfc3339(),
+        ..before.clone()
+    };
+    sign_device(&new, &mut after);
+    client.register_device(&after).await.unwrap();
+    assert_eq!(
+        client.device(&after.device_pk).await.unwrap().manufacturer,
+        mfr
+    );
+    sign_device(&old, &mut after);
+    let check = client.verify_device(&after).await.unwrap();
+    assert_eq!(check.reason.as_deref(), Some("outside_key_window"));
+
+    // Only a current key may rotate
+    let mut again = ManufacturerKey {
+        pk_hex: pubkey_hex(&secret(7)),
+        ..key
+    };
+    sign_rotation(&old, &mut again);
+    assert_eq!(
+        client.rotate_key(&again).await.unwrap_err().code(),
+        Some("signer_not_current")
+    );
+}
+
+#[tokio::test]
+async fn attestation_yields_registry_signed_token() {
+    let registry = secret(8);
+    let mut config = Config {
+        rate_limits: unlimited(),
+        ..Config::default()
+    };
+    config.registry.signing_key = Some(hex::encode(registry.secret_bytes()));
+    let (base, state) = spawn_with(config).await;
+    let acme = secret(5);
+    let mfr = register_acme(&state, &acme);
+    let client = RegistryClient::new(base);
+
+    let device_key = secret(6);
+    let mut device = device(pubkey_hex(&device_key));
+    sign_device(&acme, &mut device);
+    let mut att = Attestation {
+        device,
+        challenge: "creator-nonce".into(),
+        challenge_signature: String::new(),
+    };
+    sign_attestation(&device_key, &mut att);
+
+    let verdict = client.attest(&att).await.unwrap();
+    assert!(verdict.valid);
+    let token = verdict.token.unwrap();
+    assert_eq!(
+        token.issuer,
+        client.info().await.unwrap().operator_pubkey.unwrap()
+    );
+    assert_eq!(
+        (token.manufacturer_id.as_str(), token.challenge.as_str()),
+        (mfr.id.as_str(), "creator-nonce")
+    );
+    assert!(verify_attestation_token(&token));
+
+    att.challenge = "another-nonce".into();
+    let verdict = client.attest(&att).await.unwrap();
+    assert_eq!(
+        verdict.reason.as_deref(),
+        Some("invalid_challenge_signature")
+    );
+    assert!(verdict.token.is_none());
+}
+
+#[tokio::test]
+async fn info_describes_capabilities() {
+    let (base, _) = spawn(unlimited()).await;
+    let info = RegistryClient::new(base).info().await.unwrap();
+    assert_eq!(info.software, "conduit-registry");
+    assert!(info.api_versions.iter().any(|v| v == "v2"));
+    assert!(info.has_module("manufacturers"));
+    assert_eq!(info.limits.rate_limits.read.per_minute, 0);
+}
+
+#[tokio::test]
+async fn publish_signed_listing() {
+    let (base, _) = spawn(unlimited()).await;
+    let client = RegistryClient::new(base);
+
+    let sk = secret(9);
+    let mut l = listing(4);
+    sign_listing(&sk, &mut l);
+    client.publish(&l).await.unwrap();
+    assert_eq!(client.listing(&l.content_hash).await.unwrap(), l);
+}
+
+#[tokio::test]
+async fn playback_policies_are_signed_and_searchable() {
+    let (base, _) = spawn(unlimited()).await;
+    let client = RegistryClient::new(base);
+    let sk = secret(9);
+
+    let mut tee = listing(5);
+    tee.playback_policy = "tee_required".into();
+    tee.playback_params.max_devices = Some(2);
+    sign_listing(&sk, &mut tee);
+    client.publish(&tee).await.unwrap();
+    let mut open = listing(6);
+    sign_listing(&sk, &mut open);
+    client.publish(&open).await.unwrap();
+
+    let params = SearchParams {
+        policy: Some("tee_required".into()),
+        ..Default::default()
+    };
+    let found = client.search(&params, None).await.unwrap();
+    assert_eq!(found.items, [tee]);
+
+    let mut unknown = listing(7);
+    unknown.playback_policy = "drm".into();
+    sign_listing(&sk, &mut unknown);
+    assert_eq!(
+        client.publish(&unknown).await.unwrap_err().code(),
+        Some("unknown_policy")
+    );
+    let mut misfit = listing(8);
+    misfit.playback_params.max_devices = Some(2);
+    sign_listing(&sk, &mut misfit);
+    assert_eq!(
+        client.publish(&misfit).await.unwrap_err().code(),
+        Some("invalid_policy_params")
+    );
+}
+
+#[tokio::test]
+async fn allowed_manufacturers_resolve_in_discover() {
+    let (base, state) = spawn(unlimited()).await;
+    let client = RegistryClient::new(base);
+    let creator = secret(9);
+    let acme = register_acme(&state, &secret(5));
+
+    let mut tee = listing(5);
+    tee.playback_policy = "tee_required".into();
+    tee.playback_params.allowed_manufacturers = vec![acme.id.clone()];
+    sign_listing(&creator, &mut tee);
+    client.publish(&tee).await.unwrap();
+    let found = client.discover(&tee.content_hash).await.unwrap();
+    assert_eq!(found.manufacturers, std::slice::from_ref(&acme));
+
+    let mut stranger = listing(6);
+    stranger.playback_policy = "tee_required".into();
+    stranger.playback_params.allowed_manufacturers = vec![pubkey_hex(&secret(6))];
+    sign_listing(&creator, &mut stranger);
+    assert_eq!(
+        client.publish(&stranger).await.unwrap_err().code(),
+        Some("unknown_manufacturer")
+    );
+
+    let revocation = Revocation {
+        pk_hex: acme.id.clone(),
+        revoked_at: "2026-03-01T00:00:00Z".into(),
+        reason: String::new(),
+        signature: String::new(),
+    };
+    conduit_registry::db::revoke_manufacturer(&state.db.lock().unwrap(), &revocation).unwrap();
+    let mut late = listing(7);
+    late.playback_policy = "tee_required".into();
+    late.playback_params.allowed_manufacturers = vec![acme.id.clone()];
+    sign_listing(&creator, &mut late);
+    assert_eq!(
+        client.publish(&late).await.unwrap_err().code(),
+        Some("manufacturer_revoked")
+    );
+
+    // Listings published earlier show the revocation to buyers
+    let found = client.discover(&tee.content_hash).await.unwrap();
+    assert_eq!(found.manufacturers[0].revocation, Some(revocation));
+}
+
+#[tokio::test]
+async fn buyer_keys_rotate_and_keep_history() {
+    let (base, _) = spawn(unlimited()).await;
+    let client = RegistryClient::new(base);
+    let node = secret(4);
+
+    let mut first = BuyerKey {
+        node_pubkey: String::new(),
+        scheme: DEFAULT_PRE_SCHEME.into(),
+        pre_pk_hex: g1(11),
+        published_at: "2026-05-01T00:00:00Z".into(),
+        superseded_at: String::new(),
+        signature: String::new(),
+    };
+    sign_buyer_key(&node, &mut first);
+    client.publish_buyer_key(&first).await.unwrap();
+    let mut second = BuyerKey {
+        pre_pk_hex: g1(12),
+        published_at: "2026-06-01T00:00:00Z".into(),
+        ..first.clone()
+    };
+    sign_buyer_key(&node, &mut second);
+    client.publish_buyer_key(&second).await.unwrap();
+    assert_eq!(
+        client.publish_buyer_key(&first).await.unwrap_err().code(),
+        Some("stale_key")
+    );
+
+    let current = client.buyer_key(&pubkey_hex(&node)).await.unwrap();
+    assert!(verify_buyer_key(&current));
+    assert_eq!(current, second);
+    let history = client.buyer_key_history(&pubkey_hex(&node)).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+        (
+            history[1].pre_pk_hex.as_str(),
+            history[1].superseded_at.as_str()
+        ),
+        (first.pre_pk_hex.as_str(), "2026-06-01T00:00:00Z")
+    );
+
+    let stranger = pubkey_hex(&secret(7));
+    assert_eq!(
+        client.buyer_key(&stranger).await.unwrap_err().code(),
+        Some("not_found")
+    );
+}
+
+#[tokio::test]
+async fn proxies_are_offered_for_pre_listings() {
+    let (base, _) = spawn(unlimited()).await;
+    let client = RegistryClient::new(base);
+    let creator = secret(9);
+
+    let mut proxy = ProxyAnnouncement {
+        proxy_pubkey: String::new(),
+        proxy_address: "10.0.0.3:9735".into(),
+        proxy_ln_address: "proxy@example.com".into(),
+        proxy_alias: "proxy".into(),
+        fee_sats: 3,
+        schemes: vec![DEFAULT_PRE_SCHEME.into()],
+        ttl_secs: 600,
+        announced_at: proxies::timestamp(chrono::Utc::now()),
+        signature: String::new(),
+    };
+    sign_proxy(&secret(8), &mut proxy);
+    client.announce_proxy(&proxy).await.unwrap();
+    let live = client
+        .proxies(Some(DEFAULT_PRE_SCHEME), None)
+        .await
+        .unwrap();
+    assert_eq!(live.items, std::slice::from_ref(&proxy));
+    assert!(client
+        .proxies(Some("umbral"), None)
+        .await
+        .unwrap()
+        .items
+        .is_empty());
+
+    let mut plain = listing(1);
+    sign_listing(&creator, &mut plain);
+    client.publish(&plain).await.unwrap();
+    assert!(client
+        .discover(&plain.content_hash)
+        .await
+        .unwrap()
+        .proxies
+        .is_empty());
+
+    let mut pre = pre_listing(2);
+    sign_listing(&creator, &mut pre);
+    client.publish(&pre).await.unwrap();
+    assert_eq!(
+        client.discover(&pre.content_hash).await.unwrap().proxies,
+        [proxy]
+    );
+}
+
+#[tokio::test]
+async fn api_errors_are_typed() {
+    let (base, _) = spawn(unlimited()).await;
+    let client = RegistryClient::new(base);
+
+    match client.publish(&listing(1)).await.unwrap_err() {
+        ClientError::Api { status, code, .. } => {
+            assert_eq!(status.as_u16(), 400);
+            assert_eq!(code, "signature_required");
+        }
+        other => panic!("expected an API error, got {:?}", other),
+    }
+
+    let mut forged = listing(1);
+    forged.creator_signature = "d".repeat(104);
+    let err = client.publish(&forged).await.unwrap_err();
+    assert_eq!(err.code(), Some("invalid_signature"));
+
+    let err = client.discover("missing").await.unwrap_err();
+    assert_eq!(err.code(), Some("not_found"));
+}
+
+#[tokio::test]
+async fn retries_after_rate_limit() {
+    // One request per second, no burst beyond the first.
+    let (base, state) = spawn(RateLimits {
+        read: "60,1".parse().unwrap(),
+        ..unlimited()
+    })
+    .await;
+    seed_listing(&state, &listing(1));
+
+    let impatient = RegistryClient::new(base.clone()).with_retry(RetryPolicy::none());
+    impatient.listing(&listing(1).content_hash).await.unwrap();
+    match impatient
+        .listing(&listing(1).content_hash)
+        .await
+        .unwrap_err()
+    {
+        ClientError::Api {
+            code, retry_after, ..
+        } => {
+            assert_eq!(code, "rate_limited");
+            assert!(retry_after.is_some());
+        }
+        other => panic!("expected rate_limited, got {:?}", other),
+    }
+
+    let patient = RegistryClient::new(base).with_retry(RetryPolicy {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(2),
+    });
+    assert_eq!(
+        patient.listing(&listing(1).content_hash).await.unwrap(),
+        listing(1)
+    );
+}
+
+#[tokio::test]
+async fn forged_requests_do_not_spend_the_pubkey_bucket() {
+    let (base, state) = spawn(RateLimits {
+        verify: "60,3".parse().unwrap(),
+        ..unlimited()
+    })
+    .await;
+    let client = RegistryClient::new(base).with_retry(RetryPolicy::none());
+    let victim = secret(9);
+
+    let mut forged = listing(1);
+    forged.creator_pubkey = pubkey_hex(&victim);
+    forged.creator_signature = "d".repeat(104);
+    for _ in 0..2 {
+        assert_eq!(
+            client.publish(&forged).await.unwrap_err().code(),
+            Some("invalid_signature")
+        );
+    }
+    // The victim's bucket still holds its whole burst
+    let pk_key = format!("pk:{}", pubkey_hex(&victim));
+    for _ in 0..3 {
+        assert!(state.limiter.check(RouteClass::Verify, &pk_key).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn transport_errors_surface_after_retries() {
+    let client = RegistryClient::new("http://127.0.0.1:1").with_retry(RetryPolicy {
+        max_retries: 1,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(10),
+    });
+    let err = client.listing("x").await.unwrap_err();
+    assert!(matches!(err, ClientError::Http(_)), "{:?}", err);
+}